@@ -1,6 +1,7 @@
 use anyhow::Result;
-use reqwest::Client;
+use reqwest::{Client, Response, StatusCode};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use crate::{AuthStatus, Chat, Message};
 
@@ -37,8 +38,14 @@ pub struct CodeResponse {
 #[derive(Debug, Serialize, Deserialize)]
 struct ChatsResponse {
     chats: Vec<Chat>,
+    // Курсор следующей страницы; нет - чаты закончились (или бэкенд не делит список на страницы)
+    #[serde(default)]
+    next_cursor: Option<String>,
 }
 
+// Сколько страниц чатов запрашивать подряд, чтобы не зациклиться на курсоре бэкенда
+const MAX_CHAT_PAGES: usize = 20;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct MessagesResponse {
     messages: Vec<Message>,
@@ -47,6 +54,25 @@ struct MessagesResponse {
 #[derive(Debug, Serialize, Deserialize)]
 struct SendMessageRequest {
     text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ScheduleMessageRequest {
+    text: String,
+    send_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TranslateRequest {
+    text: String,
+    to: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TranslateResponse {
+    text: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,12 +82,146 @@ pub struct SendMessageResponse {
     pub message_id: Option<i32>,
 }
 
+// Файл лежит на той же машине, что и бэкенд, поэтому передается путь, а не содержимое
+#[derive(Debug, Serialize, Deserialize)]
+struct SendFileRequest {
+    path: String,
+    r#type: String,
+    duration: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SetOnlineRequest {
+    online: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MarkReadRequest {
+    max_id: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SetReactionRequest {
+    emoji: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ReportMessageRequest {
+    reason: String,
+}
+
+// Общий ответ для простых действий без данных
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActionResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+// Изменения с момента since из long-poll запроса
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdatesResponse {
+    pub last_update_id: i64,
+    #[serde(default)]
+    pub new_messages: Vec<Message>,
+    #[serde(default)]
+    pub unread: Vec<UnreadUpdate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnreadUpdate {
+    pub chat_id: i64,
+    pub unread: i32,
+}
+
+// Кто прочитал сообщение; total может быть больше списка, если бэкенд отдал не всех
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadersResponse {
+    pub readers: Vec<Reader>,
+    pub total: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reader {
+    pub id: i64,
+    pub name: String,
+    pub read_at: Option<String>,
+}
+
+// Подробности чата: для личных чатов - bio собеседника, для групп и каналов - описание
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatDetails {
+    pub id: i64,
+    pub title: String,
+    pub r#type: String,
+    pub username: Option<String>,
+    pub description: Option<String>,
+    pub bio: Option<String>,
+    pub members_count: Option<i32>,
+}
+
+// Ссылка-приглашение; нет - у чата ее нет (например, закрытая группа без ссылки)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct InviteLinkResponse {
+    link: Option<String>,
+}
+
+// Аккаунт, под которым выполнен вход
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Me {
+    pub id: i64,
+    pub name: String,
+    pub username: Option<String>,
+    pub phone: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct ErrorResponse {
     error: String,
     code: i32,
 }
 
+// Ошибки, на которые клиент реагирует особым образом, а не показывает пользователю
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("rate limited, retry after {}s", .0.as_secs())]
+    RateLimited(Duration),
+    #[error("endpoint is not supported by the backend")]
+    Unsupported,
+    #[error("not enough rights for this action")]
+    Forbidden,
+}
+
+impl ApiError {
+    // Сколько ждать перед повтором, если бэкенд ответил 429
+    pub fn retry_after(error: &anyhow::Error) -> Option<Duration> {
+        match error.downcast_ref::<ApiError>() {
+            Some(ApiError::RateLimited(delay)) => Some(*delay),
+            _ => None,
+        }
+    }
+}
+
+// Без Retry-After ждем столько
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+async fn error_from_response(response: Response) -> anyhow::Error {
+    if response.status() == StatusCode::TOO_MANY_REQUESTS {
+        let delay = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_RETRY_AFTER);
+        return ApiError::RateLimited(delay).into();
+    }
+
+    match response.json::<ErrorResponse>().await {
+        Ok(error) => anyhow::anyhow!("API error: {}", error.error),
+        Err(e) => e.into(),
+    }
+}
+
 impl ApiClient {
     pub fn new(base_url: String) -> Self {
         Self {
@@ -78,8 +238,7 @@ impl ApiClient {
             let auth_status: AuthStatus = response.json().await?;
             Ok(auth_status)
         } else {
-            let error: ErrorResponse = response.json().await?;
-            Err(anyhow::anyhow!("API error: {}", error.error))
+            Err(error_from_response(response).await)
         }
     }
 
@@ -99,8 +258,7 @@ impl ApiClient {
             let phone_response: PhoneResponse = response.json().await?;
             Ok(phone_response)
         } else {
-            let error: ErrorResponse = response.json().await?;
-            Err(anyhow::anyhow!("API error: {}", error.error))
+            Err(error_from_response(response).await)
         }
     }
 
@@ -120,29 +278,100 @@ impl ApiClient {
             let code_response: CodeResponse = response.json().await?;
             Ok(code_response)
         } else {
-            let error: ErrorResponse = response.json().await?;
-            Err(anyhow::anyhow!("API error: {}", error.error))
+            Err(error_from_response(response).await)
         }
     }
 
-    pub async fn get_chats(&self) -> Result<Vec<Chat>> {
+    // Загружаем чаты, проходя по страницам, пока есть next_cursor; limit - сколько чатов всего
+    pub async fn get_chats(&self, limit: Option<i32>) -> Result<Vec<Chat>> {
+        let mut chats: Vec<Chat> = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..MAX_CHAT_PAGES {
+            let page = self.get_chats_page(limit.map(|l| l - chats.len() as i32), cursor.as_deref()).await?;
+            for chat in page.chats {
+                // Список мог сдвинуться между страницами - не дублируем чаты
+                if !chats.iter().any(|c| c.id == chat.id) {
+                    chats.push(chat);
+                }
+            }
+            cursor = page.next_cursor.filter(|c| !c.is_empty());
+            let enough = limit.is_some_and(|l| chats.len() as i32 >= l);
+            if cursor.is_none() || enough {
+                break;
+            }
+        }
+        if cursor.is_some() && limit.is_none() {
+            log::warn!("Загружено {} страниц чатов, остальные пропущены", MAX_CHAT_PAGES);
+        }
+
+        if let Some(limit) = limit {
+            chats.truncate(limit.max(0) as usize);
+        }
+        Ok(chats)
+    }
+
+    async fn get_chats_page(&self, limit: Option<i32>, cursor: Option<&str>) -> Result<ChatsResponse> {
         let url = format!("{}/api/chats", self.base_url);
+        let mut query: Vec<(&str, String)> = Vec::new();
+        if let Some(limit) = limit {
+            query.push(("limit", limit.to_string()));
+        }
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor.to_string()));
+        }
+
+        let response = self.client.get(&url).query(&query).send().await?;
+
+        if response.status().is_success() {
+            Ok(response.json().await?)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    pub async fn get_chat_details(&self, chat_id: i64) -> Result<ChatDetails> {
+        let url = format!("{}/api/chats/{}", self.base_url, chat_id);
         let response = self.client.get(&url).send().await?;
-        
+
+        if response.status().is_success() {
+            let details: ChatDetails = response.json().await?;
+            Ok(details)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    // Ссылка-приглашение в группу или канал; 403 - мы не администратор
+    pub async fn get_invite_link(&self, chat_id: i64) -> Result<Option<String>> {
+        let url = format!("{}/api/chats/{}/invite", self.base_url, chat_id);
+        let response = self.client.get(&url).send().await?;
+
         if response.status().is_success() {
-            let chats_response: ChatsResponse = response.json().await?;
-            Ok(chats_response.chats)
+            let invite: InviteLinkResponse = response.json().await?;
+            Ok(invite.link.filter(|link| !link.is_empty()))
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ApiError::Unsupported.into())
+        } else if response.status() == StatusCode::FORBIDDEN {
+            Err(ApiError::Forbidden.into())
         } else {
-            let error: ErrorResponse = response.json().await?;
-            Err(anyhow::anyhow!("API error: {}", error.error))
+            Err(error_from_response(response).await)
         }
     }
 
-    pub async fn get_messages(&self, chat_id: i64, limit: Option<i32>) -> Result<Vec<Message>> {
+    // skip_download - типы медиа, которые бэкенд не скачивает заранее (auto_download)
+    pub async fn get_messages(&self, chat_id: i64, limit: Option<i32>, skip_download: &[String]) -> Result<Vec<Message>> {
         let mut url = format!("{}/api/chats/{}/messages", self.base_url, chat_id);
         
+        let mut params = Vec::new();
         if let Some(limit) = limit {
-            url = format!("{}?limit={}", url, limit);
+            params.push(format!("limit={}", limit));
+        }
+        if !skip_download.is_empty() {
+            params.push(format!("skip_download={}", skip_download.join(",")));
+        }
+        if !params.is_empty() {
+            url = format!("{}?{}", url, params.join("&"));
         }
         
         let response = self.client.get(&url).send().await?;
@@ -151,15 +380,56 @@ impl ApiClient {
             let messages_response: MessagesResponse = response.json().await?;
             Ok(messages_response.messages)
         } else {
-            let error: ErrorResponse = response.json().await?;
-            Err(anyhow::anyhow!("API error: {}", error.error))
+            Err(error_from_response(response).await)
+        }
+    }
+
+    // Поиск сообщений по всем чатам
+    pub async fn search_messages(&self, query: &str) -> Result<Vec<Message>> {
+        let url = format!("{}/api/search/messages", self.base_url);
+        let response = self.client.get(&url).query(&[("q", query)]).send().await?;
+
+        if response.status().is_success() {
+            let messages_response: MessagesResponse = response.json().await?;
+            Ok(messages_response.messages)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    // Long-poll: бэкенд держит запрос до появления изменений или до истечения timeout_secs
+    pub async fn poll_updates(&self, since: i64, timeout_secs: u64) -> Result<UpdatesResponse> {
+        let url = format!("{}/api/updates?since={}&timeout={}", self.base_url, since, timeout_secs);
+        let response = self.client
+            .get(&url)
+            .timeout(Duration::from_secs(timeout_secs + 10))
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let updates: UpdatesResponse = response.json().await?;
+            Ok(updates)
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ApiError::Unsupported.into())
+        } else {
+            Err(error_from_response(response).await)
         }
     }
 
     pub async fn send_message(&self, chat_id: i64, text: &str) -> Result<SendMessageResponse> {
+        self.post_message(chat_id, text, None).await
+    }
+
+    // Ответ на сообщение reply_to_message_id того же чата
+    pub async fn reply_message(&self, chat_id: i64, reply_to_message_id: i32, text: &str) -> Result<SendMessageResponse> {
+        self.post_message(chat_id, text, Some(reply_to_message_id)).await
+    }
+
+    async fn post_message(&self, chat_id: i64, text: &str, reply_to: Option<i32>) -> Result<SendMessageResponse> {
         let url = format!("{}/api/chats/{}/messages", self.base_url, chat_id);
         let request = SendMessageRequest {
             text: text.to_string(),
+            reply_to,
         };
         
         let response = self.client
@@ -172,10 +442,358 @@ impl ApiClient {
             let send_response: SendMessageResponse = response.json().await?;
             Ok(send_response)
         } else {
-            let error: ErrorResponse = response.json().await?;
-            Err(anyhow::anyhow!("API error: {}", error.error))
+            Err(error_from_response(response).await)
+        }
+    }
+
+    // Отправка локального файла; file_type - "voice", "audio", "photo", "video" или "document"
+    pub async fn send_file(&self, chat_id: i64, path: &str, file_type: &str, duration: Option<i32>) -> Result<SendMessageResponse> {
+        let url = format!("{}/api/chats/{}/files", self.base_url, chat_id);
+        let request = SendFileRequest {
+            path: path.to_string(),
+            r#type: file_type.to_string(),
+            duration,
+        };
+
+        let response = self.client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let send_response: SendMessageResponse = response.json().await?;
+            Ok(send_response)
+        } else {
+            Err(error_from_response(response).await)
         }
     }
 
+    // Отложенная отправка: Telegram сам отправит сообщение в момент send_at
+    pub async fn schedule_message(&self, chat_id: i64, text: &str, send_at: chrono::DateTime<chrono::Local>) -> Result<SendMessageResponse> {
+        let url = format!("{}/api/chats/{}/schedule", self.base_url, chat_id);
+        let request = ScheduleMessageRequest {
+            text: text.to_string(),
+            send_at: send_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        };
+
+        let response = self.client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let schedule_response: SendMessageResponse = response.json().await?;
+            Ok(schedule_response)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
 
+    // Перевод текста на язык target_lang (код ISO 639-1)
+    pub async fn translate(&self, text: &str, target_lang: &str) -> Result<String> {
+        let url = format!("{}/api/translate", self.base_url);
+        let request = TranslateRequest {
+            text: text.to_string(),
+            to: target_lang.to_string(),
+        };
+
+        let response = self.client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let translate_response: TranslateResponse = response.json().await?;
+            Ok(translate_response.text)
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ApiError::Unsupported.into())
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    // Файл медиа с бэкенда: kind - "images", "stickers", "videos", "voices" или "audios"
+    pub async fn download_media(&self, kind: &str, media_id: i64) -> Result<Vec<u8>> {
+        let url = format!("{}/api/{}/{}", self.base_url, kind, media_id);
+        let response = self.client.get(&url).send().await?;
+
+        if response.status().is_success() {
+            Ok(response.bytes().await?.to_vec())
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    pub async fn get_me(&self) -> Result<Me> {
+        let url = format!("{}/api/self", self.base_url);
+        let response = self.client.get(&url).send().await?;
+
+        if response.status().is_success() {
+            let me: Me = response.json().await?;
+            Ok(me)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    pub async fn set_online(&self, online: bool) -> Result<ActionResponse> {
+        let url = format!("{}/api/self/online", self.base_url);
+        let request = SetOnlineRequest { online };
+
+        let response = self.client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let online_response: ActionResponse = response.json().await?;
+            Ok(online_response)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    pub async fn get_message_readers(&self, chat_id: i64, message_id: i32) -> Result<ReadersResponse> {
+        let url = format!("{}/api/chats/{}/messages/{}/readers", self.base_url, chat_id, message_id);
+        let response = self.client.get(&url).send().await?;
+
+        if response.status().is_success() {
+            let readers: ReadersResponse = response.json().await?;
+            Ok(readers)
+        } else if response.status() == StatusCode::NOT_FOUND {
+            Err(ApiError::Unsupported.into())
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    pub async fn block_user(&self, user_id: i64) -> Result<ActionResponse> {
+        let url = format!("{}/api/users/{}/block", self.base_url, user_id);
+
+        let response = self.client
+            .post(&url)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let block_response: ActionResponse = response.json().await?;
+            Ok(block_response)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    // Поставить реакцию; прежняя своя реакция заменяется
+    pub async fn set_reaction(&self, chat_id: i64, message_id: i32, emoji: &str) -> Result<ActionResponse> {
+        let url = format!("{}/api/chats/{}/messages/{}/reactions", self.base_url, chat_id, message_id);
+        let request = SetReactionRequest {
+            emoji: emoji.to_string(),
+        };
+
+        let response = self.client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let reaction_response: ActionResponse = response.json().await?;
+            Ok(reaction_response)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    // Снять свою реакцию с сообщения
+    pub async fn remove_reaction(&self, chat_id: i64, message_id: i32) -> Result<ActionResponse> {
+        let url = format!("{}/api/chats/{}/messages/{}/reactions", self.base_url, chat_id, message_id);
+
+        let response = self.client
+            .delete(&url)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let reaction_response: ActionResponse = response.json().await?;
+            Ok(reaction_response)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    // Удалить свое сообщение у всех участников
+    pub async fn delete_message(&self, chat_id: i64, message_id: i32) -> Result<ActionResponse> {
+        let url = format!("{}/api/chats/{}/messages/{}", self.base_url, chat_id, message_id);
+
+        let response = self.client
+            .delete(&url)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let delete_response: ActionResponse = response.json().await?;
+            Ok(delete_response)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    pub async fn report_message(&self, chat_id: i64, message_id: i32, reason: &str) -> Result<ActionResponse> {
+        let url = format!("{}/api/chats/{}/messages/{}/report", self.base_url, chat_id, message_id);
+        let request = ReportMessageRequest {
+            reason: reason.to_string(),
+        };
+
+        let response = self.client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let report_response: ActionResponse = response.json().await?;
+            Ok(report_response)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+
+    pub async fn mark_messages_read(&self, chat_id: i64, up_to_id: i32) -> Result<ActionResponse> {
+        let url = format!("{}/api/chats/{}/read", self.base_url, chat_id);
+        let request = MarkReadRequest { max_id: up_to_id };
+
+        let response = self.client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await?;
+
+        if response.status().is_success() {
+            let read_response: ActionResponse = response.json().await?;
+            Ok(read_response)
+        } else {
+            Err(error_from_response(response).await)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Страница ответа фейкового бэкенда по cursor и limit запроса: id чатов и next_cursor
+    type PageFn = fn(Option<&str>, Option<i32>) -> (Vec<i64>, Option<String>);
+
+    // Локальный HTTP-сервер, отдающий /api/chats по страницам; возвращает адрес и журнал запросов
+    async fn serve_chat_pages(page: PageFn) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut buffer = vec![0u8; 4096];
+                let mut read = 0;
+                while !buffer[..read].windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buffer[read..]).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => read += n,
+                    }
+                }
+                let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+                let target = request.split_whitespace().nth(1).unwrap_or_default().to_string();
+                log.lock().unwrap().push(target.clone());
+
+                let query: Vec<(String, String)> = target
+                    .split_once('?')
+                    .map(|(_, q)| reqwest::Url::parse(&format!("http://x/?{}", q)).unwrap())
+                    .map(|url| url.query_pairs().into_owned().collect())
+                    .unwrap_or_default();
+                let param = |name: &str| query.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+                let cursor = param("cursor");
+                let limit = param("limit").and_then(|l| l.parse().ok());
+                let (ids, next_cursor) = page(cursor.as_deref(), limit);
+
+                let chats: Vec<serde_json::Value> = ids
+                    .iter()
+                    .map(|id| serde_json::json!({ "id": id, "title": format!("Чат {}", id), "type": "group", "unread": 0 }))
+                    .collect();
+                let body = serde_json::json!({ "chats": chats, "next_cursor": next_cursor }).to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+        (address, requests)
+    }
+
+    fn ids(chats: &[Chat]) -> Vec<i64> {
+        chats.iter().map(|c| c.id).collect()
+    }
+
+    #[tokio::test]
+    async fn get_chats_follows_next_cursor() {
+        // Вторая страница повторяет чат 2: список сдвинулся между запросами
+        let (address, requests) = serve_chat_pages(|cursor, _| match cursor {
+            None => (vec![1, 2], Some("page2".to_string())),
+            Some("page2") => (vec![2, 3], Some(String::new())),
+            Some(other) => panic!("неожиданный cursor {}", other),
+        })
+        .await;
+
+        let chats = ApiClient::new(address).get_chats(None).await.unwrap();
+
+        assert_eq!(ids(&chats), vec![1, 2, 3]);
+        assert_eq!(*requests.lock().unwrap(), vec!["/api/chats", "/api/chats?cursor=page2"]);
+    }
+
+    #[tokio::test]
+    async fn get_chats_stops_at_max_pages() {
+        // Бэкенд всегда обещает еще одну страницу
+        let (address, requests) = serve_chat_pages(|cursor, _| {
+            let page: i64 = cursor.map(|c| c.parse().unwrap()).unwrap_or(0);
+            (vec![page], Some((page + 1).to_string()))
+        })
+        .await;
+
+        let chats = ApiClient::new(address).get_chats(None).await.unwrap();
+
+        assert_eq!(chats.len(), MAX_CHAT_PAGES);
+        assert_eq!(ids(&chats), (0..MAX_CHAT_PAGES as i64).collect::<Vec<_>>());
+        assert_eq!(requests.lock().unwrap().len(), MAX_CHAT_PAGES);
+    }
+
+    #[tokio::test]
+    async fn get_chats_honors_limit() {
+        // Не больше двух чатов на странице, даже если limit больше
+        let (address, requests) = serve_chat_pages(|cursor, limit| {
+            let start: i64 = cursor.map(|c| c.parse().unwrap()).unwrap_or(1);
+            let count = limit.unwrap_or(2).min(2) as i64;
+            ((start..start + count).collect(), Some((start + count).to_string()))
+        })
+        .await;
+
+        let chats = ApiClient::new(address).get_chats(Some(3)).await.unwrap();
+
+        assert_eq!(ids(&chats), vec![1, 2, 3]);
+        // Вторая страница запрашивает только оставшееся, третьей нет
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec!["/api/chats?limit=3", "/api/chats?limit=1&cursor=3"]
+        );
+    }
 }
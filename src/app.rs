@@ -1,9 +1,29 @@
 use anyhow::Result;
 use std::time::{Duration, Instant};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::mpsc;
 
-use crate::api::ApiClient;
-use crate::{AuthStatus, Chat, Message};
+use crate::api::{ApiClient, ApiError, ChatDetails, Me, SendMessageResponse, UpdatesResponse};
+use crate::cache;
+use crate::config::{Config, CorruptMediaPolicy, UnsupportedAction};
+use crate::session::{self, SessionOverlay, UiSession};
+use crate::symbols::{self, Symbols};
+use crate::{AuthStatus, Chat, Message, SendState};
+
+// Id локальной копии, отправка черновика (id первой части), ответ и время запроса (None, если часть не отправлялась)
+type SendResult = (i32, Option<i32>, Result<SendMessageResponse>, Option<Duration>);
+
+// Результат принудительной перезагрузки медиа: путь файла и ошибка, если не удалось
+type RedownloadResult = (String, Result<()>);
+
+// Результат фоновой отправки голосового: чат, файл записи, длительность и ответ сервера
+type VoiceResult = (i64, String, Duration, Result<SendMessageResponse>);
+
+// Сколько ждать завершения программы записи после SIGINT, прежде чем убить ее
+const RECORDER_STOP_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Ход экспорта медиа: сколько файлов сохранено, сколько не удалось и сколько всего
+type ExportProgress = (usize, usize, usize);
 
 
 
@@ -17,6 +37,22 @@ pub struct AudioPlayer {
     pub current_file_path: Option<String>, // Store current audio file path for restart
 }
 
+// Идущая запись голосового сообщения внешней программой
+#[derive(Debug)]
+pub struct VoiceRecording {
+    pub child: std::process::Child,
+    pub path: String,
+    // Чат, открытый при начале записи: голосовое уходит туда, даже если чат успели сменить
+    pub chat_id: i64,
+    pub started: Instant,
+}
+
+impl VoiceRecording {
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
 impl Default for AudioPlayer {
     fn default() -> Self {
         Self {
@@ -70,15 +106,6 @@ impl AudioPlayer {
         self.process_id = None;
     }
 
-    pub fn stop_playback(&mut self) {
-        self.stop();
-    }
-
-    pub fn stop_playback_with_timestamp(&mut self, app: &mut crate::App) {
-        self.stop();
-        app.audio_start_time = None;
-    }
-
     pub fn seek(&mut self, seconds: i64) -> bool {
         // Обновляем позицию в памяти для UI
         let old_position = self.current_position;
@@ -103,10 +130,11 @@ impl AudioPlayer {
         // Пробуем разные методы управления плеером
         if let Some(pid) = self.process_id {
             // Проверяем, что процесс еще работает
-            if let Ok(_) = std::process::Command::new("kill")
+            if std::process::Command::new("kill")
                 .arg("-0")  // Проверяем, что процесс существует
                 .arg(pid.to_string())
-                .status() {
+                .status()
+                .is_ok() {
 
                 log::debug!("Process {} is running, attempting to send seek command", pid);
 
@@ -256,14 +284,50 @@ async fn download_map_image_async(url: &str, message_id: i32) -> Result<String>
     let image_data = response.bytes().await
         .map_err(|e| anyhow::anyhow!("Ошибка чтения данных изображения: {}", e))?;
 
-    // Write to temporary file
-    tokio::fs::write(&temp_path, &image_data).await
+    // Пишем во временный .part файл и переименовываем только после полной записи,
+    // чтобы прерванная загрузка не оставляла битый файл под итоговым именем
+    let part_path = partial_download_path(&temp_path);
+    tokio::fs::write(&part_path, &image_data).await
+        .map_err(|e| anyhow::anyhow!("Ошибка сохранения файла: {}", e))?;
+    tokio::fs::rename(&part_path, &temp_path).await
         .map_err(|e| anyhow::anyhow!("Ошибка сохранения файла: {}", e))?;
 
     log::info!("Карта успешно скачана и сохранена в фоновом режиме: {}", temp_path);
     Ok(temp_path)
 }
 
+// Убираем повторы id (правки, пересечение страниц), оставляя последнее вхождение на его месте
+fn dedup_messages_by_id(messages: &mut Vec<Message>) {
+    let mut seen = HashSet::new();
+    let mut keep: Vec<bool> = messages.iter().rev().map(|m| seen.insert(m.id)).collect();
+    keep.reverse();
+    let mut keep = keep.into_iter();
+    messages.retain(|_| keep.next().unwrap_or(true));
+}
+
+// Позиция ближайшего к удаленному сообщения, которое осталось после обновления:
+// сначала ищем среди более новых, затем среди более старых
+fn nearest_remaining(old_ids: &[i32], old_index: usize, messages: &[Message]) -> Option<usize> {
+    let newer = old_ids.iter().skip(old_index + 1);
+    let older = old_ids.iter().take(old_index).rev();
+    newer
+        .chain(older)
+        .find_map(|id| messages.iter().position(|m| m.id == *id))
+}
+
+fn partial_download_path(path: &str) -> String {
+    format!("{}.part", path)
+}
+
+// Области, между которыми по кругу переключают Tab и Shift+Tab
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FocusPane {
+    Chats,
+    Messages,
+    // Поле ввода сообщения; черновик при уходе из него сохраняется
+    Compose,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppState {
     Loading,
@@ -274,14 +338,306 @@ pub enum AppState {
     Error,
     ImagePreview,
     VideoPreview,
+    Confirm,
+    ReportReason,
+    RawJson,
+    SearchInput,
+    Bookmarks,
+    CatchUp,
+    Reactions,
+    GlobalSearch,
+    Readers,
+    ScheduleInput,
+    ChatInfo,
+    ExportInput,
+    GotoInput,
+    ChatFilterInput,
+    InviteLink,
+}
+
+// Действие, ожидающее подтверждения пользователя (y/n)
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingAction {
+    BlockUser { user_id: i64, name: String },
+    OpenExternally { message_type: String, path: String },
+    ClearHistory { chat_id: i64, title: String },
+    DeleteMessage { chat_id: i64, message_id: i32, preview: String },
+    // Отмеченные свои сообщения; skipped - сколько отмеченных чужих останется
+    DeleteMarked { chat_id: i64, message_ids: Vec<i32>, skipped: usize },
+}
+
+impl PendingAction {
+    pub fn prompt(&self) -> String {
+        match self {
+            PendingAction::BlockUser { name, .. } => format!("Заблокировать пользователя {}?", name),
+            PendingAction::OpenExternally { message_type, path } => {
+                format!("Тип сообщения «{}» не поддерживается. Открыть {} во внешней программе?", message_type, path)
+            }
+            PendingAction::ClearHistory { title, .. } => {
+                format!("Очистить локальную историю чата «{}»? На сервере сообщения останутся", title)
+            }
+            PendingAction::DeleteMessage { preview, .. } => {
+                format!("Удалить сообщение «{}» у всех участников?", preview)
+            }
+            PendingAction::DeleteMarked { message_ids, skipped, .. } => {
+                let skipped = if *skipped > 0 { format!(" Чужие ({}) останутся", skipped) } else { String::new() };
+                format!("Удалить отмеченные сообщения ({}) у всех участников?{}", message_ids.len(), skipped)
+            }
+        }
+    }
+}
+
+// Максимальная длина сообщения в Telegram (в UTF-16 единицах)
+pub const MESSAGE_LENGTH_LIMIT: usize = 4096;
+
+// Подставляем значения в шаблон (status_format, chat_list_format); неизвестные плейсхолдеры остаются как есть
+pub fn expand_template(format: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(format.len());
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            result.push_str(&rest[start..]);
+            return result;
+        };
+        let name = &after[..end];
+        match value(name) {
+            Some(value) => result.push_str(&value),
+            None => {
+                result.push('{');
+                result.push_str(name);
+                result.push('}');
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+pub fn message_length(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+// Делим длинный текст на части не длиннее лимита, разрывая по переводу строки или пробелу.
+// Разделители остаются в конце частей, поэтому склейка частей дает исходный текст.
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while message_length(rest) > limit {
+        // Байтовая граница, до которой часть укладывается в лимит
+        let mut units = 0;
+        let mut hard_end = 0;
+        for (i, ch) in rest.char_indices() {
+            if units + ch.len_utf16() > limit {
+                break;
+            }
+            units += ch.len_utf16();
+            hard_end = i + ch.len_utf8();
+        }
+        let head = &rest[..hard_end];
+        let end = head
+            .rfind('\n')
+            .filter(|&i| i >= head.len() / 2)
+            .or_else(|| head.rfind(char::is_whitespace))
+            .filter(|&i| i > 0)
+            .map(|i| i + head[i..].chars().next().map_or(1, |c| c.len_utf8()))
+            .unwrap_or(hard_end);
+        parts.push(rest[..end].to_string());
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        parts.push(rest.to_string());
+    }
+    parts
+}
+
+// Эндпоинты с периодическим опросом, которые бэкенд может ограничить по частоте (429)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PollEndpoint {
+    AuthStatus,
+    Chats,
+    Messages,
+}
+
+// Совпадение поиска в тексте или подписи сообщения; term уже в нижнем регистре
+fn message_matches(msg: &Message, term: &str) -> bool {
+    msg.text.to_lowercase().contains(term)
+        || msg.caption.as_ref().is_some_and(|c| c.to_lowercase().contains(term))
+}
+
+// Строка сводки "что нового": чат и прирост непрочитанных с момента ухода
+#[derive(Debug, Clone)]
+pub struct CatchUpEntry {
+    pub chat_id: i64,
+    pub title: String,
+    pub new_messages: i32,
+    pub mentions: i32,
+}
+
+// Сколько сообщений истории загружать обычно и до скольких догружать при переходе к сообщению по id
+const DEFAULT_HISTORY_LIMIT: i32 = 200;
+const MAX_HISTORY_LIMIT: i32 = 3200;
+
+// Сколько бэкенд держит long-poll запрос без изменений
+const LONG_POLL_TIMEOUT_SECS: u64 = 25;
+
+// Сколько выделение должно задержаться на медиа, чтобы открылся предпросмотр (hover_preview)
+const HOVER_PREVIEW_DWELL: Duration = Duration::from_millis(700);
+
+// Через сколько без нажатий пользователь считается отошедшим
+const IDLE_CATCH_UP_AFTER: Duration = Duration::from_secs(5 * 60);
+
+// Файл неподдерживаемого сообщения: любое поле *_path, указывающее на существующий файл
+fn unsupported_message_path(msg: &Message) -> Option<String> {
+    let mut keys: Vec<&String> = msg.extra.keys().filter(|k| k.ends_with("_path")).collect();
+    keys.sort();
+    keys.into_iter()
+        .filter_map(|key| msg.extra.get(key).and_then(|v| v.as_str()))
+        .find(|path| std::path::Path::new(path).exists())
+        .map(str::to_string)
+}
+
+// Время отложенной отправки: "+30m"/"+2h"/"+1d", "ЧЧ:ММ" (сегодня), "ДД.ММ ЧЧ:ММ" или "ГГГГ-ММ-ДД ЧЧ:ММ".
+// Время должно быть в будущем.
+pub fn parse_schedule_time(input: &str, now: chrono::DateTime<chrono::Local>) -> Result<chrono::DateTime<chrono::Local>, String> {
+    use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+
+    let input = input.trim();
+    let at = if let Some(relative) = input.strip_prefix('+') {
+        // Единица - последний символ; кириллические занимают два байта
+        let unit = relative.chars().last().ok_or("ожидается число и единица: +30m")?;
+        let amount = &relative[..relative.len() - unit.len_utf8()];
+        let amount: i64 = amount.parse().map_err(|_| format!("не число: {}", amount))?;
+        let delta = match unit {
+            'm' | 'м' => chrono::TimeDelta::try_minutes(amount),
+            'h' | 'ч' => chrono::TimeDelta::try_hours(amount),
+            'd' | 'д' => chrono::TimeDelta::try_days(amount),
+            _ => return Err("единица должна быть m, h или d".to_string()),
+        };
+        delta
+            .and_then(|delta| now.checked_add_signed(delta))
+            .ok_or_else(|| format!("слишком далеко: {}", input))?
+    } else {
+        let naive = if let Ok(time) = NaiveTime::parse_from_str(input, "%H:%M") {
+            now.date_naive().and_time(time)
+        } else if let Ok(datetime) = NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M") {
+            datetime
+        } else {
+            let (date, time) = input.split_once(' ').ok_or("ожидается ЧЧ:ММ, ДД.ММ ЧЧ:ММ или +30m")?;
+            let date = NaiveDate::parse_from_str(&format!("{}.{}", date, now.year()), "%d.%m.%Y")
+                .map_err(|_| format!("не дата: {}", date))?;
+            let time = NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| format!("не время: {}", time))?;
+            date.and_time(time)
+        };
+        chrono::Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .ok_or("такого времени нет из-за перевода часов")?
+    };
+
+    if at <= now {
+        return Err("время уже прошло".to_string());
+    }
+    Ok(at)
+}
+
+// Локальная копия запланированного сообщения, время отправки которого еще не наступило
+fn is_pending_scheduled(msg: &Message, now: chrono::DateTime<chrono::Local>) -> bool {
+    msg.id <= 0
+        && msg.scheduled_at
+            .as_deref()
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .is_some_and(|at| at > now)
+}
+
+// Тип медиа в auto_download по виду файла на бэкенде
+fn media_type_of(kind: &str) -> &'static str {
+    match kind {
+        "images" => "photo",
+        "videos" => "video",
+        "voices" => "voice",
+        "audios" => "audio",
+        "stickers" => "sticker",
+        _ => "document",
+    }
+}
+
+// Сколько состояние интерфейса должно не меняться, чтобы сохранить сессию на диск
+const SESSION_SAVE_DELAY: Duration = Duration::from_secs(2);
+
+// Расхождение часов бэкенда и терминала, о котором стоит предупредить
+const CLOCK_SKEW_WARNING: chrono::TimeDelta = chrono::TimeDelta::minutes(2);
+
+// Сколько файлов экспорт медиа копирует или скачивает одновременно
+const EXPORT_CONCURRENCY: usize = 4;
+
+// Файл для экспорта: локальный путь (если бэкенд его уже скачал) и как скачать заново
+struct ExportItem {
+    path: Option<String>,
+    download: Option<(&'static str, i64)>,
+    file_name: String,
+}
+
+// Что из сообщения можно выгрузить: фото, видео, голосовые, аудио и документы неизвестных типов
+fn export_item(msg: &Message) -> Option<ExportItem> {
+    let (path, download, extension) = match msg.r#type.as_str() {
+        "photo" => (msg.image_path.clone(), msg.image_id.map(|id| ("images", id)), "jpg"),
+        "video" => (msg.video_path.clone(), msg.video_id.map(|id| ("videos", id)), "mp4"),
+        "voice" => (msg.voice_path.clone(), msg.voice_id.map(|id| ("voices", id)), "ogg"),
+        "audio" => (msg.audio_path.clone(), msg.audio_id.map(|id| ("audios", id)), "mp3"),
+        t if t != "text" && !crate::ui::is_service_type(t) => (unsupported_message_path(msg), None, "bin"),
+        _ => return None,
+    };
+    if path.is_none() && download.is_none() {
+        return None;
+    }
+    // Имя с id сообщения, чтобы одинаковые имена из разных сообщений не затирали друг друга
+    let base_name = path
+        .as_deref()
+        .and_then(|p| std::path::Path::new(p).file_name())
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| format!("{}.{}", msg.r#type, extension));
+    Some(ExportItem { path, download, file_name: format!("{}_{}", msg.id, base_name) })
 }
 
+async fn export_one(api_client: &ApiClient, item: &ExportItem, dir: &std::path::Path) -> Result<()> {
+    let target = dir.join(&item.file_name);
+    if let Some(path) = item.path.as_deref().filter(|p| std::path::Path::new(p).exists()) {
+        tokio::fs::copy(path, &target).await?;
+        return Ok(());
+    }
+    let Some((kind, media_id)) = item.download else {
+        anyhow::bail!("файл {} не найден", item.file_name);
+    };
+    let data = api_client.download_media(kind, media_id).await?;
+    tokio::fs::write(&target, &data).await?;
+    Ok(())
+}
+
+// Причины жалобы: код для API и подпись для списка
+pub const REPORT_REASONS: &[(&str, &str)] = &[
+    ("spam", "Спам"),
+    ("violence", "Насилие"),
+    ("pornography", "Порнография"),
+    ("child_abuse", "Жестокое обращение с детьми"),
+    ("copyright", "Нарушение авторских прав"),
+    ("fake", "Фейк"),
+    ("illegal_drugs", "Наркотики"),
+    ("personal_details", "Личные данные"),
+    ("other", "Другое"),
+];
+
 pub struct App {
     pub api_client: ApiClient,
+    pub config: Config,
     pub state: AppState,
 
     // Состояние авторизации
     pub auth_status: Option<AuthStatus>,
+    // Данные вошедшего аккаунта, чтобы не перепутать аккаунты
+    pub me: Option<Me>,
     pub phone_input: String,
     pub code_input: String,
 
@@ -291,11 +647,39 @@ pub struct App {
     pub selected_chat: Option<Chat>,
     pub messages: Vec<Message>,
     pub message_input: String,
+    pub show_timestamps: bool,
+    // Набор значков интерфейса: Unicode или ASCII (config.ascii_only или определение терминала)
+    pub symbols: &'static Symbols,
+    // Находится ли окно терминала в фокусе; без фокуса периодический опрос приостанавливается
+    pub terminal_focused: bool,
+    // Бэкенд недоступен, показываются данные из локального кэша
+    pub offline: bool,
+    // Последняя проверка статуса не дошла до бэкенда; ждем переподключения
+    pub disconnected: bool,
+    // Насколько часы бэкенда впереди местных; None - еще не измерено
+    pub clock_skew: Option<chrono::TimeDelta>,
+    // Собственный статус присутствия: false - скрываем "в сети"
+    pub self_online: bool,
+    // Нужно ли перерисовать экран на следующем шаге цикла
+    pub needs_redraw: bool,
 
     // Выбор и фокус сообщений
     pub focus_on_messages: bool,
+    // Порядок обхода областей по Tab; новые области добавляются сюда
+    pub focus_ring: Vec<FocusPane>,
     pub selected_message_index: usize,
     pub message_scroll_offset: usize,
+    // На сколько сообщений лента сдвинута вверх при наборе (PageUp/PageDown), выделение не меняется
+    pub input_scroll_back: usize,
+    // История переходов по ответам: снимки (selected_message_index, message_scroll_offset)
+    pub jump_back_stack: Vec<(usize, usize)>,
+    pub jump_forward_stack: Vec<(usize, usize)>,
+    // Отмеченные сообщения для пакетных операций (Space)
+    pub marked_messages: HashSet<i32>,
+    // Выделение держится на последнем сообщении, в том числе после обновления ленты
+    pub follow_tail: bool,
+    // Сообщения, у которых пользователь раскрыл спойлеры
+    pub revealed_spoilers: HashSet<i32>,
     pub last_loaded_chat_id: Option<i64>,
 
     // Просмотр изображения
@@ -304,8 +688,28 @@ pub struct App {
     // Просмотр видео
     pub preview_video_path: Option<String>,
 
+    // Фоновые загрузки по пути итогового файла, чтобы их можно было отменить
+    pub downloads: HashMap<String, tokio::task::AbortHandle>,
+    // Поврежденные файлы, уже обработанные по on_corrupt_media (чтобы не скачивать по кругу)
+    pub corrupt_media_handled: HashSet<String>,
+
     // Состояние ошибки
     pub error_message: String,
+    // Краткое уведомление в строке статуса, пропадает при следующем нажатии клавиши
+    pub notice: Option<String>,
+
+    // Подтверждение действий и выбор причины жалобы
+    pub pending_action: Option<PendingAction>,
+    pub report_target: Option<(i64, i32)>,
+    pub report_reason_index: usize,
+
+    // Отладочный просмотр сообщения в исходном JSON (F12)
+    pub raw_json: String,
+    pub raw_json_scroll: u16,
+
+    // Отметка прочтения при листании: максимальный отправленный id по чатам и отложенный запрос
+    pub read_up_to: HashMap<i64, i32>,
+    pub pending_read: Option<(i64, i32, Instant)>,
 
     // Изображения
     pub image_paths: HashMap<i64, String>,
@@ -316,7 +720,68 @@ pub struct App {
     // Таймеры для обновления
     pub last_update: Instant,
     pub last_auth_check: Instant,
-    pub last_data_refresh: Instant,
+    // До какого момента не опрашивать эндпоинт после ответа 429
+    pub backoff_until: HashMap<PollEndpoint, Instant>,
+    // Список чатов скрыт, сообщения занимают всю ширину
+    pub sidebar_hidden: bool,
+    // Рисовать картинки цветными полублоками вместо графического протокола терминала (до выхода)
+    pub half_block_images: bool,
+    // Сессия с прошлого запуска, ждет загрузки чатов
+    pub pending_session: Option<UiSession>,
+    // Последняя сохраненная сессия и момент, с которого текущая от нее отличается
+    pub saved_session: UiSession,
+    pub session_changed_at: Option<Instant>,
+
+    // Поиск по сообщениям: набираемый запрос и активный термин, который подсвечивается до сброса
+    pub search_input: String,
+    pub search_term: Option<String>,
+    // Выделение и прокрутка до "/": к ним возвращает Esc и пустой запрос
+    pub search_origin: Option<(usize, usize)>,
+    // Сообщение (id чата, id сообщения), на которое отвечает набираемый черновик (R)
+    pub reply_to: Option<(i64, i32)>,
+    // Фильтр списка чатов по названию ("/" в списке чатов); пусто - показываются все
+    pub chat_filter: String,
+    // Время отложенной отправки, набираемое для текущего черновика
+    pub schedule_input: String,
+
+    // Числовой префикс-повторитель в стиле vi (5j)
+    pub count_prefix: Option<usize>,
+    // Первая клавиша двухклавишной команды (gg); как и префикс, сбрасывается следующей клавишей
+    pub pending_key: Option<char>,
+
+    // Список закладок: подписи строятся при открытии
+    pub bookmark_labels: Vec<String>,
+    pub bookmark_index: usize,
+
+    // Сводка после отсутствия: снимок непрочитанных (непрочитанные, упоминания) и ее строки
+    pub unread_snapshot: Option<HashMap<i64, (i32, i32)>>,
+    pub last_input: Instant,
+    pub catch_up: Vec<CatchUpEntry>,
+    pub catch_up_index: usize,
+
+    // Предыдущий открытый чат для быстрого переключения (Ctrl+^)
+    pub previous_chat_id: Option<i64>,
+
+    // Поиск по всем чатам: набираемый запрос, выполненный запрос и найденные сообщения
+    pub global_search_input: String,
+    pub global_search_query: String,
+    pub global_search_results: Vec<Message>,
+    pub global_search_index: usize,
+
+    // Кто прочитал выбранное сообщение (готовые строки для окна)
+    pub readers_lines: Vec<String>,
+    // Предпросмотр при наведении: на каком медиа выделение и с какого момента (None - уже открыто или отменено)
+    pub hover_message: Option<i32>,
+    pub hover_since: Option<Instant>,
+    // Текущий предпросмотр открыт наведением и закрывается любой клавишей
+    pub hover_preview_open: bool,
+    // Описание или bio чата для окна информации (I)
+    pub chat_details: Option<ChatDetails>,
+    // Ссылка-приглашение (L): название чата и ссылка
+    pub invite_link: Option<(String, String)>,
+    // Переводы сообщений (t) по (id чата, id сообщения) и какие из них показаны под оригиналом
+    pub translations: HashMap<(i64, i32), String>,
+    pub shown_translations: HashSet<(i64, i32)>,
     pub audio_start_time: Option<Instant>,
 
     // Реальная видимая емкость из UI
@@ -324,14 +789,61 @@ pub struct App {
 
     // Аудио плеер состояние
     pub audio_player: AudioPlayer,
+
+    // Оптимистичная отправка сообщений
+    pub next_local_message_id: i32,
+    pub send_results_tx: mpsc::UnboundedSender<SendResult>,
+    pub send_results_rx: mpsc::UnboundedReceiver<SendResult>,
+    // Куда в поле ввода возвращаются неотправленные части черновика: отправка и конец вставленного текста
+    restored_draft: Option<(i32, usize)>,
+    // Последний замеренный запрос (отправка или загрузка сообщений) и его длительность
+    pub last_latency: Option<(&'static str, Duration)>,
+
+    // Запись голосового сообщения (V): пока идет, в строке статуса тикает таймер
+    pub recording: Option<VoiceRecording>,
+
+    // Сколько последних сообщений открытого чата загружается (растет при переходе к старому сообщению)
+    pub history_limit: i32,
+    // Id сообщения, набираемый для перехода (:)
+    pub goto_input: String,
+
+    // Экспорт медиа чата (E): набираемая папка, папка идущего экспорта и ход
+    pub export_input: String,
+    pub export_dir: Option<String>,
+    pub export_progress: Option<ExportProgress>,
+    pub export_tx: mpsc::UnboundedSender<ExportProgress>,
+    pub export_rx: mpsc::UnboundedReceiver<ExportProgress>,
+
+    // Принудительная перезагрузка медиа (F)
+    pub redownload_tx: mpsc::UnboundedSender<RedownloadResult>,
+    pub redownload_rx: mpsc::UnboundedReceiver<RedownloadResult>,
+
+    // Отправка записанного голосового (V) идет в фоне
+    pub voice_results_tx: mpsc::UnboundedSender<VoiceResult>,
+    pub voice_results_rx: mpsc::UnboundedReceiver<VoiceResult>,
+
+    // Фоновый long-poll обновлений и канал, по которому приходят изменения
+    pub updates_task: Option<tokio::task::AbortHandle>,
+    pub updates_tx: mpsc::UnboundedSender<UpdatesResponse>,
+    pub updates_rx: mpsc::UnboundedReceiver<UpdatesResponse>,
 }
 
 impl App {
-    pub fn new(api_client: ApiClient) -> Self {
+    pub fn new(api_client: ApiClient, config: Config) -> Self {
+        let (send_results_tx, send_results_rx) = mpsc::unbounded_channel();
+        let (updates_tx, updates_rx) = mpsc::unbounded_channel();
+        let (redownload_tx, redownload_rx) = mpsc::unbounded_channel();
+        let (voice_results_tx, voice_results_rx) = mpsc::unbounded_channel();
+        let (export_tx, export_rx) = mpsc::unbounded_channel();
+        let show_timestamps = config.show_timestamps;
+        let symbols = symbols::get(config.ascii_only.unwrap_or_else(symbols::terminal_lacks_unicode));
+        let pending_session = session::load();
         Self {
             api_client,
+            config,
             state: AppState::Loading,
             auth_status: None,
+            me: None,
             phone_input: String::new(),
             code_input: String::new(),
             chats: Vec::new(),
@@ -339,56 +851,282 @@ impl App {
             selected_chat: None,
             messages: Vec::new(),
             message_input: String::new(),
+            show_timestamps,
+            symbols,
+            terminal_focused: true,
+            offline: false,
+            disconnected: false,
+            clock_skew: None,
+            self_online: true,
+            needs_redraw: true,
             //
             focus_on_messages: false,
+            focus_ring: vec![FocusPane::Chats, FocusPane::Messages, FocusPane::Compose],
             selected_message_index: 0,
             message_scroll_offset: 0,
+            input_scroll_back: 0,
+            jump_back_stack: Vec::new(),
+            jump_forward_stack: Vec::new(),
+            marked_messages: HashSet::new(),
+            follow_tail: false,
+            revealed_spoilers: HashSet::new(),
             last_loaded_chat_id: None,
             //
             preview_image_path: None,
             preview_video_path: None,
+            downloads: HashMap::new(),
+            corrupt_media_handled: HashSet::new(),
             error_message: String::new(),
+            notice: None,
+            pending_action: None,
+            report_target: None,
+            report_reason_index: 0,
+            raw_json: String::new(),
+            raw_json_scroll: 0,
+            read_up_to: HashMap::new(),
+            backoff_until: HashMap::new(),
+            sidebar_hidden: pending_session.as_ref().is_some_and(|s| s.sidebar_hidden),
+            half_block_images: false,
+            saved_session: pending_session.clone().unwrap_or_default(),
+            pending_session,
+            session_changed_at: None,
+            search_input: String::new(),
+            search_term: None,
+            search_origin: None,
+            chat_filter: String::new(),
+            reply_to: None,
+            schedule_input: String::new(),
+            count_prefix: None,
+            pending_key: None,
+            bookmark_labels: Vec::new(),
+            bookmark_index: 0,
+            unread_snapshot: None,
+            last_input: Instant::now(),
+            catch_up: Vec::new(),
+            catch_up_index: 0,
+            previous_chat_id: None,
+            global_search_input: String::new(),
+            global_search_query: String::new(),
+            global_search_results: Vec::new(),
+            global_search_index: 0,
+            readers_lines: Vec::new(),
+            chat_details: None,
+            invite_link: None,
+            translations: HashMap::new(),
+            shown_translations: HashSet::new(),
+            hover_message: None,
+            hover_since: None,
+            hover_preview_open: false,
+            pending_read: None,
             image_paths: HashMap::new(),
             sticker_paths: HashMap::new(),
             last_update: Instant::now(),
             last_auth_check: Instant::now(),
-            last_data_refresh: Instant::now(),
             audio_start_time: None,
             visible_capacity: 15, // Значение по умолчанию
             audio_player: AudioPlayer::new(),
+            next_local_message_id: -1,
+            send_results_tx,
+            send_results_rx,
+            restored_draft: None,
+            last_latency: None,
+            redownload_tx,
+            redownload_rx,
+            voice_results_tx,
+            voice_results_rx,
+            recording: None,
+            history_limit: DEFAULT_HISTORY_LIMIT,
+            goto_input: String::new(),
+            export_input: String::new(),
+            export_dir: None,
+            export_progress: None,
+            export_tx,
+            export_rx,
+            updates_task: None,
+            updates_tx,
+            updates_rx,
         }
     }
 
     pub async fn update(&mut self) -> Result<()> {
         let now = Instant::now();
 
+        // Применяем результаты фоновой отправки сообщений
+        self.process_send_results();
+
+        // Применяем изменения, пришедшие через long-poll
+        self.process_updates();
+
+        // Сообщаем о завершении перезагрузки медиа
+        self.process_redownload_results();
+
+        // Сообщаем об отправке голосового
+        self.process_voice_results().await?;
+
+        // Поступаем с найденными при отрисовке битыми файлами по on_corrupt_media
+        self.process_corrupt_media();
+
+        // Обновляем ход экспорта медиа
+        self.process_export_progress();
+
         // В режиме предпросмотра картинки ничего не обновляем, чтобы не дергать layout
         if self.state == AppState::ImagePreview {
             return Ok(());
         }
 
+        // Выделение задержалось на медиа - открываем предпросмотр
+        self.check_hover_preview(now);
+
+        // Долгое бездействие - запоминаем непрочитанные для сводки
+        if self.state == AppState::Main && now.duration_since(self.last_input) > IDLE_CATCH_UP_AFTER {
+            self.take_unread_snapshot();
+        }
+
+        // Отправляем накопленную отметку прочтения
+        self.flush_pending_read(now);
+
+        // Запоминаем сессию, когда она перестала меняться
+        self.save_session_debounced(now);
+
         // Обновляем позицию аудио плеера
         self.update_audio_position(now);
+        if self.audio_player.is_playing {
+            self.needs_redraw = true;
+        }
 
         // Проверяем авторизацию каждые 2 секунды
-        if now.duration_since(self.last_auth_check) > Duration::from_secs(2) {
+        if self.terminal_focused
+            && !self.is_backed_off(PollEndpoint::AuthStatus)
+            && now.duration_since(self.last_auth_check) > Duration::from_secs(2)
+        {
             self.check_auth_status().await?;
             self.last_auth_check = now;
+            self.needs_redraw = true;
+        }
+
+        self.last_update = now;
+        Ok(())
+    }
+
+    pub async fn on_focus_gained(&mut self) {
+        self.terminal_focused = true;
+        self.last_input = Instant::now();
+        if self.config.refresh_on_focus && self.state == AppState::Main {
+            if let Err(e) = self.refresh_data().await {
+                self.show_error(&format!("Ошибка обновления: {}", e));
+            }
         }
+        self.open_catch_up();
+    }
 
-    // ВРЕМЕННО ОТКЛЮЧЕНО: Обновляем данные каждые 5 секунд в основном состоянии
-    /*
-    if self.state == AppState::Main &&
-       now.duration_since(self.last_data_refresh) > Duration::from_secs(5) {
-        self.refresh_data().await?;
-        self.last_data_refresh = now;
+    pub fn on_focus_lost(&mut self) {
+        self.terminal_focused = false;
+        self.take_unread_snapshot();
     }
-    */
 
-        self.last_update = now;
+    // Первая клавиша после простоя: обновляем данные и показываем сводку.
+    // Возвращает true, если клавиша ушла на открытие сводки
+    pub async fn wake_from_idle(&mut self) -> bool {
+        self.last_input = Instant::now();
+        if !self.terminal_focused || self.unread_snapshot.is_none() || self.state != AppState::Main {
+            return false;
+        }
+        if let Err(e) = self.refresh_data().await {
+            self.show_error(&format!("Ошибка обновления: {}", e));
+            return true;
+        }
+        self.open_catch_up();
+        self.state == AppState::CatchUp
+    }
+
+    fn take_unread_snapshot(&mut self) {
+        if self.unread_snapshot.is_some() || self.chats.is_empty() {
+            return;
+        }
+        self.unread_snapshot = Some(
+            self.chats
+                .iter()
+                .map(|c| (c.id, (c.unread, c.unread_mentions.unwrap_or(0))))
+                .collect(),
+        );
+    }
+
+    // Сравниваем текущие непрочитанные со снимком; если что-то пришло, открываем сводку
+    fn open_catch_up(&mut self) {
+        let Some(snapshot) = self.unread_snapshot.take() else {
+            return;
+        };
+        if self.state != AppState::Main {
+            return;
+        }
+        self.catch_up = self.chats
+            .iter()
+            .filter_map(|chat| {
+                let (unread_before, mentions_before) = snapshot.get(&chat.id).copied().unwrap_or((0, 0));
+                let new_messages = (chat.unread - unread_before).max(0);
+                let mentions = (chat.unread_mentions.unwrap_or(0) - mentions_before).max(0);
+                (new_messages > 0 || mentions > 0).then(|| CatchUpEntry {
+                    chat_id: chat.id,
+                    title: chat.title.clone(),
+                    new_messages,
+                    mentions,
+                })
+            })
+            .collect();
+        if self.catch_up.is_empty() {
+            return;
+        }
+        // Сначала чаты с упоминаниями, затем по числу новых сообщений
+        self.catch_up.sort_by(|a, b| b.mentions.cmp(&a.mentions).then(b.new_messages.cmp(&a.new_messages)));
+        self.catch_up_index = 0;
+        self.state = AppState::CatchUp;
+    }
+
+    pub fn move_catch_up_selection(&mut self, direction: i32) {
+        let last = self.catch_up.len().saturating_sub(1);
+        self.catch_up_index = if direction > 0 {
+            (self.catch_up_index + 1).min(last)
+        } else {
+            self.catch_up_index.saturating_sub(1)
+        };
+    }
+
+    pub fn close_catch_up(&mut self) {
+        self.catch_up.clear();
+        self.state = AppState::Main;
+    }
+
+    // Enter в сводке: переходим в выбранный чат разбирать новые сообщения
+    pub async fn open_catch_up_chat(&mut self) -> Result<()> {
+        let chat_id = self.catch_up.get(self.catch_up_index).map(|e| e.chat_id);
+        self.close_catch_up();
+        let Some(pos) = chat_id.and_then(|id| self.chats.iter().position(|c| c.id == id)) else {
+            return Ok(());
+        };
+        self.selected_chat_index = pos;
+        self.select_chat().await?;
+        self.focus_messages();
         Ok(())
     }
 
+    // Сколько ждать события в главном цикле
+    pub fn tick_rate(&self) -> Duration {
+        let base = self.config.tick_rate_ms.clamp(10, 1000);
+        if !self.config.adaptive_tick_rate {
+            return Duration::from_millis(base);
+        }
+        let busy = self.audio_player.is_playing
+            || matches!(self.state, AppState::ImagePreview | AppState::VideoPreview);
+        let millis = if busy {
+            (base / 2).max(10)
+        } else if !self.terminal_focused {
+            (base * 5).min(1000)
+        } else {
+            base
+        };
+        Duration::from_millis(millis)
+    }
+
     pub fn update_audio_position(&mut self, now: Instant) {
         if self.audio_player.is_playing {
             if let Some(start_time) = self.audio_start_time {
@@ -414,12 +1152,16 @@ impl App {
     async fn check_auth_status(&mut self) -> Result<()> {
         match self.api_client.get_auth_status().await {
             Ok(auth_status) => {
+                let reconnected = std::mem::replace(&mut self.disconnected, false);
                 let previously_authorized = self.auth_status
                     .as_ref()
                     .map(|s| s.authorized)
                     .unwrap_or(false);
 
                 self.auth_status = Some(auth_status.clone());
+                if self.clock_skew.is_none() {
+                    self.measure_clock_skew(&auth_status);
+                }
 
                 // Определяем новое состояние на основе статуса авторизации
                 match self.state {
@@ -449,25 +1191,41 @@ impl App {
                             self.state = AppState::PhoneInput;
                         }
                     }
-                    AppState::Main => {
-                        if !auth_status.authorized {
-                            self.state = AppState::PhoneInput;
-                            self.chats.clear();
-                            self.messages.clear();
-                            self.selected_chat = None;
-                        }
+                    AppState::Main if !auth_status.authorized => {
+                        self.state = AppState::PhoneInput;
+                        self.chats.clear();
+                        self.messages.clear();
+                        self.selected_chat = None;
+                        self.me = None;
+                        self.stop_update_polling();
                     }
                     _ => {}
                 }
 
-                // Если только что авторизовались, загружаем чаты
+                // Если только что авторизовались, загружаем данные аккаунта и чаты
                 if !previously_authorized && auth_status.authorized {
+                    self.load_me().await;
                     self.load_chats().await?;
+                    self.start_update_polling();
+                    if let Some(session) = self.pending_session.take() {
+                        self.restore_session(session).await?;
+                    }
+                }
+
+                // Бэкенд снова доступен - заменяем кэш и устаревшие данные живыми
+                if (self.offline || reconnected) && auth_status.authorized {
+                    self.refresh_data().await?;
                 }
             }
+            Err(e) if self.note_rate_limit(PollEndpoint::AuthStatus, &e) => {}
             Err(e) => {
                 log::error!("Ошибка проверки статуса авторизации: {}", e);
-                // Не меняем состояние при ошибке сети
+                self.disconnected = true;
+                self.needs_redraw = true;
+                // Не меняем состояние при ошибке сети; при старте без бэкенда показываем кэш
+                if self.state == AppState::Loading && self.show_cached_chats() {
+                    self.state = AppState::Main;
+                }
             }
         }
 
@@ -520,777 +1278,3194 @@ impl App {
         Ok(())
     }
 
-    async fn load_chats(&mut self) -> Result<()> {
-        match self.api_client.get_chats().await {
-            Ok(chats) => {
-                self.chats = chats;
-                if self.selected_chat_index >= self.chats.len() {
-                    self.selected_chat_index = 0;
-                }
-
-                // Автоматически выбираем первый чат если есть
-                if !self.chats.is_empty() && self.selected_chat.is_none() {
-                    self.selected_chat = Some(self.chats[0].clone());
-                    self.load_messages().await?;
-                }
-            }
-            Err(e) => {
-                log::error!("Ошибка загрузки чатов: {}", e);
-                self.show_error(&format!("Ошибка загрузки чатов: {}", e));
-            }
+    // Один раз сравниваем время бэкенда с местным и предупреждаем о заметном расхождении
+    fn measure_clock_skew(&mut self, auth_status: &AuthStatus) {
+        let Some(server_time) = auth_status.server_time
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        else {
+            return;
+        };
+        let skew = server_time.with_timezone(&chrono::Local) - chrono::Local::now();
+        self.clock_skew = Some(skew);
+        if skew.abs() >= CLOCK_SKEW_WARNING {
+            let direction = if skew > chrono::TimeDelta::zero() { "спешат" } else { "отстают" };
+            log::warn!("Часы бэкенда расходятся с местными на {}с", skew.num_seconds());
+            self.show_notice(&format!(
+                "Часы бэкенда {} на {} мин относительно местных - проверьте синхронизацию времени",
+                direction,
+                skew.num_minutes().abs()
+            ));
         }
-
-        Ok(())
     }
 
-    async fn load_messages(&mut self) -> Result<()> {
-        if let Some(chat) = &self.selected_chat {
-            let current_chat_id = chat.id;
-            let old_len = self.messages.len();
-            let was_at_bottom = old_len > 0 && self.selected_message_index == old_len - 1;
-            let old_selected_id = self.messages.get(self.selected_message_index).map(|m| m.id);
-
-            // Загружаем большое количество сообщений для полноценного листания
-            let message_limit = 200 as i32;
-            match self.api_client.get_messages(chat.id, Some(message_limit)).await {
-                Ok(messages) => {
-                    // Инвертируем порядок: новые сообщения внизу, старые вверху
-                    self.messages = messages.into_iter().rev().collect();
-
-                    // Выбор сообщения после обновления
-                    // Сохраняем позицию выделенного сообщения
-                    if self.messages.is_empty() {
-                        self.selected_message_index = 0;
-                        self.message_scroll_offset = 0;
-                    } else {
-                        // Пытаемся сохранить предыдущую позицию
-                        if let Some(old_id) = old_selected_id {
-                            // Ищем сообщение с тем же id
-                            if let Some(pos) = self.messages.iter().position(|m| m.id == old_id) {
-                                self.selected_message_index = pos;
-                            } else {
-                                // Если не нашли, выбираем последнее сообщение
-                                self.selected_message_index = self.messages.len() - 1;
-                            }
-                        } else {
-                            // Если нет предыдущего id, выбираем последнее
-                            self.selected_message_index = self.messages.len() - 1;
-                        }
-                        self.message_scroll_offset = 0; // Всегда начинаем с начала
-                    }
-
-                    // Загружаем пути к изображениям
-                    self.load_image_paths().await?;
+    // Текущее время по часам бэкенда: с ним сравниваются присланные им метки времени
+    pub fn server_now(&self) -> chrono::DateTime<chrono::Local> {
+        chrono::Local::now() + self.clock_skew.unwrap_or_default()
+    }
 
-                    // Загружаем пути к стикерам
-                    self.load_sticker_paths().await?;
+    // Текущее состояние интерфейса в виде сессии; до восстановления прошлой - она сама
+    fn current_session(&self) -> UiSession {
+        if let Some(pending) = &self.pending_session {
+            return pending.clone();
+        }
+        let overlay = match self.state {
+            AppState::Bookmarks => Some(SessionOverlay::Bookmarks),
+            AppState::GlobalSearch => Some(SessionOverlay::GlobalSearch),
+            AppState::ChatInfo => Some(SessionOverlay::ChatInfo),
+            _ => None,
+        };
+        UiSession {
+            chat_id: self.selected_chat.as_ref().map(|c| c.id),
+            message_id: self.messages.get(self.selected_message_index).map(|m| m.id).filter(|&id| id > 0),
+            focus_on_messages: self.focus_on_messages,
+            sidebar_hidden: self.sidebar_hidden,
+            overlay,
+        }
+    }
 
-                    // Отмечаем id чата, для которого загружены сообщения
-                    self.last_loaded_chat_id = Some(current_chat_id);
-                }
-                Err(e) => {
-                    log::error!("Ошибка загрузки сообщений: {}", e);
-                    self.show_error(&format!("Ошибка загрузки сообщений: {}", e));
-                }
-            }
+    fn save_session_debounced(&mut self, now: Instant) {
+        if self.current_session() == self.saved_session {
+            self.session_changed_at = None;
+            return;
         }
+        let changed_at = *self.session_changed_at.get_or_insert(now);
+        if now.duration_since(changed_at) >= SESSION_SAVE_DELAY {
+            self.save_session();
+        }
+    }
 
-        Ok(())
+    pub fn save_session(&mut self) {
+        let session = self.current_session();
+        session::save(&session);
+        self.saved_session = session;
+        self.session_changed_at = None;
     }
 
-    async fn load_image_paths(&mut self) -> Result<()> {
-        for msg in &self.messages {
-            if msg.r#type == "photo" {
-                if let Some(image_path) = &msg.image_path {
-                    if let Some(image_id) = msg.image_id {
-                        // Проверяем, не загружен ли уже путь к изображению
-                        if !self.image_paths.contains_key(&image_id) {
-                            self.image_paths.insert(image_id, image_path.clone());
-                        }
-                    }
+    // Открываем чат, сообщение, фокус и оверлей прошлой сессии, если они еще существуют
+    async fn restore_session(&mut self, session: UiSession) -> Result<()> {
+        let Some(index) = session.chat_id.and_then(|id| self.chats.iter().position(|c| c.id == id)) else {
+            return Ok(());
+        };
+        self.selected_chat_index = index;
+        self.select_chat().await?;
+        if let Some(position) = session.message_id.and_then(|id| self.messages.iter().position(|m| m.id == id)) {
+            self.selected_message_index = position;
+            self.follow_tail = position + 1 == self.messages.len();
+        }
+        self.focus_on_messages = session.focus_on_messages || self.sidebar_hidden;
+        match session.overlay {
+            Some(SessionOverlay::Bookmarks) => self.open_bookmarks(),
+            Some(SessionOverlay::GlobalSearch) => self.open_global_search(),
+            Some(SessionOverlay::ChatInfo) => {
+                if let Err(e) = self.show_chat_info().await {
+                    log::warn!("Не удалось восстановить информацию о чате: {}", e);
                 }
             }
+            None => {}
         }
-
         Ok(())
     }
 
-    async fn load_sticker_paths(&mut self) -> Result<()> {
-        for msg in &self.messages {
-            if msg.r#type == "sticker" {
-                if let Some(sticker_path) = &msg.sticker_path {
-                    if let Some(sticker_id) = msg.sticker_id {
-                        // Проверяем, не загружен ли уже путь к стикеру
-                        if !self.sticker_paths.contains_key(&sticker_id) {
-                            self.sticker_paths.insert(sticker_id, sticker_path.clone());
+    fn start_update_polling(&mut self) {
+        if !self.config.long_poll || self.updates_task.is_some() {
+            return;
+        }
+        let api_client = self.api_client.clone();
+        let tx = self.updates_tx.clone();
+        let handle = tokio::spawn(async move {
+            let mut since = 0;
+            loop {
+                match api_client.poll_updates(since, LONG_POLL_TIMEOUT_SECS).await {
+                    Ok(updates) => {
+                        since = updates.last_update_id;
+                        if tx.send(updates).is_err() {
+                            break;
                         }
                     }
+                    Err(e) if matches!(e.downcast_ref::<ApiError>(), Some(ApiError::Unsupported)) => {
+                        log::info!("Бэкенд не поддерживает /api/updates, остаемся на ручном обновлении");
+                        break;
+                    }
+                    Err(e) => {
+                        let delay = ApiError::retry_after(&e).unwrap_or(Duration::from_secs(5));
+                        log::warn!("Ошибка long-poll обновлений: {}, повтор через {}с", e, delay.as_secs());
+                        tokio::time::sleep(delay).await;
+                    }
                 }
             }
-        }
-
-        Ok(())
+        });
+        self.updates_task = Some(handle.abort_handle());
     }
 
-    pub async fn refresh_data(&mut self) -> Result<()> {
-        self.load_chats().await?;
-        if self.selected_chat.is_some() {
-            self.load_messages().await?;
+    fn stop_update_polling(&mut self) {
+        if let Some(task) = self.updates_task.take() {
+            task.abort();
         }
-        Ok(())
     }
 
-    pub fn move_chat_selection(&mut self, direction: i32) {
-        if self.chats.is_empty() {
+    // F: удалить локальный файл фото, стикера или видео и скачать его с бэкенда заново
+    pub fn redownload_selected_media(&mut self) {
+        let Some(msg) = self.messages.get(self.selected_message_index) else {
             return;
-        }
-
-        let new_index = if direction > 0 {
-            (self.selected_chat_index + 1).min(self.chats.len() - 1)
-        } else {
-            self.selected_chat_index.saturating_sub(1)
         };
-
-        if new_index != self.selected_chat_index {
-            self.selected_chat_index = new_index;
+        let target = match msg.r#type.as_str() {
+            "photo" => msg.image_id.zip(msg.image_path.clone()).map(|(id, path)| ("images", id, path)),
+            "sticker" => msg.sticker_id.zip(msg.sticker_path.clone()).map(|(id, path)| ("stickers", id, path)),
+            "video" => msg.video_id.zip(msg.video_path.clone()).map(|(id, path)| ("videos", id, path)),
+            _ => {
+                self.show_notice("Перезагрузить можно только фото, стикер или видео");
+                return;
+            }
+        };
+        let Some((kind, media_id, path)) = target else {
+            self.show_notice("У сообщения нет файла для перезагрузки");
+            return;
+        };
+        if self.is_downloading(&path) {
+            self.show_notice("Файл уже загружается");
+            return;
         }
+        self.spawn_redownload(kind, media_id, path);
+        self.show_notice("Перезагрузка файла…");
     }
 
-    pub fn move_message_selection(&mut self, direction: i32, visible_capacity: usize) {
-        if self.messages.is_empty() {
-            return;
-        }
-        let last_index = self.messages.len() - 1;
-        let new_index = if direction > 0 {
-            (self.selected_message_index + 1).min(last_index)
-        } else {
-            self.selected_message_index.saturating_sub(1)
-        };
-        if new_index != self.selected_message_index {
-            self.selected_message_index = new_index;
-            // Обновляем прокрутку, чтобы выделение было видно
-            if self.selected_message_index < self.message_scroll_offset {
-                self.message_scroll_offset = self.selected_message_index;
-            } else if self.selected_message_index >= self.message_scroll_offset + visible_capacity {
-                let overshoot = self.selected_message_index - (self.message_scroll_offset + visible_capacity) + 1;
-                self.message_scroll_offset += overshoot;
+    fn process_corrupt_media(&mut self) {
+        for path in crate::ui::take_corrupt_media() {
+            if !self.corrupt_media_handled.insert(path.clone()) {
+                continue;
+            }
+            match self.config.on_corrupt_media {
+                CorruptMediaPolicy::Keep => {}
+                CorruptMediaPolicy::Delete => {
+                    log::info!("Удаляем поврежденный файл {}", path);
+                    let _ = std::fs::remove_file(&path);
+                    crate::ui::clear_image_cache();
+                }
+                CorruptMediaPolicy::Redownload => match self.media_source(&path) {
+                    Some((kind, media_id)) if !self.is_downloading(&path) && self.config.auto_download_allowed(media_type_of(kind)) => {
+                        self.spawn_redownload(kind, media_id, path)
+                    }
+                    Some(_) => {}
+                    None => log::warn!("Не удалось определить, откуда скачать {} заново", path),
+                },
             }
         }
     }
 
-    pub fn toggle_focus(&mut self) {
-        self.focus_on_messages = !self.focus_on_messages;
+    // Откуда скачать файл сообщения: вид медиа на бэкенде и id
+    fn media_source(&self, path: &str) -> Option<(&'static str, i64)> {
+        self.messages.iter().find_map(|msg| {
+            let is = |p: &Option<String>| p.as_deref() == Some(path);
+            if is(&msg.image_path) {
+                msg.image_id.map(|id| ("images", id))
+            } else if is(&msg.sticker_path) {
+                msg.sticker_id.map(|id| ("stickers", id))
+            } else if is(&msg.video_path) {
+                msg.video_id.map(|id| ("videos", id))
+            } else if is(&msg.voice_path) {
+                msg.voice_id.map(|id| ("voices", id))
+            } else if is(&msg.audio_path) {
+                msg.audio_id.map(|id| ("audios", id))
+            } else {
+                None
+            }
+        })
     }
 
-    pub fn focus_messages(&mut self) {
-        self.focus_on_messages = true;
+    // При открытии вручную докачиваем файл, который не скачан заранее (auto_download).
+    // true - файл на месте, false - загрузка идет
+    fn ensure_media_downloaded(&mut self, path: Option<String>) -> bool {
+        let Some(path) = path.filter(|p| !std::path::Path::new(p).exists()) else {
+            return true;
+        };
+        if self.is_downloading(&path) {
+            self.show_notice("Файл загружается…");
+            return false;
+        }
+        match self.media_source(&path) {
+            Some((kind, media_id)) => {
+                self.spawn_redownload(kind, media_id, path);
+                self.show_notice("Файл загружается…");
+                false
+            }
+            None => true,
+        }
     }
 
-    pub fn focus_chats(&mut self) {
-        self.focus_on_messages = false;
+    // Удаляем локальный файл и скачиваем его в фоне; результат приходит в redownload_rx
+    fn spawn_redownload(&mut self, kind: &'static str, media_id: i64, path: String) {
+        log::info!("Перезагружаем {} (id {}) в {}", kind, media_id, path);
+        let _ = std::fs::remove_file(&path);
+        crate::ui::clear_image_cache();
+
+        let api_client = self.api_client.clone();
+        let tx = self.redownload_tx.clone();
+        let target_path = path.clone();
+        let handle = tokio::spawn(async move {
+            let result = async {
+                let data = api_client.download_media(kind, media_id).await?;
+                // Как и для карт: сначала .part, итоговое имя только после полной записи
+                let part_path = partial_download_path(&target_path);
+                tokio::fs::write(&part_path, &data).await?;
+                tokio::fs::rename(&part_path, &target_path).await?;
+                Ok(())
+            }
+            .await;
+            let _ = tx.send((target_path, result));
+        });
+        self.downloads.insert(path, handle.abort_handle());
     }
 
-    pub fn open_selected_message(&mut self) {
-        if self.messages.is_empty() {
-            log::warn!("Нет сообщений для открытия");
+    // E: спросить папку для экспорта медиа открытого чата
+    pub fn start_export_input(&mut self) {
+        let Some(chat) = &self.selected_chat else {
+            self.show_notice("Сначала выберите чат (Enter в списке чатов)");
+            return;
+        };
+        if self.export_progress.is_some() {
+            self.show_notice("Экспорт уже идет");
             return;
         }
-        if let Some(msg) = self.messages.get(self.selected_message_index) {
-            log::info!("Открываем сообщение типа: {}, id: {}", msg.r#type, msg.id);
+        let folder: String = chat.title
+            .chars()
+            .map(|c| if c == '/' || c.is_control() { '_' } else { c })
+            .collect();
+        let base = dirs::download_dir()
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(|| std::path::PathBuf::from("."));
+        self.export_input = base.join("vi-tg").join(folder).to_string_lossy().into_owned();
+        self.state = AppState::ExportInput;
+    }
 
-            if msg.r#type == "photo" {
-                if let Some(path) = &msg.image_path {
-                    log::info!("Открываем фото: {}", path);
-                    self.preview_image_path = Some(path.clone());
-                    self.state = AppState::ImagePreview;
-                } else {
-                    log::warn!("Фото сообщение без пути к файлу");
-                }
-            } else if msg.r#type == "video" {
-                log::info!("Открываем видео. Путь к превью: {:?}, путь к видео: {:?}", msg.video_preview_path, msg.video_path);
+    pub fn cancel_export_input(&mut self) {
+        self.export_input.clear();
+        self.state = AppState::Main;
+    }
 
-                // For video preview, use the preview image (JPEG) and show overlay
-                if let Some(preview_path) = &msg.video_preview_path {
-                    self.preview_image_path = Some(preview_path.clone());
-                    // Store video path for later playback when Enter is pressed in ImagePreview
-                    self.preview_video_path = Some(msg.video_path.clone().unwrap_or_default());
-                    self.state = AppState::ImagePreview;
-                    log::info!("Установлен режим ImagePreview для видео с превью");
-                } else if let Some(video_path) = &msg.video_path {
-                    // Fallback to video file if no preview is available
-                    self.preview_video_path = Some(video_path.clone());
-                    self.state = AppState::VideoPreview;
-                    log::info!("Установлен режим VideoPreview для видео без превью");
-                } else {
-                    log::warn!("Видео сообщение без путей к файлам");
-                }
-            } else if msg.r#type == "sticker" {
-                if let Some(path) = &msg.sticker_path {
-                    log::info!("Открываем стикер: {}", path);
-                    self.preview_image_path = Some(path.clone());
-                    self.state = AppState::ImagePreview;
-                } else {
-                    log::warn!("Стикер сообщение без пути к файлу");
-                }
-            } else if msg.r#type == "voice" {
-                log::info!("Воспроизводим голосовое сообщение");
-                log::info!("Проверяем voice_path: {:?}", msg.voice_path);
-                if let Err(e) = self.play_voice() {
-                    log::error!("Ошибка воспроизведения голосового сообщения: {}", e);
-                    self.show_error(&format!("Ошибка воспроизведения голосового сообщения: {}", e));
-                }
-            } else if msg.r#type == "audio" {
-                log::info!("Воспроизводим аудио сообщение");
-                log::info!("Проверяем audio_path: {:?}", msg.audio_path);
-                if let Err(e) = self.play_audio() {
-                    log::error!("Ошибка воспроизведения аудио сообщения: {}", e);
-                    self.show_error(&format!("Ошибка воспроизведения аудио сообщения: {}", e));
+    // Копируем уже скачанные бэкендом файлы загруженной истории, недостающие скачиваем заново
+    pub fn start_media_export(&mut self) -> Result<()> {
+        let dir = std::mem::take(&mut self.export_input).trim().to_string();
+        self.state = AppState::Main;
+        if dir.is_empty() {
+            return Ok(());
+        }
+        let items: Vec<ExportItem> = self.messages.iter().filter_map(export_item).collect();
+        if items.is_empty() {
+            self.show_notice("В загруженной истории нет медиа для экспорта");
+            return Ok(());
+        }
+        std::fs::create_dir_all(&dir)?;
+
+        let total = items.len();
+        self.export_progress = Some((0, 0, total));
+        self.export_dir = Some(dir.clone());
+        let api_client = self.api_client.clone();
+        let tx = self.export_tx.clone();
+        tokio::spawn(async move {
+            use futures::StreamExt;
+            let dir = std::path::PathBuf::from(dir);
+            let mut results = futures::stream::iter(items)
+                .map(|item| {
+                    let api_client = api_client.clone();
+                    let dir = dir.clone();
+                    async move { export_one(&api_client, &item, &dir).await }
+                })
+                .buffer_unordered(EXPORT_CONCURRENCY);
+            let (mut saved, mut failed) = (0, 0);
+            while let Some(result) = results.next().await {
+                match result {
+                    Ok(()) => saved += 1,
+                    Err(e) => {
+                        log::error!("Ошибка экспорта файла: {}", e);
+                        failed += 1;
+                    }
                 }
-            } else if msg.r#type == "location" {
-                log::info!("Открываем сообщение с местоположением");
-                log::info!("Координаты: lat={:?}, lng={:?}, title: {:?}", msg.location_lat, msg.location_lng, msg.location_title);
-                log::info!("Адрес: {:?}", msg.location_address);
-                log::info!("Карта: {:?}", msg.location_map_path);
+                let _ = tx.send((saved, failed, total));
+            }
+        });
+        Ok(())
+    }
 
-                // Show location preview - this will show detailed location info
-                if let Err(e) = self.open_location() {
-                    log::error!("Ошибка открытия местоположения: {}", e);
-                    self.show_error(&format!("Ошибка открытия местоположения: {}", e));
-                }
+    fn process_export_progress(&mut self) {
+        while let Ok(progress) = self.export_rx.try_recv() {
+            self.export_progress = Some(progress);
+            self.needs_redraw = true;
+        }
+        let Some((saved, failed, total)) = self.export_progress else {
+            return;
+        };
+        if saved + failed == total {
+            self.export_progress = None;
+            let dir = self.export_dir.take().unwrap_or_default();
+            if failed > 0 {
+                self.show_notice(&format!("Экспорт завершен: {} из {} файлов в {} (ошибок: {})", saved, total, dir, failed));
             } else {
-                log::info!("Неизвестный тип сообщения: {}", msg.r#type);
+                self.show_notice(&format!("Экспорт завершен: {} файлов в {}", saved, dir));
             }
-        } else {
-            log::error!("Сообщение не найдено по индексу {}", self.selected_message_index);
         }
     }
 
-    pub fn close_image_preview(&mut self) {
-        self.preview_image_path = None;
-        self.preview_video_path = None; // Clear video path too
-        self.state = AppState::Main;
+    fn process_redownload_results(&mut self) {
+        while let Ok((path, result)) = self.redownload_rx.try_recv() {
+            self.downloads.remove(&path);
+            self.needs_redraw = true;
+            match result {
+                Ok(()) => {
+                    // Старое изображение могло остаться в кэше протоколов под тем же путем
+                    crate::ui::clear_image_cache();
+                    self.show_notice("Файл перезагружен");
+                }
+                Err(e) => {
+                    log::error!("Ошибка перезагрузки {}: {}", path, e);
+                    self.show_error(&format!("Ошибка перезагрузки файла: {}", e));
+                }
+            }
+        }
     }
 
-    pub fn close_video_preview(&mut self) {
-        self.preview_video_path = None;
-        self.state = AppState::Main;
-    }
+    fn process_updates(&mut self) {
+        while let Ok(updates) = self.updates_rx.try_recv() {
+            self.needs_redraw = true;
+            self.offline = false;
+            let selected_chat_id = self.selected_chat.as_ref().map(|c| c.id);
 
-    pub fn play_video(&mut self) -> Result<()> {
-        // Get the video file path - prefer the stored preview_video_path if available
-        // This is important for fullscreen preview mode where we have a stored path
-        let video_path = if let Some(preview_path) = &self.preview_video_path {
-            if !preview_path.is_empty() {
-                log::info!("Используем сохраненный путь к видео из превью: {}", preview_path);
-                preview_path.clone()
-            } else {
-                // Fallback to getting path from current message
-                if let Some(msg) = self.messages.get(self.selected_message_index) {
-                    if let Some(msg_video_path) = &msg.video_path {
-                        log::info!("Используем путь к видео из текущего сообщения: {}", msg_video_path);
-                        msg_video_path.clone()
-                    } else {
-                        log::error!("Путь к видео файлу не найден в сообщении");
-                        return Err(anyhow::anyhow!("Путь к видео файлу не найден"));
-                    }
-                } else {
-                    log::error!("Сообщение не найдено по индексу {}", self.selected_message_index);
-                    return Err(anyhow::anyhow!("Сообщение не найдено"));
+            for message in updates.new_messages {
+                self.notify_new_message(&message, selected_chat_id);
+                if let Some(chat) = self.chats.iter_mut().find(|c| c.id == message.chat_id) {
+                    chat.last_message = Some(message.text.clone());
+                }
+                if Some(message.chat_id) == selected_chat_id {
+                    self.messages.push(message);
                 }
             }
-        } else {
-            // No stored preview path, get from current message
-            if let Some(msg) = self.messages.get(self.selected_message_index) {
-                if let Some(msg_video_path) = &msg.video_path {
-                    log::info!("Пытаемся воспроизвести видео из сообщения: {}", msg_video_path);
-                    msg_video_path.clone()
-                } else {
-                    log::error!("Путь к видео файлу не найден в сообщении");
-                    return Err(anyhow::anyhow!("Путь к видео файлу не найден"));
+            for update in updates.unread {
+                if let Some(chat) = self.chats.iter_mut().find(|c| c.id == update.chat_id) {
+                    chat.unread = update.unread;
                 }
-            } else {
-                log::error!("Сообщение не найдено по индексу {}", self.selected_message_index);
-                return Err(anyhow::anyhow!("Сообщение не найдено"));
             }
-        };
-
-        log::info!("Пытаемся воспроизвести видео: {}", video_path);
 
-        // Проверяем, существует ли файл
-        if !std::path::Path::new(&video_path).exists() {
-            return Err(anyhow::anyhow!("Файл видео не существует: {}", video_path));
+            if selected_chat_id.is_some() {
+                dedup_messages_by_id(&mut self.messages);
+                if self.follow_tail && self.config.auto_scroll_on_new && !self.messages.is_empty() {
+                    self.selected_message_index = self.messages.len() - 1;
+                }
+            }
         }
+    }
 
-        // Пробуем получить ID окна терминала для overlay
-        let window_id = self.get_terminal_window_id();
-        log::info!("ID окна терминала: {:?}", window_id);
+    // Уведомление о чужом сообщении, которое не видно на экране прямо сейчас
+    fn notify_new_message(&self, message: &Message, selected_chat_id: Option<i64>) {
+        if !self.config.notifications {
+            return;
+        }
+        let from_me = self.me.as_ref().is_some_and(|me| message.from_id == Some(me.id));
+        if from_me || (Some(message.chat_id) == selected_chat_id && self.terminal_focused) {
+            return;
+        }
+        let title = self.chats
+            .iter()
+            .find(|c| c.id == message.chat_id)
+            .map(|c| c.title.clone())
+            .unwrap_or_else(|| message.from.clone());
+        let body = if message.text.is_empty() {
+            format!("{}: [{}]", message.from, message.r#type)
+        } else {
+            format!("{}: {}", message.from, message.text)
+        };
 
-        // Создаем строку для window ID заранее, чтобы избежать временных значений
-        let window_id_str = window_id.unwrap_or(0).to_string();
+        let (program, args) = match self.config.notification_command.split_first() {
+            Some((program, args)) => (program.clone(), args.to_vec()),
+            None => ("notify-send".to_string(), vec!["--app-name=vi-tg".to_string()]),
+        };
+        // Ждем завершения в отдельной задаче, чтобы не оставлять зомби-процессов
+        tokio::spawn(async move {
+            let status = tokio::process::Command::new(&program)
+                .args(&args)
+                .arg(&title)
+                .arg(&body)
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+                .await;
+            if let Err(e) = status {
+                log::warn!("Не удалось запустить {} для уведомления: {}", program, e);
+            }
+        });
+    }
 
-        // Пробуем разные стратегии запуска mpv для tiling WM
-        let strategies = vec![
-            // Стратегия 1: Floating window for tiling WM (dwm)
-            (Some("floating"), vec![
-                "--no-terminal",
-                "--input-ipc-server=/tmp/mpv-socket",
-                "--force-window=yes",
-                "--keep-open=no",
-                "--geometry=800x600+100+100"  // Set specific position
-            ]),
-            // Стратегия 2: Terminal-based with audio only (for headless)
-            (Some("audio-only"), vec![
-                "--no-terminal",
-                "--input-ipc-server=/tmp/mpv-socket",
-                "--force-window=no",
-                "--audio-device=alsa",
-                "--keep-open=no",
-                "--no-video"  // Audio only
-            ]),
-            // Стратегия 3: Minimal config for compatibility
-            (Some("minimal"), vec![
-                "--no-config",
-                "--no-terminal",
-                "--force-window=yes",
-                "--keep-open=no",
-                "--geometry=50%x50%"  // Center, half size
-            ])
-        ];
+    async fn load_me(&mut self) {
+        match self.api_client.get_me().await {
+            Ok(me) => self.me = Some(me),
+            Err(e) => log::error!("Ошибка загрузки данных аккаунта: {}", e),
+        }
+    }
 
-        for (strategy_name, args) in strategies {
-            if strategy_name.is_none() && window_id.is_none() {
-                continue; // Skip overlay strategy if no window ID
+    // Подпись аккаунта: имя, @username и телефон; без /api/self берем то, что есть в статусе авторизации
+    pub fn account_label(&self) -> Option<String> {
+        let (name, username, phone) = match &self.me {
+            Some(me) => (Some(me.name.clone()), me.username.clone(), me.phone.clone()),
+            None => {
+                let status = self.auth_status.as_ref().filter(|s| s.authorized)?;
+                (status.name.clone(), None, status.phone_number.clone())
             }
+        };
+        let details: Vec<String> = username
+            .filter(|u| !u.is_empty())
+            .map(|u| format!("@{}", u))
+            .into_iter()
+            .chain(phone.filter(|p| !p.is_empty()))
+            .collect();
+        match (name.filter(|n| !n.is_empty()), details.is_empty()) {
+            (Some(name), true) => Some(name),
+            (Some(name), false) => Some(format!("{} ({})", name, details.join(", "))),
+            (None, false) => Some(details.join(", ")),
+            (None, true) => None,
+        }
+    }
 
-            log::info!("Пробуем стратегию: {:?}", strategy_name);
+    async fn load_chats(&mut self) -> Result<()> {
+        if self.is_backed_off(PollEndpoint::Chats) {
+            return Ok(());
+        }
+        match self.api_client.get_chats(self.config.chat_fetch_limit).await {
+            Ok(chats) => {
+                cache::save_chats(&chats);
+                self.chats = chats;
+                self.sort_chats();
+                if self.selected_chat_index >= self.chats.len() {
+                    self.selected_chat_index = 0;
+                }
 
-            let mut cmd = std::process::Command::new("mpv");
-            for arg in &args {
-                if arg != &"0" {  // Skip the dummy window ID
-                    cmd.arg(arg);
+                // Автоматически выбираем первый чат если есть
+                if !self.chats.is_empty() && self.selected_chat.is_none() {
+                    self.selected_chat = Some(self.chats[0].clone());
+                    self.load_messages().await?;
                 }
             }
+            Err(e) if self.note_rate_limit(PollEndpoint::Chats, &e) => {}
+            Err(e) => {
+                log::error!("Ошибка загрузки чатов: {}", e);
+                if !self.show_cached_chats() {
+                    self.show_error(&format!("Ошибка загрузки чатов: {}", e));
+                }
+            }
+        }
 
-            // Set environment for better compatibility
-            cmd.env("DISPLAY", ":0")
-               .env("XDG_SESSION_TYPE", "tty");
-
-            cmd.arg(&video_path);
-            cmd.stdout(std::process::Stdio::null())
-               .stderr(std::process::Stdio::null());
+        Ok(())
+    }
 
-            log::info!("Запускаем команду: {:?}", cmd);
-            let result = cmd.spawn();
-
-            match result {
-                Ok(child) => {
-                    log::info!("mpv успешно запущен стратегией {:?}, PID: {}", strategy_name, child.id());
-                    // Wait a moment and check if process is still running
-                    std::thread::sleep(std::time::Duration::from_millis(500));
+    async fn load_messages(&mut self) -> Result<()> {
+        if self.is_backed_off(PollEndpoint::Messages) {
+            return Ok(());
+        }
+        if let Some(chat) = &self.selected_chat {
+            let current_chat_id = chat.id;
+            let old_selected_id = self.messages.get(self.selected_message_index).map(|m| m.id);
+            let old_selected_index = self.selected_message_index;
+            let old_ids: Vec<i32> = self.messages.iter().map(|m| m.id).collect();
+            // Внизу ленты того же чата - новые сообщения можно показать, не сбивая чтение истории
+            let same_chat = self.messages.first().map(|m| m.chat_id) == Some(current_chat_id);
+            let was_at_bottom = !same_chat
+                || self.follow_tail
+                || self.selected_message_index + 1 >= self.messages.len();
+            // Сообщения, которые еще отправляются, сохраняем до подтверждения сервером,
+            // запланированные - до наступления времени отправки
+            let pending: Vec<Message> = self.messages
+                .iter()
+                .filter(|m| m.chat_id == current_chat_id && (m.send_state == Some(SendState::Sending) || is_pending_scheduled(m, self.server_now())))
+                .cloned()
+                .collect();
 
-                    // Check if process is still alive
-                    let pid = child.id();
-                    let is_alive = std::process::Command::new("kill")
-                        .arg("-0")  // Signal 0 just checks if process exists
-                        .arg(pid.to_string())
-                        .status()
-                        .is_ok();
+            // Загружаем большое количество сообщений для полноценного листания
+            let started = Instant::now();
+            let loaded = self.api_client.get_messages(chat.id, Some(self.history_limit), &self.config.skipped_downloads()).await;
+            self.note_latency("загрузка", started.elapsed());
+            match loaded {
+                Ok(messages) => {
+                    // Инвертируем порядок: новые сообщения внизу, старые вверху
+                    self.messages = messages.into_iter().rev().collect();
+                    // Подтвержденные локальные копии заменяются серверными, неподтвержденные остаются внизу
+                    self.messages.extend(pending);
+                    dedup_messages_by_id(&mut self.messages);
+                    self.offline = false;
+                    let confirmed: Vec<Message> = self.messages.iter().filter(|m| m.id > 0).cloned().collect();
+                    cache::save_messages(current_chat_id, &confirmed);
 
-                    if is_alive {
-                        log::info!("mpv процесс работает нормально");
+                    // Выбор сообщения после обновления
+                    // Сохраняем позицию выделенного сообщения
+                    if self.messages.is_empty() {
+                        self.selected_message_index = 0;
+                        self.message_scroll_offset = 0;
                     } else {
-                        log::info!("mpv процесс завершен быстро, возможно проблема с окном");
+                        // Пытаемся сохранить предыдущую позицию
+                        if was_at_bottom && (self.config.auto_scroll_on_new || !same_chat) {
+                            self.selected_message_index = self.messages.len() - 1;
+                        } else if let Some(old_id) = old_selected_id {
+                            // Ищем сообщение с тем же id
+                            if let Some(pos) = self.messages.iter().position(|m| m.id == old_id) {
+                                self.selected_message_index = pos;
+                            } else if let Some(pos) = same_chat
+                                .then(|| nearest_remaining(&old_ids, old_selected_index, &self.messages))
+                                .flatten()
+                            {
+                                // Выбранное сообщение удалено на сервере - остаемся рядом с ним
+                                self.selected_message_index = pos;
+                                self.show_notice("Выбранное сообщение удалено");
+                            } else {
+                                // Если не нашли, выбираем последнее сообщение
+                                self.selected_message_index = self.messages.len() - 1;
+                            }
+                        } else {
+                            // Если нет предыдущего id, выбираем последнее
+                            self.selected_message_index = self.messages.len() - 1;
+                        }
+                        self.message_scroll_offset = 0; // Всегда начинаем с начала
                     }
-                    return Ok(());
-                }
-                Err(e) => {
-                    log::warn!("Стратегия {:?} не сработала: {}", strategy_name, e);
-                    continue;
-                }
-            }
-        }
-
-        // If all strategies failed, try alternative video players
-        log::info!("Все стратегии mpv провалились, пробуем альтернативные плееры");
-
-        let alternative_players = vec![
-            ("vlc", vec!["--no-video-title", "--play-and-exit"]),
-            ("ffplay", vec!["-autoexit", "-fs"]), // fullscreen
-            ("mplayer", vec!["-fs", "-really-quiet"]),
-            ("xine", vec!["-f", "-q"]), // fullscreen, quiet
-        ];
 
-        for (player, args) in alternative_players {
-            log::info!("Пробуем альтернативный плеер: {}", player);
+                    self.drop_stale_reply_target();
 
-            let mut cmd = std::process::Command::new(player);
-            for arg in &args {
-                cmd.arg(arg);
-            }
-            cmd.arg(&video_path);
-            cmd.stdout(std::process::Stdio::null())
-               .stderr(std::process::Stdio::null());
+                    // Загружаем пути к изображениям
+                    self.load_image_paths().await?;
 
-            log::info!("Запускаем команду: {:?}", cmd);
-            let result = cmd.spawn();
+                    // Загружаем пути к стикерам
+                    self.load_sticker_paths().await?;
 
-            match result {
-                Ok(child) => {
-                    log::info!("{} успешно запущен, PID: {}", player, child.id());
-                    return Ok(());
+                    // Отмечаем id чата, для которого загружены сообщения
+                    self.last_loaded_chat_id = Some(current_chat_id);
                 }
+                Err(e) if self.note_rate_limit(PollEndpoint::Messages, &e) => {}
                 Err(e) => {
-                    log::warn!("Не удалось запустить {}: {}", player, e);
-                    continue;
+                    log::error!("Ошибка загрузки сообщений: {}", e);
+                    if !self.show_cached_messages(current_chat_id) {
+                        self.show_error(&format!("Ошибка загрузки сообщений: {}", e));
+                    }
                 }
             }
         }
 
-        // If all strategies failed, try one more time with minimal mpv options
-        log::info!("Все плееры провалились, пробуем минимальную конфигурацию mpv");
-        let result = std::process::Command::new("mpv")
-            .arg("--no-config")
-            .arg("--no-terminal")
-            .arg(&video_path)
-            .stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .spawn();
+        Ok(())
+    }
 
-        match result {
-            Ok(child) => {
-                log::info!("mpv запущен с минимальной конфигурацией, PID: {}", child.id());
-                Ok(())
-            }
-            Err(e) => {
-                log::error!("Не удалось запустить mpv даже с минимальной конфигурацией: {}", e);
-                Err(anyhow::anyhow!("Не удалось запустить видео плеер. Установите mpv, vlc, ffplay, mplayer или xine"))
+    // Показываем сохраненный список чатов, если бэкенд недоступен
+    fn show_cached_chats(&mut self) -> bool {
+        let Some(chats) = cache::load_chats() else {
+            return false;
+        };
+        if self.chats.is_empty() {
+            self.chats = chats;
+            self.sort_chats();
+        }
+        if self.selected_chat.is_none() {
+            if let Some(chat) = self.chats.first().cloned() {
+                let chat_id = chat.id;
+                self.selected_chat = Some(chat);
+                self.show_cached_messages(chat_id);
             }
         }
+        self.offline = true;
+        true
     }
 
-    pub fn play_voice(&mut self) -> Result<()> {
-        // Останавливаем текущее воспроизведение, если оно есть
-        if self.audio_player.is_playing {
-            self.audio_player.stop();
-            log::info!("Остановлено предыдущее воспроизведение перед запуском нового");
+    fn is_backed_off(&self, endpoint: PollEndpoint) -> bool {
+        self.backoff_until
+            .get(&endpoint)
+            .is_some_and(|until| Instant::now() < *until)
+    }
+
+    // Ответ 429 не показываем как ошибку, а откладываем следующие запросы к этому эндпоинту
+    fn note_rate_limit(&mut self, endpoint: PollEndpoint, error: &anyhow::Error) -> bool {
+        let Some(delay) = ApiError::retry_after(error) else {
+            return false;
+        };
+        log::warn!("Бэкенд ограничил запросы ({:?}), повтор через {}с", endpoint, delay.as_secs());
+        self.backoff_until.insert(endpoint, Instant::now() + delay);
+        if endpoint != PollEndpoint::AuthStatus {
+            self.show_notice(&format!("Слишком много запросов, повтор через {}с", delay.as_secs()));
         }
+        true
+    }
 
-        // Get the actual voice file path from the current message
-        if let Some(msg) = self.messages.get(self.selected_message_index) {
-            if let Some(voice_path) = &msg.voice_path {
-                log::info!("Пытаемся воспроизвести голосовое сообщение: {}", voice_path);
+    // Показываем сохраненные сообщения чата до первой успешной живой загрузки
+    fn show_cached_messages(&mut self, chat_id: i64) -> bool {
+        let Some(messages) = cache::load_messages(chat_id) else {
+            return false;
+        };
+        // Уже показанные сообщения этого чата (живые или из кэша) не трогаем
+        if self.messages.first().map(|m| m.chat_id) != Some(chat_id) {
+            self.messages = messages;
+            self.selected_message_index = self.messages.len().saturating_sub(1);
+            self.message_scroll_offset = 0;
+            self.last_loaded_chat_id = Some(chat_id);
+        }
+        self.offline = true;
+        true
+    }
 
-                // Проверяем, существует ли файл
-                if !std::path::Path::new(voice_path).exists() {
-                    return Err(anyhow::anyhow!("Файл голосового сообщения не существует: {}", voice_path));
+    async fn load_image_paths(&mut self) -> Result<()> {
+        for msg in &self.messages {
+            if msg.r#type == "photo" {
+                if let Some(image_path) = &msg.image_path {
+                    if let Some(image_id) = msg.image_id {
+                        // Проверяем, не загружен ли уже путь к изображению
+                        self.image_paths.entry(image_id).or_insert_with(|| image_path.clone());
+                    }
                 }
+            }
+        }
 
-                // Проверяем, является ли это то же самое сообщение, что уже играет
-                if self.audio_player.is_current_message(msg.id) && self.audio_player.is_playing {
-                    // Останавливаем текущее воспроизведение
-                    self.audio_player.stop();
-                    log::info!("Остановлено воспроизведение голосового сообщения");
-                    return Ok(());
+        Ok(())
+    }
+
+    async fn load_sticker_paths(&mut self) -> Result<()> {
+        for msg in &self.messages {
+            if msg.r#type == "sticker" {
+                if let Some(sticker_path) = &msg.sticker_path {
+                    if let Some(sticker_id) = msg.sticker_id {
+                        // Проверяем, не загружен ли уже путь к стикеру
+                        self.sticker_paths.entry(sticker_id).or_insert_with(|| sticker_path.clone());
+                    }
                 }
+            }
+        }
 
+        Ok(())
+    }
 
+    pub async fn refresh_data(&mut self) -> Result<()> {
+        self.load_chats().await?;
+        if self.selected_chat.is_some() {
+            self.load_messages().await?;
+        }
+        Ok(())
+    }
 
-                // Инициализируем состояние аудио плеера
-                self.audio_player.current_message_id = Some(msg.id);
-                self.audio_player.current_position = Duration::ZERO;
-                self.audio_player.total_duration = msg.voice_duration.map(|d| Duration::from_secs(d as u64));
-                self.audio_player.is_playing = true;
-                self.audio_player.current_file_path = Some(voice_path.clone()); // Store file path for restart functionality
+    pub fn set_chat_selection(&mut self, index: usize) {
+        if self.chats.is_empty() {
+            return;
+        }
+        self.selected_chat_index = index.min(self.chats.len() - 1);
+    }
 
-                // Пробуем разные плееры для воспроизведения аудио с усилением громкости
-                // ffplay как основной (работает надежно), mpv как запасной
-                let audio_players = vec![
-                    ("ffplay", vec!["-nodisp", "-autoexit", "-af", "volume=10"]),
-                    ("mpv", vec![
-                        "--volume=200",
-                        "--input-ipc-server=/tmp/mpv-socket",
-                        "--input-ipc-server=/tmp/mpv-socket:rw"  // Явно указываем права на чтение/запись
-                    ]), // Для перемотки
-                    ("mplayer", vec!["-really-quiet", "-noconsolecontrols", "-af", "volume=10"]),
-                    ("play", vec!["-v", "10"]), // SoX play with 10x volume boost
-                    ("paplay", vec![]), // PulseAudio player (no volume control)
-                ];
+    // Индексы в chats, которые проходят фильтр. selected_chat_index по-прежнему указывает в chats,
+    // поэтому выбор чата и остальные действия не зависят от фильтра
+    pub fn visible_chat_indices(&self) -> Vec<usize> {
+        let filter = self.chat_filter.trim().to_lowercase();
+        (0..self.chats.len())
+            .filter(|&i| filter.is_empty() || self.chats[i].title.to_lowercase().contains(&filter))
+            .collect()
+    }
 
-                for (player, args) in audio_players {
-                    log::info!("Пробуем плеер: {}", player);
+    // "/" в списке чатов: фильтр применяется по мере ввода
+    pub fn start_chat_filter(&mut self) {
+        self.state = AppState::ChatFilterInput;
+    }
 
-                    let mut cmd = std::process::Command::new(player);
-                    for arg in &args {
-                        cmd.arg(arg);
-                    }
-                    cmd.arg(voice_path);
+    pub fn push_chat_filter_char(&mut self, c: char) {
+        self.chat_filter.push(c);
+        self.select_visible_chat();
+    }
 
-                    // Подавляем вывод для ffplay и других плееров
-                    if player == "ffplay" {
-                        cmd.stdout(std::process::Stdio::null())
-                           .stderr(std::process::Stdio::null());
-                    } else if player == "mplayer" {
-                        cmd.stdout(std::process::Stdio::null())
-                           .stderr(std::process::Stdio::null());
-                    } else if player == "mpv" {
-                        cmd.stdout(std::process::Stdio::null())
-                           .stderr(std::process::Stdio::null());
-                    } else if player == "play" {
-                        cmd.stdout(std::process::Stdio::null())
-                           .stderr(std::process::Stdio::null());
-                    }
+    pub fn pop_chat_filter_char(&mut self) {
+        self.chat_filter.pop();
+        self.select_visible_chat();
+    }
 
-                    log::info!("Запускаем команду: {:?}", cmd);
-                    let result = cmd.spawn();
+    pub fn submit_chat_filter(&mut self) {
+        if self.chat_filter.trim().is_empty() {
+            self.chat_filter.clear();
+        }
+        self.state = AppState::Main;
+    }
 
-                    match result {
-                        Ok(child) => {
-                            log::info!("{} успешно запущен, PID: {}", player, child.id());
-                            self.audio_player.process_id = Some(child.id() as u32);
-                            // Устанавливаем время начала воспроизведения
-                            self.audio_start_time = Some(Instant::now());
-                            return Ok(());
-                        }
-                        Err(e) => {
-                            log::warn!("Не удалось запустить {}: {}", player, e);
-                            continue;
-                        }
-                    }
-                }
+    // Выделение остается на том же чате, теперь уже в полном списке
+    pub fn clear_chat_filter(&mut self) {
+        self.chat_filter.clear();
+        self.state = AppState::Main;
+    }
 
-                // Если ни один плеер не сработал
-                log::error!("Не удалось найти подходящий аудио плеер");
-                self.audio_player.is_playing = false;
-                self.audio_player.current_message_id = None;
-                Err(anyhow::anyhow!("Не удалось найти подходящий аудио плеер. Установите mpv, ffplay, mplayer, sox или alsa-utils"))
-            } else {
-                log::error!("Путь к файлу голосового сообщения не найден в сообщении");
-                return Err(anyhow::anyhow!("Путь к файлу голосового сообщения не найден"));
+    // Если выделенный чат скрыт фильтром, переходим к первому подходящему
+    fn select_visible_chat(&mut self) {
+        let visible = self.visible_chat_indices();
+        if !visible.contains(&self.selected_chat_index) {
+            if let Some(&first) = visible.first() {
+                self.selected_chat_index = first;
             }
-        } else {
-            log::error!("Сообщение не найдено по индексу {}", self.selected_message_index);
-            return Err(anyhow::anyhow!("Сообщение не найдено"));
         }
     }
 
-    pub fn play_audio(&mut self) -> Result<()> {
-        // Останавливаем текущее воспроизведение, если оно есть
-        if self.audio_player.is_playing {
-            self.audio_player.stop();
-            log::info!("Остановлено предыдущее воспроизведение перед запуском нового");
-        }
+    // Открываем чат в официальном клиенте: сначала tg:// ссылка, при неудаче веб-версия
+    pub fn open_chat_in_official_client(&mut self) {
+        let chat = if self.focus_on_messages {
+            self.selected_chat.clone()
+        } else {
+            self.chats.get(self.selected_chat_index).cloned()
+        };
+        let Some(chat) = chat else {
+            return;
+        };
 
-        // Get the actual audio file path from the current message
-        if let Some(msg) = self.messages.get(self.selected_message_index) {
-            if let Some(audio_path) = &msg.audio_path {
-                log::info!("Пытаемся воспроизвести аудио сообщение: {}", audio_path);
+        let (app_url, web_url) = match &chat.username {
+            Some(username) => (
+                format!("tg://resolve?domain={}", username),
+                format!("https://t.me/{}", username),
+            ),
+            None => (
+                format!("tg://openmessage?chat_id={}", chat.id),
+                format!("https://web.telegram.org/k/#{}", chat.id),
+            ),
+        };
 
-                // Проверяем, существует ли файл
-                if !std::path::Path::new(audio_path).exists() {
+        for url in [&app_url, &web_url] {
+            let opened = std::process::Command::new("xdg-open")
+                .arg(url)
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+                .map(|status| status.success())
+                .unwrap_or(false);
+            if opened {
+                log::info!("Чат {} открыт по ссылке {}", chat.id, url);
+                return;
+            }
+        }
+        self.show_error(&format!("Не удалось открыть чат в Telegram: {}", web_url));
+    }
+
+    pub fn is_chat_pinned(&self, chat_id: i64) -> bool {
+        self.config.pinned_chats.contains(&chat_id)
+    }
+
+    // Закрепленные чаты поднимаем наверх, сохраняя порядок внутри групп
+    fn sort_chats(&mut self) {
+        let pinned = &self.config.pinned_chats;
+        self.chats.sort_by_key(|chat| !pinned.contains(&chat.id));
+    }
+
+    pub fn toggle_pin_selected_chat(&mut self) {
+        let Some(chat_id) = self.chats.get(self.selected_chat_index).map(|c| c.id) else {
+            return;
+        };
+        if let Some(pos) = self.config.pinned_chats.iter().position(|&id| id == chat_id) {
+            self.config.pinned_chats.remove(pos);
+        } else {
+            self.config.pinned_chats.push(chat_id);
+        }
+        if let Err(e) = self.config.save() {
+            log::error!("Ошибка сохранения конфига: {}", e);
+        }
+
+        self.sort_chats();
+        if let Some(pos) = self.chats.iter().position(|c| c.id == chat_id) {
+            self.selected_chat_index = pos;
+        }
+    }
+
+    // Шаг считается по видимым (прошедшим фильтр) чатам
+    pub fn move_chat_selection(&mut self, direction: i32) {
+        let visible = self.visible_chat_indices();
+        if visible.is_empty() {
+            return;
+        }
+
+        let current = visible
+            .iter()
+            .position(|&i| i >= self.selected_chat_index)
+            .unwrap_or(visible.len() - 1);
+        let step = direction.unsigned_abs() as usize;
+        let new_position = if direction > 0 {
+            current.saturating_add(step).min(visible.len() - 1)
+        } else {
+            current.saturating_sub(step)
+        };
+        self.selected_chat_index = visible[new_position];
+    }
+
+    pub fn move_message_selection(&mut self, direction: i32, visible_capacity: usize) {
+        if self.messages.is_empty() {
+            return;
+        }
+        let step = direction.unsigned_abs() as usize;
+        let new_index = if direction > 0 {
+            self.selected_message_index.saturating_add(step)
+        } else {
+            self.selected_message_index.saturating_sub(step)
+        };
+        self.set_message_selection(new_index, visible_capacity);
+    }
+
+    // Выбор сообщения по индексу с ограничением границ и обновлением прокрутки
+    pub fn set_message_selection(&mut self, index: usize, visible_capacity: usize) {
+        if self.messages.is_empty() {
+            return;
+        }
+        let new_index = index.min(self.messages.len() - 1);
+        // Ручной уход с последнего сообщения отключает слежение за концом ленты
+        self.follow_tail = new_index + 1 == self.messages.len();
+        self.note_message_read(new_index);
+        if new_index != self.selected_message_index {
+            self.selected_message_index = new_index;
+            // Обновляем прокрутку, чтобы выделение было видно
+            if self.selected_message_index < self.message_scroll_offset {
+                self.message_scroll_offset = self.selected_message_index;
+            } else if self.selected_message_index >= self.message_scroll_offset + visible_capacity {
+                let overshoot = self.selected_message_index - (self.message_scroll_offset + visible_capacity) + 1;
+                self.message_scroll_offset += overshoot;
+            }
+        }
+    }
+
+    pub fn select_message_by_id(&mut self, message_id: i32) -> bool {
+        // При повторяющихся id всегда берем первое совпадение
+        match self.messages.iter().position(|m| m.id == message_id) {
+            Some(pos) => {
+                self.selected_message_index = pos;
+                self.follow_tail = pos + 1 == self.messages.len();
+                self.note_message_read(pos);
+                let visible_capacity = self.calculate_visible_capacity();
+                if pos < self.message_scroll_offset || pos >= self.message_scroll_offset + visible_capacity {
+                    self.message_scroll_offset = pos.saturating_sub(visible_capacity / 2);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn jump_to_message(&mut self, message_id: i32) {
+        let snapshot = (self.selected_message_index, self.message_scroll_offset);
+        if self.select_message_by_id(message_id) {
+            self.jump_back_stack.push(snapshot);
+            self.jump_forward_stack.clear();
+        } else {
+            // Обычный промах навигации, а не ошибка: экран ошибки закрывается только выходом
+            self.show_notice(&format!("Сообщение #{} не загружено, догрузить историю - :{}", message_id, message_id));
+        }
+    }
+
+    // ":" - переход к сообщению по id (для сверки с логами)
+    pub fn start_goto_input(&mut self) {
+        if self.selected_chat.is_none() {
+            self.show_notice("Сначала выберите чат (Enter в списке чатов)");
+            return;
+        }
+        self.goto_input.clear();
+        self.state = AppState::GotoInput;
+    }
+
+    pub fn cancel_goto_input(&mut self) {
+        self.goto_input.clear();
+        self.state = AppState::Main;
+    }
+
+    // Если сообщения нет в загруженной истории, догружаем более старые, пока не найдем или не упремся в лимит
+    pub async fn goto_message(&mut self) -> Result<()> {
+        let input = std::mem::take(&mut self.goto_input);
+        self.state = AppState::Main;
+        let Ok(message_id) = input.trim().trim_start_matches('#').parse::<i32>() else {
+            self.show_notice(&format!("Неверный id сообщения: {}", input.trim()));
+            return Ok(());
+        };
+
+        let (snapshot_index, snapshot_offset) = (self.selected_message_index, self.message_scroll_offset);
+        let loaded_before = self.messages.len();
+        while !self.messages.iter().any(|m| m.id == message_id) && self.history_limit < MAX_HISTORY_LIMIT {
+            self.history_limit = (self.history_limit * 2).min(MAX_HISTORY_LIMIT);
+            let loaded = self.messages.len();
+            self.load_messages().await?;
+            if self.messages.len() <= loaded {
+                // Старше загружать нечего
+                break;
+            }
+        }
+
+        // Догруженная история добавляется сверху, поэтому позиция для Ctrl+O сдвигается
+        let shift = self.messages.len().saturating_sub(loaded_before);
+        if self.select_message_by_id(message_id) {
+            self.focus_messages();
+            self.jump_back_stack.push((snapshot_index + shift, snapshot_offset));
+            self.jump_forward_stack.clear();
+        } else {
+            self.show_notice(&format!("Сообщение #{} не найдено в последних {} сообщениях чата", message_id, self.messages.len()));
+        }
+        Ok(())
+    }
+
+    // Ctrl+O: вернуться к позиции до перехода
+    pub fn jump_back(&mut self) {
+        if let Some((index, offset)) = self.jump_back_stack.pop() {
+            self.jump_forward_stack.push((self.selected_message_index, self.message_scroll_offset));
+            self.restore_message_position(index, offset);
+        }
+    }
+
+    // Ctrl+I: повторить отмененный переход
+    pub fn jump_forward(&mut self) {
+        if let Some((index, offset)) = self.jump_forward_stack.pop() {
+            self.jump_back_stack.push((self.selected_message_index, self.message_scroll_offset));
+            self.restore_message_position(index, offset);
+        }
+    }
+
+    fn restore_message_position(&mut self, index: usize, offset: usize) {
+        if self.messages.is_empty() {
+            return;
+        }
+        let last_index = self.messages.len() - 1;
+        self.selected_message_index = index.min(last_index);
+        self.message_scroll_offset = offset.min(last_index);
+        self.follow_tail = self.selected_message_index == last_index;
+        self.focus_messages();
+    }
+
+    pub fn toggle_mark_selected_message(&mut self) {
+        if let Some(id) = self.messages.get(self.selected_message_index).map(|m| m.id) {
+            if !self.marked_messages.remove(&id) {
+                self.marked_messages.insert(id);
+            }
+        }
+    }
+
+    pub fn toggle_spoiler_reveal(&mut self) {
+        if let Some(id) = self.messages.get(self.selected_message_index).map(|m| m.id) {
+            if !self.revealed_spoilers.remove(&id) {
+                self.revealed_spoilers.insert(id);
+            }
+        }
+    }
+
+    pub fn spoilers_hidden(&self, msg: &Message) -> bool {
+        let has_spoiler = msg.spoilers.as_ref().is_some_and(|s| !s.is_empty()) || msg.media_spoiler == Some(true);
+        has_spoiler && !self.revealed_spoilers.contains(&msg.id)
+    }
+
+    // Запоминаем, что сообщение просмотрено; запрос уходит с задержкой, чтобы не слать его на каждый шаг
+    fn note_message_read(&mut self, index: usize) {
+        if !self.config.mark_read_on_scroll {
+            return;
+        }
+        let (Some(chat_id), Some(message_id)) = (
+            self.selected_chat.as_ref().map(|c| c.id),
+            self.messages.get(index).map(|m| m.id),
+        ) else {
+            return;
+        };
+        self.queue_read(chat_id, message_id);
+    }
+
+    // Отметка прочтения уходит на сервер с задержкой в flush_pending_read
+    fn queue_read(&mut self, chat_id: i64, message_id: i32) {
+        let already_read = self.read_up_to.get(&chat_id).copied().unwrap_or(0);
+        let pending = self.pending_read
+            .filter(|(pending_chat, _, _)| *pending_chat == chat_id)
+            .map(|(_, id, _)| id)
+            .unwrap_or(0);
+        if message_id > already_read.max(pending) {
+            self.pending_read = Some((chat_id, message_id, Instant::now()));
+        }
+    }
+
+    // Открытие чата (mark_read_on_open) и M: прочитано все загруженное в открытом чате
+    pub fn mark_current_chat_read(&mut self) -> bool {
+        let Some(chat_id) = self.selected_chat.as_ref().map(|c| c.id) else {
+            return false;
+        };
+        let newest = self.messages
+            .iter()
+            .filter(|m| m.chat_id == chat_id && m.id > 0)
+            .map(|m| m.id)
+            .max();
+        match newest {
+            Some(message_id) => {
+                self.queue_read(chat_id, message_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn flush_pending_read(&mut self, now: Instant) {
+        let Some((chat_id, up_to_id, noted_at)) = self.pending_read else {
+            return;
+        };
+        if now.duration_since(noted_at) < Duration::from_secs(1) {
+            return;
+        }
+        self.pending_read = None;
+        self.read_up_to.insert(chat_id, up_to_id);
+
+        // Дочитали до последнего сообщения - локально обнуляем счетчики непрочитанных и упоминаний
+        if self.messages.iter().filter(|m| m.id > 0).map(|m| m.id).max() == Some(up_to_id) {
+            if let Some(chat) = self.chats.iter_mut().find(|c| c.id == chat_id) {
+                chat.unread = 0;
+                chat.unread_mentions = None;
+            }
+        }
+
+        let api_client = self.api_client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = api_client.mark_messages_read(chat_id, up_to_id).await {
+                log::error!("Ошибка отметки прочтения: {}", e);
+            }
+        });
+    }
+
+    // Цифра продолжает набранный префикс; сам префикс сбрасывается любой другой клавишей
+    pub fn push_count_digit(&mut self, previous: Option<usize>, digit: char) {
+        let value = digit.to_digit(10).unwrap_or(0) as usize;
+        let count = (previous.unwrap_or(0) * 10 + value).min(9999);
+        self.count_prefix = Some(count);
+        self.show_notice(&count.to_string());
+    }
+
+    // Home/gg и End/G: первый или последний элемент в текущем фокусе; фокус не меняется
+    pub fn select_edge(&mut self, last: bool) {
+        let index = if last { usize::MAX } else { 0 };
+        if self.focus_on_messages {
+            self.set_message_selection(index, self.calculate_visible_capacity());
+        } else {
+            let visible = self.visible_chat_indices();
+            let edge = if last { visible.last() } else { visible.first() };
+            if let Some(&index) = edge {
+                self.set_chat_selection(index);
+            }
+        }
+    }
+
+    // Сдвиг выделения в текущем фокусе с учетом повторителя
+    pub fn move_selection(&mut self, direction: i32, count: Option<usize>) {
+        let delta = direction * count.unwrap_or(1).max(1) as i32;
+        if self.focus_on_messages {
+            self.move_message_selection(delta, self.calculate_visible_capacity());
+        } else {
+            self.move_chat_selection(delta);
+        }
+    }
+
+    // PageUp/PageDown (divisor 1) и Ctrl+U/Ctrl+D (divisor 2): шаг - экран или пол-экрана ленты.
+    // Лента сдвигается вместе с выделением, чтобы оно осталось на той же строке экрана
+    pub fn move_message_page(&mut self, direction: i32, divisor: usize, count: Option<usize>) {
+        if self.messages.is_empty() {
+            return;
+        }
+        let capacity = self.get_actual_visible_capacity().max(1);
+        let step = (capacity / divisor.max(1)).max(1) * count.unwrap_or(1).max(1);
+        let before = self.selected_message_index;
+        let offset = self.message_scroll_offset;
+        self.move_message_selection(direction.signum() * step as i32, capacity);
+
+        let moved = self.selected_message_index.abs_diff(before);
+        let last_offset = self.messages.len().saturating_sub(capacity);
+        let mut offset = if direction > 0 {
+            (offset + moved).min(last_offset)
+        } else {
+            offset.saturating_sub(moved)
+        };
+        if self.selected_message_index < offset {
+            offset = self.selected_message_index;
+        } else if self.selected_message_index >= offset + capacity {
+            offset = self.selected_message_index + 1 - capacity;
+        }
+        self.message_scroll_offset = offset;
+    }
+
+    pub fn focused_pane(&self) -> FocusPane {
+        if self.state == AppState::MessageInput {
+            FocusPane::Compose
+        } else if self.focus_on_messages {
+            FocusPane::Messages
+        } else {
+            FocusPane::Chats
+        }
+    }
+
+    fn pane_available(&self, pane: FocusPane) -> bool {
+        match pane {
+            // Скрытый список чатов фокус не получает
+            FocusPane::Chats => !self.sidebar_hidden,
+            FocusPane::Messages => true,
+            FocusPane::Compose => self.selected_chat.is_some(),
+        }
+    }
+
+    pub fn set_focus(&mut self, pane: FocusPane) {
+        if pane != FocusPane::Compose && self.state == AppState::MessageInput {
+            self.state = AppState::Main;
+        }
+        match pane {
+            FocusPane::Chats => self.focus_chats(),
+            FocusPane::Messages => self.focus_messages(),
+            FocusPane::Compose => {
+                self.focus_messages();
+                self.enter_message_input(false);
+            }
+        }
+    }
+
+    // Tab (direction = 1) и Shift+Tab (-1): следующая доступная область в focus_ring
+    pub fn cycle_focus(&mut self, direction: i32) {
+        let len = self.focus_ring.len() as i32;
+        let current = self.focused_pane();
+        let position = self.focus_ring.iter().position(|&p| p == current).unwrap_or(0) as i32;
+        let next = (1..=len)
+            .map(|step| self.focus_ring[(position + direction * step).rem_euclid(len) as usize])
+            .find(|&pane| self.pane_available(pane));
+        if let Some(pane) = next {
+            self.set_focus(pane);
+        }
+    }
+
+    pub fn toggle_sidebar(&mut self) {
+        self.sidebar_hidden = !self.sidebar_hidden;
+        if self.sidebar_hidden {
+            self.focus_on_messages = true;
+        }
+    }
+
+    // Ctrl+R: выход из строя графики после смены терминала (tmux attach, изменение шрифта) без перезапуска
+    pub fn reload_image_rendering(&mut self) {
+        crate::ui::reset_image_rendering();
+        self.show_notice("Кэш изображений сброшен, терминал опрошен заново");
+    }
+
+    // Ctrl+A: полублоки выглядят грубее, но рисуются в любом терминале с цветом
+    pub fn toggle_half_block_images(&mut self) {
+        self.half_block_images = !self.half_block_images;
+        self.show_notice(if self.half_block_images {
+            "Картинки: полублоки"
+        } else {
+            "Картинки: графика терминала"
+        });
+    }
+
+    pub fn toggle_timestamps(&mut self) {
+        self.show_timestamps = !self.show_timestamps;
+        self.config.show_timestamps = self.show_timestamps;
+        if let Err(e) = self.config.save() {
+            log::error!("Ошибка сохранения конфига: {}", e);
+        }
+    }
+
+    // Ctrl+Alt+R: перечитать tui.json. Все настройки читаются из self.config на лету,
+    // отдельно применяем только скопированные при запуске: значки, время и long-poll
+    pub fn reload_config(&mut self) {
+        let config = match Config::try_load() {
+            Ok(config) => config,
+            Err(e) => {
+                self.show_notice(&format!("{} - оставлены прежние настройки", e));
+                return;
+            }
+        };
+        let long_poll_changed = config.long_poll != self.config.long_poll;
+        self.show_timestamps = config.show_timestamps;
+        self.symbols = symbols::get(config.ascii_only.unwrap_or_else(symbols::terminal_lacks_unicode));
+        self.config = config;
+        if long_poll_changed {
+            if self.config.long_poll {
+                self.start_update_polling();
+            } else {
+                self.stop_update_polling();
+            }
+        }
+        self.needs_redraw = true;
+        self.show_notice("Конфиг перечитан");
+    }
+
+    pub fn focus_messages(&mut self) {
+        self.focus_on_messages = true;
+    }
+
+    pub fn focus_chats(&mut self) {
+        self.focus_on_messages = false;
+    }
+
+    pub fn open_selected_message(&mut self) {
+        if self.messages.is_empty() {
+            log::warn!("Нет сообщений для открытия");
+            return;
+        }
+        let media_path = self.messages.get(self.selected_message_index).and_then(|msg| match msg.r#type.as_str() {
+            "photo" => msg.image_path.clone(),
+            "video" => msg.video_path.clone(),
+            "sticker" => msg.sticker_path.clone(),
+            "voice" => msg.voice_path.clone(),
+            "audio" => msg.audio_path.clone(),
+            _ => None,
+        });
+        // Фото, видео и стикер откроются в предпросмотре по мере загрузки, звук нужен целиком
+        let downloaded = self.ensure_media_downloaded(media_path);
+        if let Some(msg) = self.messages.get(self.selected_message_index) {
+            log::info!("Открываем сообщение типа: {}, id: {}", msg.r#type, msg.id);
+            if !downloaded && matches!(msg.r#type.as_str(), "voice" | "audio") {
+                return;
+            }
+
+            if msg.r#type == "photo" {
+                if let Some(path) = &msg.image_path {
+                    log::info!("Открываем фото: {}", path);
+                    self.preview_image_path = Some(path.clone());
+                    self.preview_video_path = None;
+                    self.state = AppState::ImagePreview;
+                } else {
+                    log::warn!("Фото сообщение без пути к файлу");
+                }
+            } else if msg.r#type == "video" {
+                log::info!("Открываем видео. Путь к превью: {:?}, путь к видео: {:?}", msg.video_preview_path, msg.video_path);
+
+                // For video preview, use the preview image (JPEG) and show overlay
+                if let Some(preview_path) = &msg.video_preview_path {
+                    self.preview_image_path = Some(preview_path.clone());
+                    // Путь к видео нужен для воспроизведения по Enter в ImagePreview
+                    self.preview_video_path = msg.video_path.clone();
+                    self.state = AppState::ImagePreview;
+                    log::info!("Установлен режим ImagePreview для видео с превью");
+                } else if let Some(video_path) = &msg.video_path {
+                    // Fallback to video file if no preview is available
+                    self.preview_video_path = Some(video_path.clone());
+                    self.state = AppState::VideoPreview;
+                    log::info!("Установлен режим VideoPreview для видео без превью");
+                } else {
+                    log::warn!("Видео сообщение без путей к файлам");
+                }
+            } else if msg.r#type == "sticker" {
+                if let Some(path) = &msg.sticker_path {
+                    log::info!("Открываем стикер: {}", path);
+                    self.preview_image_path = Some(path.clone());
+                    self.preview_video_path = None;
+                    self.state = AppState::ImagePreview;
+                } else {
+                    log::warn!("Стикер сообщение без пути к файлу");
+                }
+            } else if msg.r#type == "voice" {
+                log::info!("Воспроизводим голосовое сообщение");
+                log::info!("Проверяем voice_path: {:?}", msg.voice_path);
+                if let Err(e) = self.play_voice() {
+                    log::error!("Ошибка воспроизведения голосового сообщения: {}", e);
+                    self.show_error(&format!("Ошибка воспроизведения голосового сообщения: {}", e));
+                }
+            } else if msg.r#type == "audio" {
+                log::info!("Воспроизводим аудио сообщение");
+                log::info!("Проверяем audio_path: {:?}", msg.audio_path);
+                if let Err(e) = self.play_audio() {
+                    log::error!("Ошибка воспроизведения аудио сообщения: {}", e);
+                    self.show_error(&format!("Ошибка воспроизведения аудио сообщения: {}", e));
+                }
+            } else if msg.r#type == "location" {
+                log::info!("Открываем сообщение с местоположением");
+                log::info!("Координаты: lat={:?}, lng={:?}, title: {:?}", msg.location_lat, msg.location_lng, msg.location_title);
+                log::info!("Адрес: {:?}", msg.location_address);
+                log::info!("Карта: {:?}", msg.location_map_path);
+
+                // Show location preview - this will show detailed location info
+                if let Err(e) = self.open_location() {
+                    log::error!("Ошибка открытия местоположения: {}", e);
+                    self.show_error(&format!("Ошибка открытия местоположения: {}", e));
+                }
+            } else if let Some(reply_id) = msg.reply_to_id {
+                // Для обычных сообщений-ответов Enter переходит к исходному сообщению
+                self.jump_to_message(reply_id);
+            } else if msg.r#type != "text" && !crate::ui::is_service_type(&msg.r#type) {
+                log::info!("Неизвестный тип сообщения: {}", msg.r#type);
+                self.open_unsupported_message(msg.clone());
+            }
+        } else {
+            log::error!("Сообщение не найдено по индексу {}", self.selected_message_index);
+        }
+    }
+
+    // Новые типы сообщений, которые клиент еще не умеет показывать: пробуем действия из конфига по порядку
+    fn open_unsupported_message(&mut self, msg: Message) {
+        let path = unsupported_message_path(&msg);
+        for action in self.config.unsupported_message_actions.clone() {
+            match (action, &path) {
+                (UnsupportedAction::OpenPath, Some(path)) => {
+                    self.open_externally(path);
+                    return;
+                }
+                (UnsupportedAction::Prompt, Some(path)) => {
+                    self.pending_action = Some(PendingAction::OpenExternally {
+                        message_type: msg.r#type.clone(),
+                        path: path.clone(),
+                    });
+                    self.state = AppState::Confirm;
+                    return;
+                }
+                (UnsupportedAction::CopyJson, _) => {
+                    match serde_json::to_string_pretty(&msg).map_err(anyhow::Error::from).and_then(|json| crate::clipboard::copy(&json)) {
+                        Ok(()) => self.show_notice(&format!("Тип «{}» не поддерживается, JSON скопирован", msg.r#type)),
+                        Err(e) => self.show_error(&format!("Ошибка копирования: {}", e)),
+                    }
+                    return;
+                }
+                (UnsupportedAction::RawJson, _) => {
+                    self.open_raw_json();
+                    return;
+                }
+                _ => {}
+            }
+        }
+        self.show_notice(&format!("Тип сообщения «{}» не поддерживается", msg.r#type));
+    }
+
+    // F2 / F3: папка настроек (~/.vi-tg: tui.json, session.json) или кэша чатов и сообщений.
+    // Путь остается в строке статуса - на случай, если файлового менеджера нет
+    pub fn open_data_dir(&mut self, cache: bool) {
+        let (label, dir) = if cache {
+            ("кэша", crate::cache::cache_dir())
+        } else {
+            ("настроек", crate::config::config_dir())
+        };
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::warn!("Не удалось создать {}: {}", dir.display(), e);
+        }
+        let path = dir.display().to_string();
+        self.show_notice(&format!("Папка {}: {}", label, path));
+        self.open_externally(&path);
+    }
+
+    fn open_externally(&mut self, path: &str) {
+        let opened = std::process::Command::new("xdg-open")
+            .arg(path)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        if let Err(e) = opened {
+            self.show_error(&format!("Не удалось открыть {}: {}", path, e));
+        }
+    }
+
+    // Видео, которое запустит Enter в ImagePreview; у фото, стикера и карты его нет
+    pub fn preview_video(&self) -> Option<&str> {
+        self.preview_video_path.as_deref().filter(|p| !p.is_empty())
+    }
+
+    pub fn close_image_preview(&mut self) {
+        // Закрытие предпросмотра отменяет незавершенную загрузку показываемого файла
+        if let Some(path) = self.preview_image_path.clone() {
+            self.cancel_download(&path);
+        }
+        self.preview_image_path = None;
+        self.preview_video_path = None; // Clear video path too
+        self.state = AppState::Main;
+    }
+
+    pub fn is_downloading(&self, path: &str) -> bool {
+        self.downloads
+            .get(path)
+            .map(|handle| !handle.is_finished())
+            .unwrap_or(false)
+    }
+
+    pub fn cancel_download(&mut self, path: &str) {
+        if let Some(handle) = self.downloads.remove(path) {
+            if !handle.is_finished() {
+                handle.abort();
+                log::info!("Загрузка {} отменена", path);
+                // Убираем недописанный файл, чтобы он не попал под проверки на повреждение
+                let _ = std::fs::remove_file(partial_download_path(path));
+            }
+        }
+    }
+
+    pub fn close_video_preview(&mut self) {
+        self.preview_video_path = None;
+        self.state = AppState::Main;
+    }
+
+    pub fn play_video(&mut self) -> Result<()> {
+        // Get the video file path - prefer the stored preview_video_path if available
+        // This is important for fullscreen preview mode where we have a stored path
+        let video_path = if let Some(preview_path) = &self.preview_video_path {
+            if !preview_path.is_empty() {
+                log::info!("Используем сохраненный путь к видео из превью: {}", preview_path);
+                preview_path.clone()
+            } else {
+                // Fallback to getting path from current message
+                if let Some(msg) = self.messages.get(self.selected_message_index) {
+                    if let Some(msg_video_path) = &msg.video_path {
+                        log::info!("Используем путь к видео из текущего сообщения: {}", msg_video_path);
+                        msg_video_path.clone()
+                    } else {
+                        log::error!("Путь к видео файлу не найден в сообщении");
+                        return Err(anyhow::anyhow!("Путь к видео файлу не найден"));
+                    }
+                } else {
+                    log::error!("Сообщение не найдено по индексу {}", self.selected_message_index);
+                    return Err(anyhow::anyhow!("Сообщение не найдено"));
+                }
+            }
+        } else {
+            // No stored preview path, get from current message
+            if let Some(msg) = self.messages.get(self.selected_message_index) {
+                if let Some(msg_video_path) = &msg.video_path {
+                    log::info!("Пытаемся воспроизвести видео из сообщения: {}", msg_video_path);
+                    msg_video_path.clone()
+                } else {
+                    log::error!("Путь к видео файлу не найден в сообщении");
+                    return Err(anyhow::anyhow!("Путь к видео файлу не найден"));
+                }
+            } else {
+                log::error!("Сообщение не найдено по индексу {}", self.selected_message_index);
+                return Err(anyhow::anyhow!("Сообщение не найдено"));
+            }
+        };
+
+        log::info!("Пытаемся воспроизвести видео: {}", video_path);
+
+        // Проверяем, существует ли файл
+        if !std::path::Path::new(&video_path).exists() {
+            return Err(anyhow::anyhow!("Файл видео не существует: {}", video_path));
+        }
+
+        // Пробуем получить ID окна терминала для overlay
+        let window_id = self.get_terminal_window_id();
+        log::info!("ID окна терминала: {:?}", window_id);
+
+        // Пробуем разные стратегии запуска mpv для tiling WM
+        let strategies = vec![
+            // Стратегия 1: Floating window for tiling WM (dwm)
+            (Some("floating"), vec![
+                "--no-terminal",
+                "--input-ipc-server=/tmp/mpv-socket",
+                "--force-window=yes",
+                "--keep-open=no",
+                "--geometry=800x600+100+100"  // Set specific position
+            ]),
+            // Стратегия 2: Terminal-based with audio only (for headless)
+            (Some("audio-only"), vec![
+                "--no-terminal",
+                "--input-ipc-server=/tmp/mpv-socket",
+                "--force-window=no",
+                "--audio-device=alsa",
+                "--keep-open=no",
+                "--no-video"  // Audio only
+            ]),
+            // Стратегия 3: Minimal config for compatibility
+            (Some("minimal"), vec![
+                "--no-config",
+                "--no-terminal",
+                "--force-window=yes",
+                "--keep-open=no",
+                "--geometry=50%x50%"  // Center, half size
+            ])
+        ];
+
+        for (strategy_name, args) in strategies {
+            if strategy_name.is_none() && window_id.is_none() {
+                continue; // Skip overlay strategy if no window ID
+            }
+
+            log::info!("Пробуем стратегию: {:?}", strategy_name);
+
+            let mut cmd = std::process::Command::new("mpv");
+            for arg in &args {
+                if arg != &"0" {  // Skip the dummy window ID
+                    cmd.arg(arg);
+                }
+            }
+
+            // Set environment for better compatibility
+            cmd.env("DISPLAY", ":0")
+               .env("XDG_SESSION_TYPE", "tty");
+
+            cmd.arg(&video_path);
+            cmd.stdout(std::process::Stdio::null())
+               .stderr(std::process::Stdio::null());
+
+            log::info!("Запускаем команду: {:?}", cmd);
+            let result = cmd.spawn();
+
+            match result {
+                Ok(child) => {
+                    log::info!("mpv успешно запущен стратегией {:?}, PID: {}", strategy_name, child.id());
+                    // Wait a moment and check if process is still running
+                    std::thread::sleep(std::time::Duration::from_millis(500));
+
+                    // Check if process is still alive
+                    let pid = child.id();
+                    let is_alive = std::process::Command::new("kill")
+                        .arg("-0")  // Signal 0 just checks if process exists
+                        .arg(pid.to_string())
+                        .status()
+                        .is_ok();
+
+                    if is_alive {
+                        log::info!("mpv процесс работает нормально");
+                    } else {
+                        log::info!("mpv процесс завершен быстро, возможно проблема с окном");
+                    }
+                    return Ok(());
+                }
+                Err(e) => {
+                    log::warn!("Стратегия {:?} не сработала: {}", strategy_name, e);
+                    continue;
+                }
+            }
+        }
+
+        // If all strategies failed, try alternative video players
+        log::info!("Все стратегии mpv провалились, пробуем альтернативные плееры");
+
+        let alternative_players = vec![
+            ("vlc", vec!["--no-video-title", "--play-and-exit"]),
+            ("ffplay", vec!["-autoexit", "-fs"]), // fullscreen
+            ("mplayer", vec!["-fs", "-really-quiet"]),
+            ("xine", vec!["-f", "-q"]), // fullscreen, quiet
+        ];
+
+        for (player, args) in alternative_players {
+            log::info!("Пробуем альтернативный плеер: {}", player);
+
+            let mut cmd = std::process::Command::new(player);
+            for arg in &args {
+                cmd.arg(arg);
+            }
+            cmd.arg(&video_path);
+            cmd.stdout(std::process::Stdio::null())
+               .stderr(std::process::Stdio::null());
+
+            log::info!("Запускаем команду: {:?}", cmd);
+            let result = cmd.spawn();
+
+            match result {
+                Ok(child) => {
+                    log::info!("{} успешно запущен, PID: {}", player, child.id());
+                    return Ok(());
+                }
+                Err(e) => {
+                    log::warn!("Не удалось запустить {}: {}", player, e);
+                    continue;
+                }
+            }
+        }
+
+        // If all strategies failed, try one more time with minimal mpv options
+        log::info!("Все плееры провалились, пробуем минимальную конфигурацию mpv");
+        let result = std::process::Command::new("mpv")
+            .arg("--no-config")
+            .arg("--no-terminal")
+            .arg(&video_path)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+
+        match result {
+            Ok(child) => {
+                log::info!("mpv запущен с минимальной конфигурацией, PID: {}", child.id());
+                Ok(())
+            }
+            Err(e) => {
+                log::error!("Не удалось запустить mpv даже с минимальной конфигурацией: {}", e);
+                Err(anyhow::anyhow!("Не удалось запустить видео плеер. Установите mpv, vlc, ffplay, mplayer или xine"))
+            }
+        }
+    }
+
+    // V: начать запись голосового или, если запись идет, остановить и отправить
+    pub async fn toggle_voice_recording(&mut self) -> Result<()> {
+        if self.recording.is_some() {
+            self.finish_voice_recording();
+            return Ok(());
+        }
+        let Some(chat_id) = self.selected_chat.as_ref().map(|c| c.id) else {
+            self.show_notice("Сначала выберите чат (Enter в списке чатов)");
+            return Ok(());
+        };
+
+        let stamp = chrono::Local::now().timestamp();
+        // Пробуем программы записи по очереди: ffmpeg сразу пишет Opus, arecord - WAV
+        let recorders = [
+            ("ffmpeg", vec!["-loglevel", "quiet", "-f", "pulse", "-i", "default", "-c:a", "libopus", "-y"], "ogg"),
+            ("ffmpeg", vec!["-loglevel", "quiet", "-f", "alsa", "-i", "default", "-c:a", "libopus", "-y"], "ogg"),
+            ("arecord", vec!["-q", "-f", "S16_LE", "-r", "48000", "-c", "1", "-t", "wav"], "wav"),
+        ];
+        for (recorder, args, extension) in recorders {
+            let path = format!("/tmp/vi-tg_voice_{}.{}", stamp, extension);
+            let result = std::process::Command::new(recorder)
+                .args(&args)
+                .arg(&path)
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn();
+            match result {
+                Ok(mut child) => {
+                    // Программа есть, но устройство не открылось - она сразу завершается
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    if let Ok(Some(status)) = child.try_wait() {
+                        log::warn!("{} завершился сразу ({}), пробуем следующую программу", recorder, status);
+                        let _ = std::fs::remove_file(&path);
+                        continue;
+                    }
+                    log::info!("Запись голосового через {} в {}", recorder, path);
+                    self.recording = Some(VoiceRecording { child, path, chat_id, started: Instant::now() });
+                    return Ok(());
+                }
+                Err(e) => {
+                    log::warn!("Не удалось запустить {}: {}", recorder, e);
+                }
+            }
+        }
+        Err(anyhow::anyhow!("Не удалось начать запись. Установите ffmpeg (с PulseAudio или ALSA) или alsa-utils"))
+    }
+
+    // Выполняется в spawn_blocking: ждем программу записи не дольше RECORDER_STOP_TIMEOUT
+    fn stop_recorder(child: &mut std::process::Child) {
+        // По SIGINT ffmpeg и arecord дописывают заголовки файла и завершаются
+        let interrupted = std::process::Command::new("kill")
+            .arg("-INT")
+            .arg(child.id().to_string())
+            .status()
+            .is_ok_and(|status| status.success());
+        if interrupted {
+            let deadline = Instant::now() + RECORDER_STOP_TIMEOUT;
+            while Instant::now() < deadline {
+                match child.try_wait() {
+                    Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+                    _ => return,
+                }
+            }
+            log::warn!("Программа записи не завершилась по SIGINT, останавливаем принудительно");
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    // Останавливаем запись и выбрасываем файл, не дожидаясь программы записи
+    fn discard_recording(recording: VoiceRecording) {
+        let VoiceRecording { mut child, path, .. } = recording;
+        tokio::task::spawn_blocking(move || {
+            Self::stop_recorder(&mut child);
+            let _ = std::fs::remove_file(&path);
+        });
+    }
+
+    // Остановка и загрузка идут в фоне, результат приходит в voice_results_rx
+    fn finish_voice_recording(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            return;
+        };
+        let duration = Duration::from_secs(recording.elapsed().as_secs());
+        if duration.is_zero() {
+            Self::discard_recording(recording);
+            self.show_notice("Запись слишком короткая");
+            return;
+        }
+
+        let api_client = self.api_client.clone();
+        let tx = self.voice_results_tx.clone();
+        let VoiceRecording { mut child, path, chat_id, .. } = recording;
+        self.show_notice("Отправляем голосовое…");
+        tokio::spawn(async move {
+            let stopped = tokio::task::spawn_blocking(move || Self::stop_recorder(&mut child)).await;
+            let result = match stopped {
+                Ok(()) => api_client.send_file(chat_id, &path, "voice", Some(duration.as_secs() as i32)).await,
+                Err(e) => Err(anyhow::anyhow!("не удалось остановить запись: {}", e)),
+            };
+            // Файл нужен только до успешной отправки; при ошибке оставляем запись пользователю
+            if matches!(&result, Ok(response) if response.success) {
+                let _ = tokio::fs::remove_file(&path).await;
+            }
+            let _ = tx.send((chat_id, path, duration, result));
+        });
+    }
+
+    async fn process_voice_results(&mut self) -> Result<()> {
+        while let Ok((chat_id, path, duration, result)) = self.voice_results_rx.try_recv() {
+            self.needs_redraw = true;
+            let error = match result {
+                Ok(response) if response.success => {
+                    let duration = format_duration(duration);
+                    if self.selected_chat.as_ref().map(|c| c.id) == Some(chat_id) {
+                        self.show_notice(&format!("Голосовое отправлено ({})", duration));
+                        self.load_messages().await?;
+                    } else {
+                        self.show_notice(&format!("Голосовое отправлено в «{}» ({})", self.chat_title(chat_id), duration));
+                    }
+                    continue;
+                }
+                Ok(response) => response.message,
+                Err(e) => e.to_string(),
+            };
+            self.show_notice(&format!("Голосовое не отправлено: {} (запись сохранена в {})", error, path));
+        }
+        Ok(())
+    }
+
+    // Esc во время записи: остановить и выбросить файл
+    pub fn cancel_voice_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            Self::discard_recording(recording);
+            self.show_notice("Запись отменена");
+        }
+    }
+
+    pub fn play_voice(&mut self) -> Result<()> {
+        // Останавливаем текущее воспроизведение, если оно есть
+        if self.audio_player.is_playing {
+            self.audio_player.stop();
+            log::info!("Остановлено предыдущее воспроизведение перед запуском нового");
+        }
+
+        // Get the actual voice file path from the current message
+        if let Some(msg) = self.messages.get(self.selected_message_index) {
+            if let Some(voice_path) = &msg.voice_path {
+                log::info!("Пытаемся воспроизвести голосовое сообщение: {}", voice_path);
+
+                // Проверяем, существует ли файл
+                if !std::path::Path::new(voice_path).exists() {
+                    return Err(anyhow::anyhow!("Файл голосового сообщения не существует: {}", voice_path));
+                }
+
+                // Проверяем, является ли это то же самое сообщение, что уже играет
+                if self.audio_player.is_current_message(msg.id) && self.audio_player.is_playing {
+                    // Останавливаем текущее воспроизведение
+                    self.audio_player.stop();
+                    log::info!("Остановлено воспроизведение голосового сообщения");
+                    return Ok(());
+                }
+
+
+
+                // Инициализируем состояние аудио плеера
+                self.audio_player.current_message_id = Some(msg.id);
+                self.audio_player.current_position = Duration::ZERO;
+                self.audio_player.total_duration = msg.voice_duration.map(|d| Duration::from_secs(d as u64));
+                self.audio_player.is_playing = true;
+                self.audio_player.current_file_path = Some(voice_path.clone()); // Store file path for restart functionality
+
+                // Пробуем разные плееры для воспроизведения аудио с усилением громкости
+                // ffplay как основной (работает надежно), mpv как запасной
+                let audio_players = vec![
+                    ("ffplay", vec!["-nodisp", "-autoexit", "-af", "volume=10"]),
+                    ("mpv", vec![
+                        "--volume=200",
+                        "--input-ipc-server=/tmp/mpv-socket",
+                        "--input-ipc-server=/tmp/mpv-socket:rw"  // Явно указываем права на чтение/запись
+                    ]), // Для перемотки
+                    ("mplayer", vec!["-really-quiet", "-noconsolecontrols", "-af", "volume=10"]),
+                    ("play", vec!["-v", "10"]), // SoX play with 10x volume boost
+                    ("paplay", vec![]), // PulseAudio player (no volume control)
+                ];
+
+                for (player, args) in audio_players {
+                    log::info!("Пробуем плеер: {}", player);
+
+                    let mut cmd = std::process::Command::new(player);
+                    for arg in &args {
+                        cmd.arg(arg);
+                    }
+                    cmd.arg(voice_path);
+
+                    // Подавляем вывод для ffplay и других плееров
+                    if player != "paplay" {
+                        cmd.stdout(std::process::Stdio::null())
+                           .stderr(std::process::Stdio::null());
+                    }
+
+                    log::info!("Запускаем команду: {:?}", cmd);
+                    let result = cmd.spawn();
+
+                    match result {
+                        Ok(child) => {
+                            log::info!("{} успешно запущен, PID: {}", player, child.id());
+                            self.audio_player.process_id = Some(child.id());
+                            // Устанавливаем время начала воспроизведения
+                            self.audio_start_time = Some(Instant::now());
+                            return Ok(());
+                        }
+                        Err(e) => {
+                            log::warn!("Не удалось запустить {}: {}", player, e);
+                            continue;
+                        }
+                    }
+                }
+
+                // Если ни один плеер не сработал
+                log::error!("Не удалось найти подходящий аудио плеер");
+                self.audio_player.is_playing = false;
+                self.audio_player.current_message_id = None;
+                Err(anyhow::anyhow!("Не удалось найти подходящий аудио плеер. Установите mpv, ffplay, mplayer, sox или alsa-utils"))
+            } else {
+                log::error!("Путь к файлу голосового сообщения не найден в сообщении");
+                Err(anyhow::anyhow!("Путь к файлу голосового сообщения не найден"))
+            }
+        } else {
+            log::error!("Сообщение не найдено по индексу {}", self.selected_message_index);
+            Err(anyhow::anyhow!("Сообщение не найдено"))
+        }
+    }
+
+    pub fn play_audio(&mut self) -> Result<()> {
+        // Останавливаем текущее воспроизведение, если оно есть
+        if self.audio_player.is_playing {
+            self.audio_player.stop();
+            log::info!("Остановлено предыдущее воспроизведение перед запуском нового");
+        }
+
+        // Get the actual audio file path from the current message
+        if let Some(msg) = self.messages.get(self.selected_message_index) {
+            if let Some(audio_path) = &msg.audio_path {
+                log::info!("Пытаемся воспроизвести аудио сообщение: {}", audio_path);
+
+                // Проверяем, существует ли файл
+                if !std::path::Path::new(audio_path).exists() {
                     return Err(anyhow::anyhow!("Файл аудио сообщения не существует: {}", audio_path));
                 }
 
-                // Проверяем, является ли это то же самое сообщение, что уже играет
-                if self.audio_player.is_current_message(msg.id) && self.audio_player.is_playing {
-                    // Останавливаем текущее воспроизведение
-                    self.audio_player.stop();
-                    log::info!("Остановлено воспроизведение аудио сообщения");
+                // Проверяем, является ли это то же самое сообщение, что уже играет
+                if self.audio_player.is_current_message(msg.id) && self.audio_player.is_playing {
+                    // Останавливаем текущее воспроизведение
+                    self.audio_player.stop();
+                    log::info!("Остановлено воспроизведение аудио сообщения");
+                    return Ok(());
+                }
+
+
+
+                // Инициализируем состояние аудио плеера
+                self.audio_player.current_message_id = Some(msg.id);
+                self.audio_player.current_position = Duration::ZERO;
+                self.audio_player.total_duration = msg.audio_duration.map(|d| Duration::from_secs(d as u64));
+                self.audio_player.is_playing = true;
+                self.audio_player.current_file_path = Some(audio_path.clone()); // Store file path for restart functionality
+
+                // Пробуем разные плееры для воспроизведения аудио с усилением громкости
+                // ffplay как основной (работает надежно), mpv как запасной
+                let audio_players = vec![
+                    ("ffplay", vec!["-nodisp", "-autoexit", "-af", "volume=10"]),
+                    ("mpv", vec![
+                        "--volume=200",
+                        "--input-ipc-server=/tmp/mpv-socket",
+                        "--input-ipc-server=/tmp/mpv-socket:rw"  // Явно указываем права на чтение/запись
+                    ]), // Для перемотки
+                    ("mplayer", vec!["-really-quiet", "-noconsolecontrols", "-af", "volume=10"]),
+                    ("play", vec!["-v", "10"]), // SoX play with 10x volume boost
+                    ("paplay", vec![]), // PulseAudio player (no volume control)
+                ];
+
+                for (player, args) in audio_players {
+                    log::info!("Пробуем плеер: {}", player);
+
+                    let mut cmd = std::process::Command::new(player);
+                    for arg in &args {
+                        cmd.arg(arg);
+                    }
+                    cmd.arg(audio_path);
+
+                    // Подавляем вывод для ffplay и других плееров
+                    if player != "paplay" {
+                        cmd.stdout(std::process::Stdio::null())
+                           .stderr(std::process::Stdio::null());
+                    }
+
+                    log::info!("Запускаем команду: {:?}", cmd);
+                    let result = cmd.spawn();
+
+                    match result {
+                        Ok(child) => {
+                            log::info!("{} успешно запущен, PID: {}", player, child.id());
+                            self.audio_player.process_id = Some(child.id());
+                            // Устанавливаем время начала воспроизведения
+                            self.audio_start_time = Some(Instant::now());
+                            return Ok(());
+                        }
+                        Err(e) => {
+                            log::warn!("Не удалось запустить {}: {}", player, e);
+                            continue;
+                        }
+                    }
+                }
+
+                // Если ни один плеер не сработал
+                log::error!("Не удалось найти подходящий аудио плеер");
+                self.audio_player.is_playing = false;
+                self.audio_player.current_message_id = None;
+                Err(anyhow::anyhow!("Не удалось найти подходящий аудио плеер. Установите mpv, ffplay, mplayer, sox или alsa-utils"))
+            } else {
+                log::error!("Путь к файлу аудио сообщения не найден в сообщении");
+                Err(anyhow::anyhow!("Путь к файлу аудио сообщения не найден"))
+            }
+        } else {
+            log::error!("Сообщение не найдено по индексу {}", self.selected_message_index);
+            Err(anyhow::anyhow!("Сообщение не найдено"))
+        }
+    }
+
+    fn get_terminal_window_id(&self) -> Option<u64> {
+        // Пробуем различные способы получить ID окна терминала
+
+        // Способ 1: через переменную окружения WINDOWID (для X11)
+        if let Ok(window_id_str) = std::env::var("WINDOWID") {
+            if let Ok(wid) = window_id_str.parse::<u64>() {
+                // Проверяем, что ID не равен 0 (некорректное значение)
+                if wid > 0 {
+                    log::info!("Получен window ID из переменной WINDOWID: {}", wid);
+                    return Some(wid);
+                } else {
+                    log::warn!("WINDOWID содержит некорректное значение: {}", wid);
+                }
+            } else {
+                log::warn!("Не удалось распарсить WINDOWID: {}", window_id_str);
+            }
+        } else {
+            log::debug!("Переменная WINDOWID не установлена");
+        }
+
+        // Способ 2: через xdotool (если доступен)
+        if let Ok(output) = std::process::Command::new("xdotool")
+            .args(["getactivewindow"])
+            .output() {
+            if output.status.success() {
+                if let Ok(window_id_str) = String::from_utf8(output.stdout) {
+                    if let Ok(wid) = window_id_str.trim().parse::<u64>() {
+                        if wid > 0 {
+                            log::info!("Получен window ID через xdotool: {}", wid);
+                            return Some(wid);
+                        } else {
+                            log::warn!("xdotool вернул некорректный window ID: {}", wid);
+                        }
+                    } else {
+                        log::warn!("Не удалось распарсить вывод xdotool: {}", window_id_str);
+                    }
+                } else {
+                    log::warn!("Вывод xdotool не является валидной UTF-8 строкой");
+                }
+            } else {
+                log::debug!("xdotool не найден или вернул ошибку");
+            }
+        }
+
+        // Способ 3: через xprop (если доступен)
+        if let Ok(output) = std::process::Command::new("xprop")
+            .args(["-root", "_NET_ACTIVE_WINDOW"])
+            .output() {
+            if output.status.success() {
+                if let Ok(output_str) = String::from_utf8(output.stdout) {
+                    // Парсим вывод вида "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x..."
+                    if let Some(hex_id) = output_str.split("0x").nth(1) {
+                        if let Some(hex_clean) = hex_id.split_whitespace().next() {
+                            if let Ok(wid) = u64::from_str_radix(hex_clean, 16) {
+                                if wid > 0 {
+                                    log::info!("Получен window ID через xprop: {}", wid);
+                                    return Some(wid);
+                                } else {
+                                    log::warn!("xprop вернул некорректный window ID: {}", wid);
+                                }
+                            } else {
+                                log::warn!("Не удалось распарсить hex значение: {}", hex_clean);
+                            }
+                        } else {
+                            log::warn!("Не удалось найти hex часть в выводе xprop: {}", output_str);
+                        }
+                    } else {
+                        log::warn!("Не найден hex ID в выводе xprop: {}", output_str);
+                    }
+                } else {
+                    log::warn!("Вывод xprop не является валидной UTF-8 строкой");
+                }
+            } else {
+                log::debug!("xprop не найден или вернул ошибку");
+            }
+        }
+
+        log::warn!("Не удалось получить корректный window ID ни одним из способов");
+        None
+    }
+
+    pub async fn select_chat(&mut self) -> Result<()> {
+        if self.selected_chat_index < self.chats.len() {
+            let current_id = self.selected_chat.as_ref().map(|c| c.id);
+            if current_id != Some(self.chats[self.selected_chat_index].id) {
+                self.previous_chat_id = current_id;
+                self.history_limit = DEFAULT_HISTORY_LIMIT;
+            }
+            self.selected_chat = Some(self.chats[self.selected_chat_index].clone());
+            self.last_loaded_chat_id = self.selected_chat.as_ref().map(|c| c.id);
+            self.jump_back_stack.clear();
+            self.jump_forward_stack.clear();
+            self.marked_messages.clear();
+            crate::ui::clear_image_cache();
+            self.load_messages().await?;
+            // При mark_read_on_open = false непрочитанные остаются до явной отметки (M)
+            if self.config.mark_read_on_open {
+                self.mark_current_chat_read();
+            }
+        }
+        Ok(())
+    }
+
+    // u: следующий после текущего чат с непрочитанными; чаты с упоминаниями идут первыми
+    pub async fn select_next_unread_chat(&mut self) -> Result<()> {
+        let len = self.chats.len();
+        let after_current = (1..=len).map(|step| (self.selected_chat_index + step) % len);
+        let target = after_current
+            .clone()
+            .find(|&i| self.chats[i].unread_mentions.unwrap_or(0) > 0)
+            .or_else(|| after_current.clone().find(|&i| self.chats[i].unread > 0));
+        let Some(index) = target else {
+            self.show_notice("Непрочитанных чатов нет");
+            return Ok(());
+        };
+        self.set_chat_selection(index);
+        self.select_chat().await
+    }
+
+    // t: показать перевод выбранного сообщения под оригиналом или скрыть его
+    pub async fn toggle_translation(&mut self) -> Result<()> {
+        let Some(msg) = self.messages.get(self.selected_message_index) else {
+            return Ok(());
+        };
+        let key = (msg.chat_id, msg.id);
+        if self.shown_translations.remove(&key) {
+            return Ok(());
+        }
+        if self.translations.contains_key(&key) {
+            self.shown_translations.insert(key);
+            return Ok(());
+        }
+        if msg.r#type != "text" || msg.text.trim().is_empty() {
+            self.show_notice("Переводить можно только текстовые сообщения");
+            return Ok(());
+        }
+
+        let text = msg.text.clone();
+        let translation = match self.api_client.translate(&text, &self.config.translate_to).await {
+            Ok(translation) => translation,
+            Err(e) if matches!(e.downcast_ref::<ApiError>(), Some(ApiError::Unsupported)) => {
+                self.show_notice("Перевод недоступен: бэкенд не поддерживает /api/translate");
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        self.translations.insert(key, translation);
+        self.shown_translations.insert(key);
+        Ok(())
+    }
+
+    // v: кто прочитал свое сообщение в группе
+    pub async fn show_message_readers(&mut self) -> Result<()> {
+        let Some(chat) = self.selected_chat.clone() else {
+            return Ok(());
+        };
+        if chat.r#type == "user" {
+            self.show_notice("Список прочитавших есть только в группах");
+            return Ok(());
+        }
+        let Some(msg) = self.messages.get(self.selected_message_index).filter(|m| m.id > 0).cloned() else {
+            return Ok(());
+        };
+        let my_id = self.me.as_ref().map(|me| me.id);
+        if my_id.is_some() && msg.from_id != my_id {
+            self.show_notice("Список прочитавших доступен только для своих сообщений");
+            return Ok(());
+        }
+
+        let response = match self.api_client.get_message_readers(chat.id, msg.id).await {
+            Ok(response) => response,
+            Err(e) if matches!(e.downcast_ref::<ApiError>(), Some(ApiError::Unsupported)) => {
+                self.show_notice("Список прочитавших недоступен (группа большая или сообщение старое)");
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        if response.readers.is_empty() {
+            self.show_notice("Сообщение еще никто не прочитал");
+            return Ok(());
+        }
+
+        self.readers_lines = response.readers
+            .iter()
+            .map(|reader| {
+                let time = reader.read_at
+                    .as_deref()
+                    .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                    .map(|t| format!(" — {}", t.format("%d.%m %H:%M")))
+                    .unwrap_or_default();
+                format!("{}{}", reader.name, time)
+            })
+            .collect();
+        let hidden = response.total.unwrap_or(0) - response.readers.len() as i32;
+        if hidden > 0 {
+            self.readers_lines.push(format!("… и еще {}", hidden));
+        }
+        self.state = AppState::Readers;
+        Ok(())
+    }
+
+    pub fn close_readers(&mut self) {
+        self.readers_lines.clear();
+        self.state = AppState::Main;
+    }
+
+    fn check_hover_preview(&mut self, now: Instant) {
+        let hovered = self.messages
+            .get(self.selected_message_index)
+            .filter(|m| matches!(m.r#type.as_str(), "photo" | "sticker" | "video"))
+            .map(|m| m.id);
+        if !self.config.hover_preview || self.state != AppState::Main || !self.focus_on_messages || hovered.is_none() {
+            self.hover_message = None;
+            return;
+        }
+        if self.hover_message != hovered {
+            self.hover_message = hovered;
+            self.hover_since = Some(now);
+            return;
+        }
+        if self.hover_since.is_some_and(|since| now.duration_since(since) >= HOVER_PREVIEW_DWELL) {
+            // Повторно для того же сообщения не открываем, пока выделение не сдвинется
+            self.hover_since = None;
+            self.open_selected_message();
+            self.hover_preview_open = matches!(self.state, AppState::ImagePreview | AppState::VideoPreview);
+            self.needs_redraw = true;
+        }
+    }
+
+    // Любая клавиша отменяет ожидание предпросмотра; открытый наведением предпросмотр
+    // закрывается (кроме Enter, который запускает видео). true - клавиша обработана.
+    pub fn cancel_hover_preview(&mut self, key: crossterm::event::KeyCode) -> bool {
+        self.hover_since = None;
+        if !std::mem::take(&mut self.hover_preview_open) || key == crossterm::event::KeyCode::Enter {
+            return false;
+        }
+        match self.state {
+            AppState::ImagePreview => self.close_image_preview(),
+            AppState::VideoPreview => self.close_video_preview(),
+            _ => return false,
+        }
+        true
+    }
+
+    // I: описание группы или bio собеседника; в списке чатов - для чата под курсором
+    pub async fn show_chat_info(&mut self) -> Result<()> {
+        let chat_id = if self.focus_on_messages {
+            self.selected_chat.as_ref().map(|c| c.id)
+        } else {
+            self.chats.get(self.selected_chat_index).map(|c| c.id)
+        };
+        let Some(chat_id) = chat_id else {
+            return Ok(());
+        };
+        self.chat_details = Some(self.api_client.get_chat_details(chat_id).await?);
+        self.state = AppState::ChatInfo;
+        Ok(())
+    }
+
+    pub fn close_chat_info(&mut self) {
+        self.chat_details = None;
+        self.state = AppState::Main;
+    }
+
+    // L: ссылка-приглашение группы или канала; в списке чатов - для чата под курсором
+    pub async fn show_invite_link(&mut self) -> Result<()> {
+        let chat = if self.focus_on_messages {
+            self.selected_chat.clone()
+        } else {
+            self.chats.get(self.selected_chat_index).cloned()
+        };
+        let Some(chat) = chat else {
+            return Ok(());
+        };
+        if chat.r#type == "user" {
+            self.show_notice("У личного чата нет ссылки-приглашения");
+            return Ok(());
+        }
+        match self.api_client.get_invite_link(chat.id).await {
+            Ok(Some(link)) => {
+                self.invite_link = Some((chat.title, link));
+                self.state = AppState::InviteLink;
+            }
+            Ok(None) => self.show_notice(&format!("У чата «{}» нет ссылки-приглашения", chat.title)),
+            Err(e) => match e.downcast_ref::<ApiError>() {
+                Some(ApiError::Forbidden) => {
+                    self.show_notice(&format!("Ссылку-приглашение «{}» видят только администраторы", chat.title));
+                }
+                Some(ApiError::Unsupported) => {
+                    self.show_notice("Ссылки-приглашения недоступны: бэкенд не поддерживает /api/chats/{id}/invite");
+                }
+                _ => return Err(e),
+            },
+        }
+        Ok(())
+    }
+
+    pub fn copy_invite_link(&mut self) {
+        let Some((_, link)) = &self.invite_link else {
+            return;
+        };
+        match crate::clipboard::copy(link) {
+            Ok(()) => self.show_notice("Ссылка скопирована в буфер обмена"),
+            Err(e) => self.show_notice(&format!("Не удалось скопировать: {}", e)),
+        }
+    }
+
+    pub fn close_invite_link(&mut self) {
+        self.invite_link = None;
+        self.state = AppState::Main;
+    }
+
+    // S: поиск по всем чатам
+    pub fn open_global_search(&mut self) {
+        self.state = AppState::GlobalSearch;
+    }
+
+    pub fn close_global_search(&mut self) {
+        self.state = AppState::Main;
+    }
+
+    pub fn move_global_search_selection(&mut self, direction: i32) {
+        let last = self.global_search_results.len().saturating_sub(1);
+        self.global_search_index = if direction > 0 {
+            (self.global_search_index + 1).min(last)
+        } else {
+            self.global_search_index.saturating_sub(1)
+        };
+    }
+
+    // Enter: новый запрос выполняет поиск, для уже выполненного - открывает выбранный результат
+    pub async fn submit_global_search(&mut self) -> Result<()> {
+        let query = self.global_search_input.trim().to_string();
+        if query.is_empty() {
+            return Ok(());
+        }
+        if query != self.global_search_query || self.global_search_results.is_empty() {
+            self.global_search_results = self.api_client.search_messages(&query).await?;
+            self.global_search_query = query;
+            self.global_search_index = 0;
+            if self.global_search_results.is_empty() {
+                self.show_notice("Ничего не найдено");
+            }
+            return Ok(());
+        }
+        self.open_global_search_result().await
+    }
+
+    async fn open_global_search_result(&mut self) -> Result<()> {
+        let Some(found) = self.global_search_results.get(self.global_search_index).cloned() else {
+            return Ok(());
+        };
+        self.close_global_search();
+
+        if self.selected_chat.as_ref().map(|c| c.id) != Some(found.chat_id) {
+            let Some(pos) = self.chats.iter().position(|c| c.id == found.chat_id) else {
+                self.show_notice("Чат найденного сообщения не найден в списке чатов");
+                return Ok(());
+            };
+            self.selected_chat_index = pos;
+            self.select_chat().await?;
+        }
+        self.focus_messages();
+
+        let snapshot = (self.selected_message_index, self.message_scroll_offset);
+        if self.select_message_by_id(found.id) {
+            self.jump_back_stack.push(snapshot);
+            self.jump_forward_stack.clear();
+        } else {
+            self.show_notice("Сообщение слишком старое и не загружено в ленту");
+        }
+        Ok(())
+    }
+
+    pub fn chat_title(&self, chat_id: i64) -> String {
+        self.chats
+            .iter()
+            .find(|c| c.id == chat_id)
+            .map(|c| c.title.clone())
+            .unwrap_or_else(|| format!("Чат {}", chat_id))
+    }
+
+    // Ctrl+^: вернуться в предыдущий чат, повторное нажатие - обратно
+    pub async fn switch_to_previous_chat(&mut self) -> Result<()> {
+        let Some(chat_id) = self.previous_chat_id else {
+            self.show_notice("Предыдущего чата нет");
+            return Ok(());
+        };
+        let Some(pos) = self.chats.iter().position(|c| c.id == chat_id) else {
+            self.previous_chat_id = None;
+            return Ok(());
+        };
+        self.selected_chat_index = pos;
+        self.select_chat().await?;
+        self.focus_messages();
+        Ok(())
+    }
+
+    pub async fn toggle_online(&mut self) -> Result<()> {
+        let online = !self.self_online;
+        let response = self.api_client.set_online(online).await?;
+        if !response.success {
+            return Err(anyhow::anyhow!(response.message));
+        }
+        self.self_online = online;
+        Ok(())
+    }
+
+    pub async fn send_message(&mut self) -> Result<()> {
+        if let Some(chat) = &self.selected_chat {
+            if self.message_input.trim().is_empty() {
+                return Ok(());
+            }
+            let text = std::mem::take(&mut self.message_input);
+            let chat_id = chat.id;
+            self.drop_stale_reply_target();
+            let reply_to = self.reply_to.take().map(|(_, message_id)| message_id);
+            self.queue_text_message(chat_id, text, reply_to, true);
+        }
+
+        Ok(())
+    }
+
+    // e и клавиша из quick_emoji: эмодзи уходит обычным сообщением, черновик не трогаем
+    pub fn send_quick_emoji(&mut self, key: char) {
+        let Some(chat_id) = self.selected_chat.as_ref().map(|c| c.id) else {
+            self.show_notice("Сначала выберите чат (Enter в списке чатов)");
+            return;
+        };
+        match self.config.quick_emoji_for(key).map(str::to_string) {
+            Some(emoji) => self.queue_text_message(chat_id, emoji, None, false),
+            None => self.show_notice(&format!("Нет эмодзи для e {} в quick_emoji", key)),
+        }
+    }
+
+    // from_draft - текст взят из поля ввода, и при ошибке его нужно туда вернуть
+    fn queue_text_message(&mut self, chat_id: i64, text: String, reply_to: Option<i32>, from_draft: bool) {
+        let parts = if self.config.split_long_messages {
+            split_message(&text, MESSAGE_LENGTH_LIMIT)
+        } else {
+            vec![text]
+        };
+
+        // Сразу показываем сообщения в ленте со статусом "отправляется"
+        let draft = Some(self.next_local_message_id).filter(|_| from_draft);
+        let mut queued = Vec::new();
+        for (i, part) in parts.into_iter().enumerate() {
+            let local_id = self.next_local_message_id;
+            self.next_local_message_id -= 1;
+            // Ответом отправляется только первая часть длинного сообщения
+            let part_reply_to = reply_to.filter(|_| i == 0);
+            self.messages.push(Message {
+                id: local_id,
+                text: part.clone(),
+                from: "Вы".to_string(),
+                timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+                chat_id,
+                r#type: "text".to_string(),
+                reply_to_id: part_reply_to,
+                send_state: Some(SendState::Sending),
+                ..Default::default()
+            });
+            queued.push((local_id, part, part_reply_to));
+        }
+
+        // Отправляем части по порядку в фоне, результат применится в update().
+        // После первой ошибки остальные части не отправляем, чтобы не нарушить порядок.
+        let api_client = self.api_client.clone();
+        let tx = self.send_results_tx.clone();
+        tokio::spawn(async move {
+            let mut failed = false;
+            for (local_id, part, reply_to) in queued {
+                let (result, elapsed) = if failed {
+                    (Err(anyhow::anyhow!("предыдущая часть сообщения не отправлена")), None)
+                } else {
+                    let started = Instant::now();
+                    let result = match reply_to {
+                        Some(message_id) => api_client.reply_message(chat_id, message_id, &part).await,
+                        None => api_client.send_message(chat_id, &part).await,
+                    };
+                    (result, Some(started.elapsed()))
+                };
+                failed = !matches!(&result, Ok(response) if response.success);
+                let _ = tx.send((local_id, draft, result, elapsed));
+            }
+        });
+    }
+
+    // Ctrl+S: черновик, а без него текст выбранного сообщения - в «Избранное» (чат с самим собой,
+    // его id совпадает с id аккаунта)
+    pub async fn send_to_saved_messages(&mut self) -> Result<()> {
+        let Some(self_id) = self.me.as_ref().map(|me| me.id) else {
+            self.show_notice("Аккаунт еще не загружен, «Избранное» недоступно");
+            return Ok(());
+        };
+        let from_draft = !self.message_input.trim().is_empty();
+        let text = if from_draft {
+            self.message_input.clone()
+        } else {
+            match self.messages.get(self.selected_message_index).filter(|_| self.focus_on_messages) {
+                Some(msg) if !msg.text.is_empty() => msg.text.clone(),
+                _ => {
+                    self.show_notice("Нечего сохранять: черновик пуст, у выбранного сообщения нет текста");
                     return Ok(());
                 }
+            }
+        };
 
+        let response = self.api_client.send_message(self_id, &text).await?;
+        if !response.success {
+            return Err(anyhow::anyhow!(response.message));
+        }
+        if from_draft {
+            self.message_input.clear();
+            if self.state == AppState::MessageInput {
+                self.state = AppState::Main;
+            }
+        }
+        self.show_notice(if from_draft { "Черновик сохранен в «Избранное»" } else { "Сообщение сохранено в «Избранное»" });
+        Ok(())
+    }
 
+    // Ctrl+L при наборе: спросить время отложенной отправки черновика
+    pub fn start_schedule_input(&mut self) {
+        if self.message_input.trim().is_empty() {
+            self.show_notice("Нечего планировать: сообщение пустое");
+            return;
+        }
+        self.schedule_input.clear();
+        self.state = AppState::ScheduleInput;
+    }
 
-                // Инициализируем состояние аудио плеера
-                self.audio_player.current_message_id = Some(msg.id);
-                self.audio_player.current_position = Duration::ZERO;
-                self.audio_player.total_duration = msg.audio_duration.map(|d| Duration::from_secs(d as u64));
-                self.audio_player.is_playing = true;
-                self.audio_player.current_file_path = Some(audio_path.clone()); // Store file path for restart functionality
+    pub fn cancel_schedule_input(&mut self) {
+        self.schedule_input.clear();
+        self.state = AppState::MessageInput;
+    }
 
-                // Пробуем разные плееры для воспроизведения аудио с усилением громкости
-                // ffplay как основной (работает надежно), mpv как запасной
-                let audio_players = vec![
-                    ("ffplay", vec!["-nodisp", "-autoexit", "-af", "volume=10"]),
-                    ("mpv", vec![
-                        "--volume=200",
-                        "--input-ipc-server=/tmp/mpv-socket",
-                        "--input-ipc-server=/tmp/mpv-socket:rw"  // Явно указываем права на чтение/запись
-                    ]), // Для перемотки
-                    ("mplayer", vec!["-really-quiet", "-noconsolecontrols", "-af", "volume=10"]),
-                    ("play", vec!["-v", "10"]), // SoX play with 10x volume boost
-                    ("paplay", vec![]), // PulseAudio player (no volume control)
-                ];
+    pub async fn schedule_message(&mut self) -> Result<()> {
+        let Some(chat_id) = self.selected_chat.as_ref().map(|c| c.id) else {
+            return Ok(());
+        };
+        // При неверном времени остаемся в поле ввода времени, чтобы его можно было исправить
+        let send_at = match parse_schedule_time(&self.schedule_input, chrono::Local::now()) {
+            Ok(send_at) => send_at,
+            Err(reason) => {
+                self.show_notice(&format!("Неверное время: {}", reason));
+                return Ok(());
+            }
+        };
 
-                for (player, args) in audio_players {
-                    log::info!("Пробуем плеер: {}", player);
+        let text = self.message_input.clone();
+        let response = self.api_client.schedule_message(chat_id, &text, send_at).await?;
+        if !response.success {
+            self.show_error(&response.message);
+            return Ok(());
+        }
 
-                    let mut cmd = std::process::Command::new(player);
-                    for arg in &args {
-                        cmd.arg(arg);
-                    }
-                    cmd.arg(audio_path);
+        let local_id = self.next_local_message_id;
+        self.next_local_message_id -= 1;
+        self.messages.push(Message {
+            id: local_id,
+            text,
+            from: "Вы".to_string(),
+            timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            chat_id,
+            r#type: "text".to_string(),
+            scheduled_at: Some(send_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, false)),
+            ..Default::default()
+        });
+        self.message_input.clear();
+        self.schedule_input.clear();
+        self.state = AppState::Main;
+        self.show_notice(&format!("Сообщение запланировано на {}", send_at.format("%d.%m %H:%M")));
+        Ok(())
+    }
 
-                    // Подавляем вывод для ffplay и других плееров
-                    if player == "ffplay" {
-                        cmd.stdout(std::process::Stdio::null())
-                           .stderr(std::process::Stdio::null());
-                    } else if player == "mplayer" {
-                        cmd.stdout(std::process::Stdio::null())
-                           .stderr(std::process::Stdio::null());
-                    } else if player == "mpv" {
-                        cmd.stdout(std::process::Stdio::null())
-                           .stderr(std::process::Stdio::null());
-                    } else if player == "play" {
-                        cmd.stdout(std::process::Stdio::null())
-                           .stderr(std::process::Stdio::null());
+    fn process_send_results(&mut self) {
+        while let Ok((local_id, draft, result, elapsed)) = self.send_results_rx.try_recv() {
+            self.needs_redraw = true;
+            if let Some(elapsed) = elapsed {
+                self.note_latency("отправка", elapsed);
+            }
+            let position = self.messages
+                .iter()
+                .position(|m| m.id == local_id && m.send_state == Some(SendState::Sending));
+
+            let error = match result {
+                Ok(response) if response.success => {
+                    if let Some(pos) = position {
+                        let server_copy = response.message_id
+                            .and_then(|message_id| self.messages.iter().position(|m| m.id == message_id));
+                        let pos = match server_copy {
+                            // Long-poll опередил ответ на отправку: серверная копия уже в ленте, локальную убираем
+                            Some(existing) => {
+                                self.messages.remove(pos);
+                                if self.selected_message_index > pos {
+                                    self.selected_message_index -= 1;
+                                }
+                                self.selected_message_index = self.selected_message_index.min(self.messages.len().saturating_sub(1));
+                                if existing > pos { existing - 1 } else { existing }
+                            }
+                            None => {
+                                let msg = &mut self.messages[pos];
+                                msg.send_state = Some(SendState::Sent);
+                                if let Some(message_id) = response.message_id {
+                                    msg.id = message_id;
+                                }
+                                pos
+                            }
+                        };
+                        if self.config.select_after_send {
+                            self.selected_message_index = pos;
+                            self.follow_tail = pos + 1 == self.messages.len();
+                            self.focus_messages();
+                        }
                     }
+                    continue;
+                }
+                Ok(response) => response.message,
+                Err(e) => format!("Ошибка отправки сообщения: {}", e),
+            };
+
+            // Отправка не удалась: убираем локальную копию, текст черновика возвращаем в поле ввода
+            if let Some(pos) = position {
+                let msg = self.messages.remove(pos);
+                if let Some(draft) = draft {
+                    self.restore_failed_draft(draft, &msg.text);
+                }
+                if self.selected_message_index >= self.messages.len() {
+                    self.selected_message_index = self.messages.len().saturating_sub(1);
+                }
+            }
+            // Не show_error: экран ошибки закрывается только выходом, и возвращенный черновик пропал бы
+            if draft.is_some() {
+                self.show_notice(&format!("{} (текст возвращен в поле ввода)", error));
+            } else {
+                self.show_notice(&error);
+            }
+        }
+    }
 
-                    log::info!("Запускаем команду: {:?}", cmd);
-                    let result = cmd.spawn();
+    // Неотправленные части длинного сообщения склеиваются обратно по порядку. Новый черновик,
+    // начатый после отправки, не затираем: возвращенный текст встает перед ним отдельной строкой
+    fn restore_failed_draft(&mut self, draft: i32, text: &str) {
+        let end = match self.restored_draft {
+            Some((id, end)) if id == draft && self.message_input.is_char_boundary(end) => {
+                self.message_input.insert_str(end, text);
+                end + text.len()
+            }
+            _ if self.message_input.trim().is_empty() => {
+                self.message_input = text.to_string();
+                text.len()
+            }
+            _ => {
+                self.message_input.insert_str(0, &format!("{}\n", text));
+                text.len()
+            }
+        };
+        self.restored_draft = Some((draft, end));
+    }
 
-                    match result {
-                        Ok(child) => {
-                            log::info!("{} успешно запущен, PID: {}", player, child.id());
-                            self.audio_player.process_id = Some(child.id() as u32);
-                            // Устанавливаем время начала воспроизведения
-                            self.audio_start_time = Some(Instant::now());
-                            return Ok(());
-                        }
-                        Err(e) => {
-                            log::warn!("Не удалось запустить {}: {}", player, e);
-                            continue;
-                        }
-                    }
+    // Ввод символа в поле сообщения с учетом лимита длины
+    pub fn push_message_char(&mut self, c: char) {
+        let over_limit = message_length(&self.message_input) + c.len_utf16() > MESSAGE_LENGTH_LIMIT;
+        if over_limit && !self.config.split_long_messages {
+            return;
+        }
+        self.message_input.push(c);
+    }
+
+    pub fn open_raw_json(&mut self) {
+        let Some(msg) = self.messages.get(self.selected_message_index) else {
+            return;
+        };
+        match serde_json::to_string_pretty(msg) {
+            Ok(json) => {
+                self.raw_json = json;
+                self.raw_json_scroll = 0;
+                self.state = AppState::RawJson;
+            }
+            Err(e) => self.show_error(&format!("Ошибка сериализации сообщения: {}", e)),
+        }
+    }
+
+    pub fn scroll_raw_json(&mut self, direction: i32) {
+        let max_scroll = self.raw_json.lines().count().saturating_sub(1) as u16;
+        self.raw_json_scroll = if direction > 0 {
+            (self.raw_json_scroll + 1).min(max_scroll)
+        } else {
+            self.raw_json_scroll.saturating_sub(1)
+        };
+    }
+
+    pub fn copy_raw_json(&mut self) {
+        match crate::clipboard::copy(&self.raw_json) {
+            Ok(()) => self.show_notice("JSON скопирован в буфер обмена"),
+            Err(e) => self.show_notice(&format!("Не удалось скопировать: {}", e)),
+        }
+    }
+
+    pub fn close_raw_json(&mut self) {
+        self.raw_json.clear();
+        self.state = AppState::Main;
+    }
+
+    // "/" в сообщениях: ввод поискового запроса, начинаем с текущего термина
+    pub fn start_search(&mut self) {
+        self.search_input = self.search_term.clone().unwrap_or_default();
+        self.search_origin = Some((self.selected_message_index, self.message_scroll_offset));
+        self.state = AppState::SearchInput;
+        self.update_incremental_search();
+    }
+
+    pub fn push_search_char(&mut self, c: char) {
+        self.search_input.push(c);
+        self.update_incremental_search();
+    }
+
+    pub fn pop_search_char(&mut self) {
+        self.search_input.pop();
+        self.update_incremental_search();
+    }
+
+    // Пока запрос набирается, выделение стоит на ближайшем от исходного сообщения совпадении
+    // (исходное тоже подходит, в сторону старых, как n)
+    fn update_incremental_search(&mut self) {
+        self.restore_search_origin();
+        let term = self.search_input.trim().to_lowercase();
+        let len = self.messages.len();
+        if term.is_empty() || len == 0 {
+            return;
+        }
+        let from = self.selected_message_index;
+        let found = (0..len)
+            .map(|step| (from + len - step) % len)
+            .find(|&i| message_matches(&self.messages[i], &term));
+        if let Some(index) = found {
+            self.set_message_selection(index, self.calculate_visible_capacity());
+        }
+    }
+
+    fn restore_search_origin(&mut self) {
+        if let Some((index, offset)) = self.search_origin {
+            self.selected_message_index = index.min(self.messages.len().saturating_sub(1));
+            self.message_scroll_offset = offset;
+        }
+    }
+
+    // Термин для подсветки: набираемый запрос, пока идет ввод, иначе принятый
+    pub fn active_search_term(&self) -> Option<&str> {
+        if self.state == AppState::SearchInput {
+            Some(self.search_input.trim()).filter(|t| !t.is_empty())
+        } else {
+            self.search_term.as_deref()
+        }
+    }
+
+    // Для строки статуса: номер выделенного совпадения (с самого нового, как идет n) и сколько их всего
+    pub fn search_matches(&self) -> Option<(Option<usize>, usize)> {
+        let term = self.active_search_term()?.to_lowercase();
+        let matches: Vec<usize> = (0..self.messages.len())
+            .rev()
+            .filter(|&i| message_matches(&self.messages[i], &term))
+            .collect();
+        let position = matches.iter().position(|&i| i == self.selected_message_index).map(|p| p + 1);
+        Some((position, matches.len()))
+    }
+
+    pub fn cancel_search_input(&mut self) {
+        self.restore_search_origin();
+        self.search_origin = None;
+        self.search_input.clear();
+        self.state = AppState::Main;
+    }
+
+    pub fn submit_search(&mut self) {
+        let term = self.search_input.trim().to_string();
+        self.search_input.clear();
+        self.state = AppState::Main;
+        if term.is_empty() {
+            self.restore_search_origin();
+            self.search_origin = None;
+            self.search_term = None;
+            return;
+        }
+        let matched = self.messages
+            .get(self.selected_message_index)
+            .is_some_and(|m| message_matches(m, &term.to_lowercase()));
+        if matched {
+            // Ctrl+O возвращает туда, откуда начинали поиск
+            if let Some(origin) = self.search_origin.take() {
+                if origin.0 != self.selected_message_index {
+                    self.jump_back_stack.push(origin);
+                    self.jump_forward_stack.clear();
                 }
+            }
+        } else {
+            self.search_origin = None;
+            self.show_notice(&format!("Не найдено: {}", term));
+        }
+        self.search_term = Some(term);
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search_term = None;
+    }
+
+    // n - к более старому совпадению, N - к более новому; по краям ленты поиск идет по кругу
+    pub fn search_next(&mut self, direction: i32) {
+        let Some(term) = self.search_term.as_ref().map(|t| t.to_lowercase()) else {
+            return;
+        };
+        let len = self.messages.len();
+        if len == 0 {
+            return;
+        }
+        let found = (1..=len)
+            .map(|step| {
+                let offset = if direction < 0 { len - step } else { step };
+                (self.selected_message_index + offset) % len
+            })
+            .find(|&i| message_matches(&self.messages[i], &term));
+
+        match found {
+            Some(index) => {
+                let snapshot = (self.selected_message_index, self.message_scroll_offset);
+                self.jump_back_stack.push(snapshot);
+                self.jump_forward_stack.clear();
+                self.set_message_selection(index, self.calculate_visible_capacity());
+            }
+            None => {
+                let term = self.search_term.clone().unwrap_or_default();
+                self.show_notice(&format!("Не найдено: {}", term));
+            }
+        }
+    }
+
+    // [ / ] - предыдущее / следующее сообщение того же отправителя; по краям ленты по кругу
+    pub fn select_same_sender_message(&mut self, direction: i32) {
+        let Some(selected) = self.messages.get(self.selected_message_index) else {
+            return;
+        };
+        let (from_id, from) = (selected.from_id, selected.from.clone());
+        let same_sender = |m: &Message| match (from_id, m.from_id) {
+            (Some(a), Some(b)) => a == b,
+            _ => m.from == from,
+        };
+        let len = self.messages.len();
+        let found = (1..len)
+            .map(|step| {
+                let offset = if direction < 0 { len - step } else { step };
+                (self.selected_message_index + offset) % len
+            })
+            .find(|&i| same_sender(&self.messages[i]));
+
+        match found {
+            Some(index) => {
+                let snapshot = (self.selected_message_index, self.message_scroll_offset);
+                self.jump_back_stack.push(snapshot);
+                self.jump_forward_stack.clear();
+                self.set_message_selection(index, self.calculate_visible_capacity());
+            }
+            None => self.show_notice(&format!("Других сообщений от {} не загружено", from)),
+        }
+    }
+
+    pub fn show_notice(&mut self, message: &str) {
+        self.notice = Some(message.to_string());
+        self.needs_redraw = true;
+    }
+
+    // R в сообщениях: черновик уйдет ответом на выделенное сообщение
+    pub fn reply_to_message(&mut self) {
+        let Some(chat_id) = self.selected_chat.as_ref().map(|c| c.id) else {
+            return;
+        };
+        match self.messages.get(self.selected_message_index) {
+            // У неотправленных сообщений еще нет настоящего id
+            Some(msg) if msg.id > 0 => {
+                self.reply_to = Some((chat_id, msg.id));
+                self.enter_message_input(false);
+            }
+            Some(_) => self.show_notice("Сообщение еще не отправлено, ответить на него нельзя"),
+            None => {}
+        }
+    }
+
+    pub fn cancel_reply(&mut self) {
+        self.reply_to = None;
+    }
+
+    // Сообщение, на которое отвечаем, пропало (удалено, сменился чат) - отвечать не на что
+    fn drop_stale_reply_target(&mut self) {
+        let current_chat = self.selected_chat.as_ref().map(|c| c.id);
+        if let Some((chat_id, message_id)) = self.reply_to {
+            if current_chat != Some(chat_id) || !self.messages.iter().any(|m| m.id == message_id) {
+                self.reply_to = None;
+            }
+        }
+    }
+
+    // Подпись цели ответа для строки ввода: отправитель и начало текста
+    pub fn reply_target_label(&self) -> Option<String> {
+        let (_, message_id) = self.reply_to?;
+        let msg = self.messages.iter().find(|m| m.id == message_id)?;
+        let text = if msg.text.is_empty() { format!("[{}]", msg.r#type) } else { msg.text.replace('\n', " ") };
+        let snippet: String = text.chars().take(40).collect();
+        let ellipsis = if text.chars().count() > 40 { "…" } else { "" };
+        Some(format!("{}{}: {}{}", self.symbols.reply, msg.from, snippet, ellipsis))
+    }
+
+    // Вход в режим ввода: i - как есть, a - дописать к черновику, o - с новой строки
+    pub fn enter_message_input(&mut self, new_line: bool) {
+        if self.selected_chat.is_none() {
+            self.show_notice("Сначала выберите чат (Enter в списке чатов)");
+            return;
+        }
+        if new_line && !self.message_input.is_empty() {
+            self.message_input.push('\n');
+        }
+        self.input_scroll_back = 0;
+        self.state = AppState::MessageInput;
+    }
+
+    // PageUp/PageDown при наборе: листаем ленту на полэкрана, черновик и фокус ввода остаются
+    pub fn scroll_while_typing(&mut self, direction: i32) {
+        let step = (self.visible_capacity / 2).max(1);
+        self.input_scroll_back = if direction < 0 {
+            (self.input_scroll_back + step).min(self.messages.len().saturating_sub(1))
+        } else {
+            self.input_scroll_back.saturating_sub(step)
+        };
+    }
 
-                // Если ни один плеер не сработал
-                log::error!("Не удалось найти подходящий аудио плеер");
-                self.audio_player.is_playing = false;
-                self.audio_player.current_message_id = None;
-                Err(anyhow::anyhow!("Не удалось найти подходящий аудио плеер. Установите mpv, ffplay, mplayer, sox или alsa-utils"))
-            } else {
-                log::error!("Путь к файлу аудио сообщения не найден в сообщении");
-                return Err(anyhow::anyhow!("Путь к файлу аудио сообщения не найден"));
-            }
+    // B: блокируем отправителя выбранного сообщения или собеседника в личном чате
+    pub fn request_block_user(&mut self) {
+        let target = if self.focus_on_messages {
+            self.messages
+                .get(self.selected_message_index)
+                .and_then(|m| m.from_id.map(|id| (id, m.from.clone())))
         } else {
-            log::error!("Сообщение не найдено по индексу {}", self.selected_message_index);
-            return Err(anyhow::anyhow!("Сообщение не найдено"));
+            self.chats
+                .get(self.selected_chat_index)
+                .filter(|c| c.r#type == "user")
+                .map(|c| (c.id, c.title.clone()))
+        };
+
+        match target {
+            // Свой id - это и чат «Избранное»: после блокировки он пропал бы из списка
+            Some((user_id, _)) if Some(user_id) == self.me.as_ref().map(|me| me.id) => {
+                self.show_notice("Нельзя заблокировать самого себя");
+            }
+            Some((user_id, name)) => {
+                self.pending_action = Some(PendingAction::BlockUser { user_id, name });
+                self.state = AppState::Confirm;
+            }
+            None => self.show_notice("Не удалось определить пользователя для блокировки"),
         }
     }
 
-    fn get_terminal_window_id(&self) -> Option<u64> {
-        // Пробуем различные способы получить ID окна терминала
+    pub fn cancel_pending_action(&mut self) {
+        self.pending_action = None;
+        self.state = AppState::Main;
+    }
 
-        // Способ 1: через переменную окружения WINDOWID (для X11)
-        if let Ok(window_id_str) = std::env::var("WINDOWID") {
-            if let Ok(wid) = window_id_str.parse::<u64>() {
-                // Проверяем, что ID не равен 0 (некорректное значение)
-                if wid > 0 {
-                    log::info!("Получен window ID из переменной WINDOWID: {}", wid);
-                    return Some(wid);
-                } else {
-                    log::warn!("WINDOWID содержит некорректное значение: {}", wid);
+    pub async fn confirm_pending_action(&mut self) -> Result<()> {
+        let Some(action) = self.pending_action.take() else {
+            self.state = AppState::Main;
+            return Ok(());
+        };
+        self.state = AppState::Main;
+
+        match action {
+            PendingAction::BlockUser { user_id, name } => {
+                let response = self.api_client.block_user(user_id).await?;
+                if !response.success {
+                    return Err(anyhow::anyhow!(response.message));
                 }
-            } else {
-                log::warn!("Не удалось распарсить WINDOWID: {}", window_id_str);
+                // Личный чат с заблокированным убираем из списка локально
+                self.chats.retain(|c| c.id != user_id);
+                if self.selected_chat.as_ref().map(|c| c.id) == Some(user_id) {
+                    self.selected_chat = None;
+                    self.messages.clear();
+                    self.focus_chats();
+                }
+                self.set_chat_selection(self.selected_chat_index);
+                self.show_notice(&format!("Пользователь {} заблокирован", name));
             }
-        } else {
-            log::debug!("Переменная WINDOWID не установлена");
+            PendingAction::OpenExternally { path, .. } => self.open_externally(&path),
+            PendingAction::ClearHistory { chat_id, .. } => self.clear_local_history(chat_id),
+            PendingAction::DeleteMessage { chat_id, message_id, .. } => {
+                let response = self.api_client.delete_message(chat_id, message_id).await?;
+                if !response.success {
+                    return Err(anyhow::anyhow!(response.message));
+                }
+                self.marked_messages.remove(&message_id);
+                self.load_messages().await?;
+                self.show_notice("Сообщение удалено");
+            }
+            PendingAction::DeleteMarked { chat_id, message_ids, .. } => self.delete_marked_messages(chat_id, message_ids).await?,
         }
+        Ok(())
+    }
 
-        // Способ 2: через xdotool (если доступен)
-        if let Ok(output) = std::process::Command::new("xdotool")
-            .args(&["getactivewindow"])
-            .output() {
-            if output.status.success() {
-                if let Ok(window_id_str) = String::from_utf8(output.stdout) {
-                    if let Ok(wid) = window_id_str.trim().parse::<u64>() {
-                        if wid > 0 {
-                            log::info!("Получен window ID через xdotool: {}", wid);
-                            return Some(wid);
-                        } else {
-                            log::warn!("xdotool вернул некорректный window ID: {}", wid);
-                        }
-                    } else {
-                        log::warn!("Не удалось распарсить вывод xdotool: {}", window_id_str);
-                    }
-                } else {
-                    log::warn!("Вывод xdotool не является валидной UTF-8 строкой");
-                }
-            } else {
-                log::debug!("xdotool не найден или вернул ошибку");
+    // Удаляем по одному; ошибка одного сообщения не останавливает остальные
+    async fn delete_marked_messages(&mut self, chat_id: i64, message_ids: Vec<i32>) -> Result<()> {
+        let total = message_ids.len();
+        let mut deleted = 0;
+        let mut last_error = None;
+        for message_id in message_ids {
+            match self.api_client.delete_message(chat_id, message_id).await {
+                Ok(response) if response.success => deleted += 1,
+                Ok(response) => last_error = Some(response.message),
+                Err(e) => last_error = Some(e.to_string()),
             }
         }
+        self.marked_messages.clear();
+        self.load_messages().await?;
+        match last_error {
+            None => self.show_notice(&format!("Удалено сообщений: {}", deleted)),
+            Some(error) => self.show_notice(&format!("Удалено {} из {}: {}", deleted, total, error)),
+        }
+        Ok(())
+    }
 
-        // Способ 3: через xprop (если доступен)
-        if let Ok(output) = std::process::Command::new("xprop")
-            .args(&["-root", "_NET_ACTIVE_WINDOW"])
-            .output() {
-            if output.status.success() {
-                if let Ok(output_str) = String::from_utf8(output.stdout) {
-                    // Парсим вывод вида "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x..."
-                    if let Some(hex_id) = output_str.split("0x").nth(1) {
-                        if let Some(hex_clean) = hex_id.split_whitespace().next() {
-                            if let Ok(wid) = u64::from_str_radix(hex_clean, 16) {
-                                if wid > 0 {
-                                    log::info!("Получен window ID через xprop: {}", wid);
-                                    return Some(wid);
-                                } else {
-                                    log::warn!("xprop вернул некорректный window ID: {}", wid);
-                                }
-                            } else {
-                                log::warn!("Не удалось распарсить hex значение: {}", hex_clean);
-                            }
-                        } else {
-                            log::warn!("Не удалось найти hex часть в выводе xprop: {}", output_str);
-                        }
-                    } else {
-                        log::warn!("Не найден hex ID в выводе xprop: {}", output_str);
-                    }
-                } else {
-                    log::warn!("Вывод xprop не является валидной UTF-8 строкой");
+    // d в сообщениях: удалить свое отправленное сообщение (после подтверждения),
+    // а если есть отмеченные - все отмеченные свои
+    pub async fn request_delete_message(&mut self) {
+        // Без /api/self свои сообщения не отличить от чужих; данные аккаунта могли не загрузиться при старте
+        if self.me.is_none() {
+            self.load_me().await;
+        }
+        let Some(my_id) = self.me.as_ref().map(|me| me.id) else {
+            self.show_notice("Не удалось определить текущего пользователя (/api/self), удаление недоступно");
+            return;
+        };
+        if !self.marked_messages.is_empty() {
+            self.request_delete_marked(my_id);
+            return;
+        }
+        let Some(msg) = self.messages.get(self.selected_message_index) else {
+            return;
+        };
+        if msg.from_id != Some(my_id) {
+            self.show_notice("Удалять можно только свои сообщения");
+            return;
+        }
+        if msg.id <= 0 {
+            self.show_notice("Сообщение еще не отправлено");
+            return;
+        }
+        let text = if msg.text.is_empty() { format!("[{}]", msg.r#type) } else { msg.text.replace('\n', " ") };
+        let mut preview: String = text.chars().take(60).collect();
+        if text.chars().count() > 60 {
+            preview.push('…');
+        }
+        self.pending_action = Some(PendingAction::DeleteMessage { chat_id: msg.chat_id, message_id: msg.id, preview });
+        self.state = AppState::Confirm;
+    }
+
+    fn request_delete_marked(&mut self, my_id: i64) {
+        let Some(chat_id) = self.selected_chat.as_ref().map(|c| c.id) else {
+            return;
+        };
+        // Порядок ленты, чтобы сообщения удалялись предсказуемо
+        let message_ids: Vec<i32> = self.messages
+            .iter()
+            .filter(|m| self.marked_messages.contains(&m.id))
+            .filter(|m| m.from_id == Some(my_id) && m.id > 0)
+            .map(|m| m.id)
+            .collect();
+        if message_ids.is_empty() {
+            self.show_notice("Среди отмеченных нет ваших сообщений: удалять можно только свои");
+            return;
+        }
+        let skipped = self.marked_messages.len() - message_ids.len();
+        self.pending_action = Some(PendingAction::DeleteMarked { chat_id, message_ids, skipped });
+        self.state = AppState::Confirm;
+    }
+
+    // C: спрятать историю открытого чата с экрана и диска (сервер не трогаем)
+    pub fn request_clear_history(&mut self) {
+        if let Some(chat) = &self.selected_chat {
+            self.pending_action = Some(PendingAction::ClearHistory { chat_id: chat.id, title: chat.title.clone() });
+            self.state = AppState::Confirm;
+        }
+    }
+
+    // Удаляем сообщения из памяти, кэш сообщений и скачанные бэкендом медиа этого чата;
+    // лента остается пустой до следующей загрузки (r)
+    fn clear_local_history(&mut self, chat_id: i64) {
+        let mut removed = 0;
+        for msg in self.messages.iter().filter(|m| m.chat_id == chat_id) {
+            let paths = [
+                &msg.image_path, &msg.sticker_path, &msg.video_path, &msg.video_preview_path,
+                &msg.voice_path, &msg.audio_path, &msg.location_map_path, &msg.webpage_image_path,
+            ];
+            // Удаляем только временные файлы бэкенда, а не произвольные пути
+            for path in paths.into_iter().flatten().filter(|p| p.starts_with("/tmp/vi-tg_")) {
+                if std::fs::remove_file(path).is_ok() {
+                    removed += 1;
                 }
-            } else {
-                log::debug!("xprop не найден или вернул ошибку");
             }
         }
+        cache::remove_messages(chat_id);
+        self.messages.retain(|m| m.chat_id != chat_id);
+        self.marked_messages.clear();
+        self.translations.retain(|&(chat, _), _| chat != chat_id);
+        self.shown_translations.retain(|&(chat, _)| chat != chat_id);
+        self.selected_message_index = 0;
+        self.message_scroll_offset = 0;
+        crate::ui::clear_image_cache();
+        self.show_notice(&format!("Локальная история очищена, удалено файлов: {}", removed));
+    }
 
-        log::warn!("Не удалось получить корректный window ID ни одним из способов");
-        None
+    // !: жалоба на выбранное сообщение, причина выбирается из списка
+    pub fn open_report_picker(&mut self) {
+        let chat_id = self.selected_chat.as_ref().map(|c| c.id);
+        let message_id = self.messages.get(self.selected_message_index).map(|m| m.id).filter(|&id| id > 0);
+        if let (Some(chat_id), Some(message_id)) = (chat_id, message_id) {
+            self.report_target = Some((chat_id, message_id));
+            self.report_reason_index = 0;
+            self.state = AppState::ReportReason;
+        }
     }
 
-    pub async fn select_chat(&mut self) -> Result<()> {
-        if self.selected_chat_index < self.chats.len() {
-            self.selected_chat = Some(self.chats[self.selected_chat_index].clone());
-            self.last_loaded_chat_id = self.selected_chat.as_ref().map(|c| c.id);
-            self.load_messages().await?;
+    pub fn move_report_reason(&mut self, direction: i32) {
+        let last = REPORT_REASONS.len() - 1;
+        self.report_reason_index = if direction > 0 {
+            (self.report_reason_index + 1).min(last)
+        } else {
+            self.report_reason_index.saturating_sub(1)
+        };
+    }
+
+    pub fn cancel_report(&mut self) {
+        self.report_target = None;
+        self.state = AppState::Main;
+    }
+
+    pub async fn submit_report(&mut self) -> Result<()> {
+        self.state = AppState::Main;
+        let Some((chat_id, message_id)) = self.report_target.take() else {
+            return Ok(());
+        };
+        let (reason, label) = REPORT_REASONS[self.report_reason_index];
+        let response = self.api_client.report_message(chat_id, message_id, reason).await?;
+        if !response.success {
+            return Err(anyhow::anyhow!(response.message));
         }
+        self.show_notice(&format!("Жалоба отправлена: {}", label));
         Ok(())
     }
 
-    pub async fn send_message(&mut self) -> Result<()> {
-        if let Some(chat) = &self.selected_chat {
-            match self.api_client.send_message(chat.id, &self.message_input).await {
-                Ok(response) => {
-                    if response.success {
-                        // Обновляем сообщения после отправки
-                        self.load_messages().await?;
-                    } else {
-                        self.show_error(&response.message);
-                    }
-                }
-                Err(e) => {
-                    self.show_error(&format!("Ошибка отправки сообщения: {}", e));
+    // b: поставить или снять закладку на выбранном сообщении
+    pub fn toggle_bookmark_selected_message(&mut self) {
+        let chat_id = self.selected_chat.as_ref().map(|c| c.id);
+        let message_id = self.messages.get(self.selected_message_index).map(|m| m.id).filter(|&id| id > 0);
+        let (Some(chat_id), Some(message_id)) = (chat_id, message_id) else {
+            return;
+        };
+        let bookmark = (chat_id, message_id);
+        if let Some(pos) = self.config.bookmarks.iter().position(|b| *b == bookmark) {
+            self.config.bookmarks.remove(pos);
+            self.show_notice("Закладка удалена");
+        } else {
+            self.config.bookmarks.push(bookmark);
+            self.show_notice("Закладка добавлена");
+        }
+        if let Err(e) = self.config.save() {
+            log::error!("Ошибка сохранения конфига: {}", e);
+        }
+    }
+
+    pub fn open_bookmarks(&mut self) {
+        if self.config.bookmarks.is_empty() {
+            self.show_notice("Закладок нет (b на сообщении - добавить)");
+            return;
+        }
+        self.bookmark_labels = self.config.bookmarks
+            .iter()
+            .map(|&(chat_id, message_id)| self.bookmark_label(chat_id, message_id))
+            .collect();
+        self.bookmark_index = self.bookmark_index.min(self.bookmark_labels.len() - 1);
+        self.state = AppState::Bookmarks;
+    }
+
+    // Подпись закладки: название чата и начало текста; сообщения других чатов берем из кэша
+    fn bookmark_label(&self, chat_id: i64, message_id: i32) -> String {
+        let chat_title = self.chat_title(chat_id);
+        let message = if self.messages.first().map(|m| m.chat_id) == Some(chat_id) {
+            self.messages.iter().find(|m| m.id == message_id).cloned()
+        } else {
+            cache::load_messages(chat_id).and_then(|messages| messages.into_iter().find(|m| m.id == message_id))
+        };
+        let preview = match message {
+            Some(msg) => {
+                let text = msg.caption.clone().filter(|c| !c.is_empty()).unwrap_or(msg.text.clone());
+                let first_line = text.lines().next().unwrap_or("").to_string();
+                if first_line.is_empty() {
+                    format!("{}: [{}]", msg.from, msg.r#type)
+                } else {
+                    let short: String = first_line.chars().take(60).collect();
+                    let ellipsis = if first_line.chars().count() > 60 { "…" } else { "" };
+                    format!("{}: {}{}", msg.from, short, ellipsis)
                 }
             }
+            None => format!("сообщение #{}", message_id),
+        };
+        format!("{} — {}", chat_title, preview)
+    }
+
+    pub fn move_bookmark_selection(&mut self, direction: i32) {
+        let last = self.bookmark_labels.len().saturating_sub(1);
+        self.bookmark_index = if direction > 0 {
+            (self.bookmark_index + 1).min(last)
+        } else {
+            self.bookmark_index.saturating_sub(1)
+        };
+    }
+
+    pub fn remove_selected_bookmark(&mut self) {
+        if self.bookmark_index >= self.config.bookmarks.len() {
+            return;
+        }
+        self.config.bookmarks.remove(self.bookmark_index);
+        self.bookmark_labels.remove(self.bookmark_index);
+        if let Err(e) = self.config.save() {
+            log::error!("Ошибка сохранения конфига: {}", e);
+        }
+        if self.bookmark_labels.is_empty() {
+            self.close_bookmarks();
+        } else {
+            self.bookmark_index = self.bookmark_index.min(self.bookmark_labels.len() - 1);
+        }
+    }
+
+    pub fn close_bookmarks(&mut self) {
+        self.bookmark_labels.clear();
+        self.state = AppState::Main;
+    }
+
+    // Enter в списке закладок: открываем чат (если нужно) и выделяем сообщение
+    pub async fn open_selected_bookmark(&mut self) -> Result<()> {
+        let Some(&(chat_id, message_id)) = self.config.bookmarks.get(self.bookmark_index) else {
+            return Ok(());
+        };
+        self.close_bookmarks();
+
+        if self.selected_chat.as_ref().map(|c| c.id) != Some(chat_id) {
+            let Some(pos) = self.chats.iter().position(|c| c.id == chat_id) else {
+                self.show_notice("Чат закладки не найден в списке чатов");
+                return Ok(());
+            };
+            self.selected_chat_index = pos;
+            self.select_chat().await?;
         }
+        self.focus_messages();
 
+        let snapshot = (self.selected_message_index, self.message_scroll_offset);
+        if self.select_message_by_id(message_id) {
+            self.jump_back_stack.push(snapshot);
+            self.jump_forward_stack.clear();
+        } else {
+            self.show_notice("Сообщение закладки не загружено (слишком старое)");
+        }
         Ok(())
     }
 
     pub fn show_error(&mut self, message: &str) {
         self.error_message = message.to_string();
         self.state = AppState::Error;
+        self.needs_redraw = true;
     }
 
     pub fn restart_player_at_position(&mut self) {
@@ -1312,7 +4487,6 @@ impl App {
             let position_seconds = self.audio_player.current_position.as_secs();
 
             // Создаем строковые значения для использования в векторах
-            let ffplay_ss_arg = format!("-ss {}", position_seconds);
             let mpv_start_arg = format!("--start={}", position_seconds);
             let mplayer_ss_arg = format!("-ss {}", position_seconds);
             let ffplay_position_str = position_seconds.to_string();
@@ -1362,7 +4536,7 @@ impl App {
                             player, position_seconds, child.id());
 
                         // Обновляем process_id и устанавливаем новое время начала
-                        self.audio_player.process_id = Some(child.id() as u32);
+                        self.audio_player.process_id = Some(child.id());
                         // Корректируем время начала так, чтобы позиция продолжала отображаться правильно
                         self.audio_start_time = Some(std::time::Instant::now() - self.audio_player.current_position);
 
@@ -1382,10 +4556,149 @@ impl App {
     }
 
     pub fn get_current_chat_title(&self) -> String {
-        self.selected_chat
+        let title = self.selected_chat
             .as_ref()
             .map(|c| c.title.clone())
-            .unwrap_or_else(|| "Выберите чат".to_string())
+            .unwrap_or_else(|| "Выберите чат".to_string());
+        if self.offline {
+            format!("{} [офлайн — показаны сохраненные сообщения]", title)
+        } else {
+            title
+        }
+    }
+
+    // Время последнего запроса к бэкенду, чтобы отличать медленный бэкенд от медленной отрисовки
+    fn note_latency(&mut self, request: &'static str, elapsed: Duration) {
+        log::debug!("Запрос «{}» занял {} мс", request, elapsed.as_millis());
+        self.last_latency = Some((request, elapsed));
+    }
+
+    fn expand_status_format(&self, format: &str) -> String {
+        expand_template(format, |name| self.status_placeholder(name))
+    }
+
+    // "+" в сообщениях: все реакции выбранного сообщения, включая не поместившиеся в строку
+    pub fn open_reactions(&mut self) {
+        let has_reactions = self.messages
+            .get(self.selected_message_index)
+            .and_then(|m| m.reactions.as_ref())
+            .is_some_and(|r| !r.is_empty());
+        if has_reactions {
+            self.state = AppState::Reactions;
+        } else {
+            self.show_notice("На сообщении нет реакций");
+        }
+    }
+
+    pub fn close_reactions(&mut self) {
+        self.state = AppState::Main;
+    }
+
+    // 1-5 в ленте: быстрая реакция из quick_reactions; повторное нажатие той же снимает ее
+    pub async fn quick_react(&mut self, slot: usize) -> Result<()> {
+        let Some(emoji) = self.config.quick_reactions.get(slot).cloned() else {
+            self.show_notice(&format!("Быстрая реакция {} не задана (quick_reactions)", slot + 1));
+            return Ok(());
+        };
+        let Some(msg) = self.messages.get(self.selected_message_index).filter(|m| m.id > 0) else {
+            return Ok(());
+        };
+        let already_chosen = msg.reactions.as_ref().is_some_and(|r| r.iter().any(|reaction| reaction.chosen && reaction.emoji == emoji));
+        if already_chosen {
+            return self.remove_my_reaction().await;
+        }
+        let (chat_id, message_id) = (msg.chat_id, msg.id);
+
+        let response = self.api_client.set_reaction(chat_id, message_id, &emoji).await?;
+        if !response.success {
+            return Err(anyhow::anyhow!(response.message));
+        }
+        if let Some(msg) = self.messages.iter_mut().find(|m| m.chat_id == chat_id && m.id == message_id) {
+            let reactions = msg.reactions.get_or_insert_with(Vec::new);
+            for reaction in reactions.iter_mut().filter(|r| r.chosen) {
+                reaction.chosen = false;
+                reaction.count -= 1;
+            }
+            match reactions.iter_mut().find(|r| r.emoji == emoji) {
+                Some(reaction) => {
+                    reaction.chosen = true;
+                    reaction.count += 1;
+                }
+                None => reactions.push(crate::Reaction { emoji, count: 1, chosen: true }),
+            }
+            reactions.retain(|r| r.count > 0);
+        }
+        Ok(())
+    }
+
+    // -: снять свою реакцию с выбранного сообщения (своя отмечена в reactions как chosen)
+    pub async fn remove_my_reaction(&mut self) -> Result<()> {
+        let Some(msg) = self.messages.get(self.selected_message_index).filter(|m| m.id > 0) else {
+            return Ok(());
+        };
+        let has_mine = msg.reactions.as_ref().is_some_and(|r| r.iter().any(|reaction| reaction.chosen));
+        if !has_mine {
+            self.show_notice("Вы не ставили реакцию на это сообщение");
+            return Ok(());
+        }
+        let (chat_id, message_id) = (msg.chat_id, msg.id);
+
+        let response = self.api_client.remove_reaction(chat_id, message_id).await?;
+        if !response.success {
+            return Err(anyhow::anyhow!(response.message));
+        }
+        if let Some(reactions) = self.messages
+            .iter_mut()
+            .find(|m| m.chat_id == chat_id && m.id == message_id)
+            .and_then(|m| m.reactions.as_mut())
+        {
+            for reaction in reactions.iter_mut().filter(|r| r.chosen) {
+                reaction.chosen = false;
+                reaction.count -= 1;
+            }
+            reactions.retain(|r| r.count > 0);
+        }
+        if self.state == AppState::Reactions {
+            self.state = AppState::Main;
+        }
+        self.show_notice("Реакция снята");
+        Ok(())
+    }
+
+    // Точное время выбранного сообщения: дата, секунды и часовой пояс
+    pub fn selected_message_timestamp(&self) -> Option<String> {
+        if !self.focus_on_messages {
+            return None;
+        }
+        let timestamp = &self.messages.get(self.selected_message_index)?.timestamp;
+        match chrono::DateTime::parse_from_rfc3339(timestamp) {
+            Ok(time) => Some(time.format("%d.%m.%Y %H:%M:%S %:z").to_string()),
+            Err(_) => Some(timestamp.clone()),
+        }
+    }
+
+    fn status_placeholder(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "chats" => self.chats.len().to_string(),
+            "unread" => self.chats.iter().map(|c| c.unread.max(0) as i64).sum::<i64>().to_string(),
+            "focus" => if self.focus_on_messages { "Сообщения" } else { "Чаты" }.to_string(),
+            "time" => chrono::Local::now().format("%H:%M").to_string(),
+            "chat" => self.selected_chat.as_ref().map(|c| c.title.clone()).unwrap_or_default(),
+            "presence" => if self.self_online { "в сети" } else { "невидимка" }.to_string(),
+            "latency" => match self.last_latency {
+                Some((request, elapsed)) => format!("{} {} мс", request, elapsed.as_millis()),
+                None => "—".to_string(),
+            },
+            "marked" => {
+                if self.marked_messages.is_empty() {
+                    String::new()
+                } else {
+                    format!(" | Отмечено: {} (Esc: снять)", self.marked_messages.len())
+                }
+            }
+            _ => return None,
+        };
+        Some(value)
     }
 
     pub fn get_status_text(&self) -> String {
@@ -1397,23 +4710,30 @@ impl App {
                 if self.chats.is_empty() {
                     "Нет чатов".to_string()
                 } else {
-                    let focus = if self.focus_on_messages { "Сообщения" } else { "Чаты" };
-                    format!(
-                        "Чатов: {} | Фокус: {} | q: выход, Tab: переключить фокус, ↑↓: навигация, Enter: открыть/проиграть, i: сообщение, r: обновить",
-                        self.chats.len(), focus
-                    )
+                    self.expand_status_format(&self.config.status_format)
                 }
             }
             AppState::MessageInput => "Введите сообщение (Enter: отправить, Esc: отмена)".to_string(),
+            AppState::ScheduleInput => "Enter: запланировать, Esc: назад к сообщению".to_string(),
             AppState::Error => format!("Ошибка: {}", self.error_message),
+            AppState::Confirm => "y/Enter: подтвердить, n/Esc: отмена".to_string(),
+            AppState::ReportReason => "↑↓: выбрать причину, Enter: отправить жалобу, Esc: отмена".to_string(),
+            AppState::RawJson => "↑↓: прокрутка, y: скопировать, Esc/F12: закрыть".to_string(),
+            AppState::SearchInput => "Enter: искать, Esc: отмена".to_string(),
+            AppState::Bookmarks => "↑↓: выбрать, Enter: перейти, d: удалить закладку, Esc: закрыть".to_string(),
+            AppState::CatchUp => "↑↓: выбрать чат, Enter: перейти, Esc: закрыть сводку".to_string(),
+            AppState::Reactions => "Esc/+: закрыть".to_string(),
+            AppState::GlobalSearch => "Enter: искать / открыть найденное, ↑↓: выбрать, Esc: закрыть".to_string(),
+            AppState::Readers => "Esc/v: закрыть".to_string(),
+            AppState::ChatInfo => "Esc/I: закрыть".to_string(),
+            AppState::InviteLink => "y: скопировать ссылку, Esc/L: закрыть".to_string(),
+            AppState::GotoInput => "Enter: перейти к сообщению, Esc: отмена".to_string(),
+            AppState::ChatFilterInput => "Enter: оставить фильтр, Esc: показать все чаты".to_string(),
+            AppState::ExportInput => "Enter: экспортировать медиа чата в папку, Esc: отмена".to_string(),
             AppState::ImagePreview => {
                 if let Some(path) = &self.preview_image_path {
-                    if let Some(video_path) = &self.preview_video_path {
-                        if !video_path.is_empty() {
-                            format!("Превью видео: {} | Enter: воспроизвести в mpv, Esc: назад", path)
-                        } else {
-                            format!("Предпросмотр изображения: {}", path)
-                        }
+                    if self.preview_video().is_some() {
+                        format!("Превью видео: {} | Enter: воспроизвести в mpv, Esc: назад", path)
                     } else {
                         format!("Предпросмотр изображения: {}", path)
                     }
@@ -1432,8 +4752,8 @@ impl App {
     }
 
     pub fn calculate_visible_capacity(&self) -> usize {
-        // Возвращаем разумное значение по умолчанию, реальный расчет будет в UI
-        15 // Предполагаем 15 сообщений по умолчанию
+        // Реальный расчет выполняется в UI при отрисовке, здесь берем последнее значение
+        self.get_actual_visible_capacity()
     }
 
     pub fn set_actual_visible_capacity(&mut self, capacity: usize) {
@@ -1474,7 +4794,7 @@ impl App {
                     // Spawn async task to download the map image
                     let url_clone = full_map_url.clone();
                     let message_id = msg.id;
-                    tokio::spawn(async move {
+                    let handle = tokio::spawn(async move {
                         match download_map_image_async(&url_clone, message_id).await {
                             Ok(local_path) => {
                                 log::info!("Карта успешно скачана в фоновом режиме: {}", local_path);
@@ -1486,6 +4806,7 @@ impl App {
                             }
                         }
                     });
+                    self.downloads.insert(temp_path, handle.abort_handle());
                 } else {
                     // Fallback to basic map path without coordinates
                     if let Some(ref map_path) = msg.location_map_path {
@@ -1499,7 +4820,7 @@ impl App {
                         // Spawn async task to download the map image
                         let url_clone = full_map_url.clone();
                         let message_id = msg.id;
-                        tokio::spawn(async move {
+                        let handle = tokio::spawn(async move {
                             match download_map_image_async(&url_clone, message_id).await {
                                 Ok(local_path) => {
                                     log::info!("Карта успешно скачана в фоновом режиме: {}", local_path);
@@ -1509,6 +4830,7 @@ impl App {
                                 }
                             }
                         });
+                        self.downloads.insert(temp_path, handle.abort_handle());
                     } else {
                         log::error!("Путь к карте не найден и координаты отсутствуют");
                         return Err(anyhow::anyhow!("Путь к карте не найден"));
@@ -1518,47 +4840,139 @@ impl App {
                 self.state = AppState::ImagePreview; // Use ImagePreview to show map if available
 
                 log::info!("Установлен режим просмотра местоположения");
-                return Ok(());
+                Ok(())
             } else {
                 log::error!("Сообщение не является сообщением о местоположении");
-                return Err(anyhow::anyhow!("Сообщение не является сообщением о местоположении"));
+                Err(anyhow::anyhow!("Сообщение не является сообщением о местоположении"))
             }
         } else {
             log::error!("Сообщение не найдено по индексу {}", self.selected_message_index);
-            return Err(anyhow::anyhow!("Сообщение не найдено"));
+            Err(anyhow::anyhow!("Сообщение не найдено"))
         }
     }
+}
 
-    async fn download_map_image(&self, url: &str, message_id: i32) -> Result<String> {
-        log::info!("Скачиваем карту с URL: {}", url);
-
-        // Create a temporary file path for the downloaded image
-        let temp_path = format!("/tmp/vi-tg_location_preview_{}.png", message_id);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // Check if we already have this image downloaded
-        if std::path::Path::new(&temp_path).exists() {
-            log::info!("Карта уже скачана, используем существующий файл: {}", temp_path);
-            return Ok(temp_path);
+    fn message(id: i32, text: &str) -> Message {
+        Message {
+            id,
+            text: text.to_string(),
+            r#type: "text".to_string(),
+            ..Default::default()
         }
+    }
 
-        // Create HTTP client and download the image
-        let client = reqwest::Client::new();
-        let response = client.get(url).send().await
-            .map_err(|e| anyhow::anyhow!("Ошибка HTTP запроса: {}", e))?;
+    fn test_app(messages: Vec<Message>) -> App {
+        let mut app = App::new(ApiClient::new("http://127.0.0.1:9".to_string()), Config::default());
+        app.messages = messages;
+        app
+    }
 
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!("HTTP ошибка: {} для URL: {}", response.status(), url));
-        }
+    #[test]
+    fn dedup_keeps_last_occurrence_in_place() {
+        let mut messages = vec![
+            message(1, "старая версия"),
+            message(2, "второе"),
+            message(1, "правка"),
+            message(3, "третье"),
+            message(3, "третье еще раз"),
+        ];
+        dedup_messages_by_id(&mut messages);
+
+        let ids: Vec<i32> = messages.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![2, 1, 3]);
+        assert_eq!(messages[1].text, "правка");
+        assert_eq!(messages[2].text, "третье еще раз");
+    }
+
+    #[test]
+    fn dedup_without_duplicates_is_noop() {
+        let mut messages = vec![message(1, "a"), message(2, "b"), message(3, "c")];
+        dedup_messages_by_id(&mut messages);
 
-        // Read the image data
-        let image_data = response.bytes().await
-            .map_err(|e| anyhow::anyhow!("Ошибка чтения данных изображения: {}", e))?;
+        let ids: Vec<i32> = messages.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn select_by_id_lands_on_surviving_row() {
+        let mut messages = vec![
+            message(10, "старая версия"),
+            message(11, "между"),
+            message(10, "правка"),
+            message(12, "последнее"),
+        ];
+        dedup_messages_by_id(&mut messages);
+        let mut app = test_app(messages);
+
+        assert!(app.select_message_by_id(10));
+        assert_eq!(app.selected_message_index, 1);
+        assert_eq!(app.messages[app.selected_message_index].text, "правка");
+        assert!(!app.follow_tail);
+
+        assert!(app.select_message_by_id(12));
+        assert_eq!(app.selected_message_index, 2);
+        assert!(app.follow_tail);
+    }
+
+    #[test]
+    fn send_result_drops_local_copy_when_server_copy_arrived() {
+        let mut local = message(-1, "привет");
+        local.send_state = Some(SendState::Sending);
+        // Long-poll успел добавить серверную копию раньше ответа на отправку
+        let mut app = test_app(vec![message(1, "a"), local, message(100, "привет")]);
+        app.selected_message_index = 2;
+        let response = SendMessageResponse { success: true, message: String::new(), message_id: Some(100) };
+        app.send_results_tx.send((-1, None, Ok(response), None)).unwrap();
+
+        app.process_send_results();
+
+        let ids: Vec<i32> = app.messages.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![1, 100]);
+        assert_eq!(app.selected_message_index, 1);
+    }
+
+    fn schedule_now() -> chrono::DateTime<chrono::Local> {
+        use chrono::TimeZone;
+        chrono::Local.with_ymd_and_hms(2026, 3, 10, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn schedule_relative_cyrillic_and_latin_units() {
+        let now = schedule_now();
+        assert_eq!(parse_schedule_time("+30м", now), Ok(now + chrono::TimeDelta::minutes(30)));
+        assert_eq!(parse_schedule_time("+2h", now), Ok(now + chrono::TimeDelta::hours(2)));
+        assert_eq!(parse_schedule_time(" +1д ", now), Ok(now + chrono::TimeDelta::days(1)));
+    }
+
+    #[test]
+    fn schedule_rejects_past_time() {
+        let now = schedule_now();
+        assert!(parse_schedule_time("11:59", now).is_err());
+        assert!(parse_schedule_time("12:00", now).is_err());
+        assert!(parse_schedule_time("12:01", now).is_ok());
+        assert!(parse_schedule_time("+0m", now).is_err());
+    }
+
+    #[test]
+    fn schedule_rejects_out_of_range_and_malformed() {
+        let now = schedule_now();
+        assert!(parse_schedule_time("+99999999999d", now).is_err());
+        assert!(parse_schedule_time("+9223372036854775807м", now).is_err());
+        assert!(parse_schedule_time("+", now).is_err());
+        assert!(parse_schedule_time("+м", now).is_err());
+        assert!(parse_schedule_time("+30x", now).is_err());
+    }
 
-        // Write to temporary file
-        tokio::fs::write(&temp_path, &image_data).await
-            .map_err(|e| anyhow::anyhow!("Ошибка сохранения файла: {}", e))?;
+    #[test]
+    fn select_by_missing_id_keeps_selection() {
+        let mut app = test_app(vec![message(1, "a"), message(2, "b")]);
+        app.selected_message_index = 1;
 
-        log::info!("Карта успешно скачана и сохранена в: {}", temp_path);
-        Ok(temp_path)
+        assert!(!app.select_message_by_id(5));
+        assert_eq!(app.selected_message_index, 1);
     }
 }
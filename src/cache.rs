@@ -0,0 +1,68 @@
+use std::path::{Path, PathBuf};
+
+use crate::{Chat, Message};
+
+// Локальный кэш чатов и сообщений для просмотра без подключения к бэкенду
+pub fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("vi-tg")
+}
+
+fn chats_path() -> PathBuf {
+    cache_dir().join("chats.json")
+}
+
+fn messages_path(chat_id: i64) -> PathBuf {
+    cache_dir().join("messages").join(format!("{}.json", chat_id))
+}
+
+fn write_json<T: serde::Serialize + ?Sized>(path: &Path, value: &T) {
+    if let Err(e) = try_write_json(path, value) {
+        log::warn!("Не удалось записать кэш {}: {}", path.display(), e);
+    }
+}
+
+fn try_write_json<T: serde::Serialize + ?Sized>(path: &Path, value: &T) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(value)?)?;
+    Ok(())
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    let data = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&data) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            log::warn!("Поврежденный кэш {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+pub fn save_chats(chats: &[Chat]) {
+    write_json(&chats_path(), chats);
+}
+
+pub fn load_chats() -> Option<Vec<Chat>> {
+    read_json(&chats_path())
+}
+
+pub fn save_messages(chat_id: i64, messages: &[Message]) {
+    write_json(&messages_path(chat_id), messages);
+}
+
+pub fn load_messages(chat_id: i64) -> Option<Vec<Message>> {
+    read_json(&messages_path(chat_id))
+}
+
+pub fn remove_messages(chat_id: i64) {
+    let path = messages_path(chat_id);
+    if let Err(e) = std::fs::remove_file(&path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log::warn!("Не удалось удалить кэш {}: {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// Копирование в системный буфер обмена через внешние утилиты.
+// Если ни одной нет, используем escape-последовательность OSC 52, которую понимают многие терминалы.
+pub fn copy(text: &str) -> Result<()> {
+    let candidates: [(&str, &[&str]); 4] = [
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+        ("pbcopy", &[]),
+    ];
+
+    for (program, args) in candidates {
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            continue;
+        };
+        if let Some(stdin) = child.stdin.as_mut() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        drop(child.stdin.take());
+        if child.wait()?.success() {
+            return Ok(());
+        }
+    }
+
+    copy_osc52(text)
+}
+
+fn copy_osc52(text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", BASE64.encode(text))?;
+    stdout.flush()?;
+    Ok(())
+}
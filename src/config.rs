@@ -0,0 +1,270 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// Настройки TUI клиента. Хранятся отдельно от конфига бэкенда (~/.vi-tg/config.json),
+// чтобы бэкенд при сохранении своего конфига не затирал настройки интерфейса.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // Рисовать кастомные (premium) эмодзи картинками вместо обычных эмодзи-заменителей
+    pub custom_emoji_images: bool,
+    // Показывать время "HH:MM" перед именем отправителя
+    pub show_timestamps: bool,
+    // Какой клавишей отправлять сообщение: "enter" или "ctrl_enter"
+    pub send_key: SendKey,
+    // Закрепленные чаты показываются в начале списка
+    pub pinned_chats: Vec<i64>,
+    // Локальные закладки на сообщения: (id чата, id сообщения)
+    pub bookmarks: Vec<(i64, i32)>,
+    // Обновлять данные при возврате фокуса в окно терминала
+    pub refresh_on_focus: bool,
+    // После успешной отправки выделять отправленное сообщение и следить за концом ленты
+    pub select_after_send: bool,
+    // Сколько строк показывать у невыбранного длинного сообщения
+    pub max_collapsed_lines: usize,
+    // Делить сообщения длиннее 4096 символов на несколько при отправке
+    pub split_long_messages: bool,
+    // Шаблон строки статуса: {chats}, {unread}, {focus}, {time}, {chat}, {presence}, {marked}, {latency}
+    pub status_format: String,
+    // Как подписывать отправителя: имя, @username или оба
+    pub sender_name: SenderName,
+    // Отмечать сообщения прочитанными на сервере по мере листания
+    pub mark_read_on_scroll: bool,
+    // Отмечать чат прочитанным при открытии; false - непрочитанные остаются до M
+    pub mark_read_on_open: bool,
+    // Период опроса событий главного цикла, мс
+    pub tick_rate_ms: u64,
+    // Чаще опрашивать при воспроизведении и предпросмотре, реже - когда окно не в фокусе
+    pub adaptive_tick_rate: bool,
+    // Выравнивание фото, видео и стикеров в ленте
+    pub media_align: MediaAlign,
+    // Получать новые сообщения через long-poll /api/updates вместо ожидания ручного обновления
+    pub long_poll: bool,
+    // Что делать по Enter на сообщении неподдерживаемого типа; пробуется по порядку
+    pub unsupported_message_actions: Vec<UnsupportedAction>,
+    // Переходить к новому сообщению открытого чата, если выделение было внизу ленты
+    pub auto_scroll_on_new: bool,
+    // ASCII вместо эмодзи и псевдографики; null - определить по терминалу и локали
+    pub ascii_only: Option<bool>,
+    // Пустых строк между сообщениями в ленте: 0 или 1
+    pub message_spacing: u16,
+    // Открывать предпросмотр фото, видео и стикера, когда выделение задержалось на нем
+    pub hover_preview: bool,
+    // Сколько встроенных картинок (кастомных эмодзи) рисовать за кадр, не считая выбранного сообщения
+    pub max_inline_images: usize,
+    // На какой язык переводить сообщения по t (код ISO 639-1)
+    pub translate_to: String,
+    // Уведомлять о новых сообщениях других чатов (и открытого, когда окно не в фокусе)
+    pub notifications: bool,
+    // Программа уведомлений и ее аргументы; к ним добавляются название чата и текст. Пусто - notify-send
+    pub notification_command: Vec<String>,
+    // Строк текста в поле ввода: 0 - растет вместе с черновиком (до 8), иначе фиксированная высота с прокруткой
+    pub compose_height: u16,
+    // Направление текста сообщений: по первому сильному символу или для всех одинаковое
+    pub text_direction: TextDirection,
+    // Что делать с поврежденными картинками и стикерами: оставить, удалить или скачать заново
+    pub on_corrupt_media: CorruptMediaPolicy,
+    // Сколько чатов запрашивать у бэкенда; null - сколько отдает бэкенд по умолчанию (50)
+    pub chat_fetch_limit: Option<i32>,
+    // Эмодзи быстрых реакций на 1-5 в ленте сообщений
+    pub quick_reactions: Vec<String>,
+    // Скачивать ли медиа заранее, по типам: photo, video, voice, audio, sticker, document.
+    // Тип, которого нет в списке, скачивается; открытие вручную скачивает всегда
+    pub auto_download: BTreeMap<String, bool>,
+    // Шаблон строки списка чатов: {unread}, {title}, {preview}, {time}, {mute}
+    pub chat_list_format: String,
+    // Эмодзи, которые отправляются сообщением по e и клавише: {"y": "👍"} - e y
+    pub quick_emoji: BTreeMap<String, String>,
+}
+
+const STATUS_PLACEHOLDERS: &[&str] = &["chats", "unread", "focus", "time", "chat", "presence", "marked", "latency"];
+
+const CHAT_LIST_PLACEHOLDERS: &[&str] = &["unread", "title", "preview", "time", "mute"];
+
+const DEFAULT_STATUS_FORMAT: &str = "Чатов: {chats} | Фокус: {focus} | Статус: {presence}{marked} | q: выход, Tab: переключить фокус, ↑↓: навигация, Enter: открыть/проиграть, Space: отметить, i: сообщение, r: обновить";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SendKey {
+    // Enter отправляет сообщение
+    Enter,
+    // Ctrl+Enter отправляет, Enter переносит строку
+    CtrlEnter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SenderName {
+    // Имя и фамилия
+    DisplayName,
+    // @username, если он есть, иначе имя
+    Username,
+    // Имя и @username в скобках
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaAlign {
+    // С отступом в две колонки от левого края
+    Left,
+    // По центру области сообщений
+    Center,
+    // Под именем отправителя (после времени)
+    Sender,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsupportedAction {
+    // Сразу открыть найденный файл (*_path) во внешней программе
+    OpenPath,
+    // Спросить, открыть ли найденный файл во внешней программе
+    Prompt,
+    // Скопировать JSON сообщения в буфер обмена
+    CopyJson,
+    // Показать JSON сообщения
+    RawJson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextDirection {
+    // Сообщения на иврите, арабском и т.п. выравниваются по правому краю
+    Auto,
+    // Все сообщения по левому краю
+    Ltr,
+    // Все сообщения по правому краю
+    Rtl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorruptMediaPolicy {
+    // Оставить файл как есть; перезагрузить можно вручную (F)
+    Keep,
+    // Удалить файл (при запуске - все битые файлы в /tmp)
+    Delete,
+    // Удалить и скачать с бэкенда заново, когда файл попадется на экране
+    Redownload,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            custom_emoji_images: false,
+            show_timestamps: true,
+            send_key: SendKey::Enter,
+            pinned_chats: Vec::new(),
+            bookmarks: Vec::new(),
+            refresh_on_focus: true,
+            select_after_send: true,
+            max_collapsed_lines: 3,
+            split_long_messages: false,
+            status_format: DEFAULT_STATUS_FORMAT.to_string(),
+            sender_name: SenderName::DisplayName,
+            mark_read_on_scroll: true,
+            mark_read_on_open: true,
+            tick_rate_ms: 100,
+            adaptive_tick_rate: true,
+            media_align: MediaAlign::Left,
+            long_poll: true,
+            unsupported_message_actions: vec![UnsupportedAction::Prompt, UnsupportedAction::RawJson],
+            auto_scroll_on_new: true,
+            ascii_only: None,
+            message_spacing: 0,
+            hover_preview: false,
+            max_inline_images: 32,
+            translate_to: "ru".to_string(),
+            notifications: false,
+            notification_command: Vec::new(),
+            compose_height: 0,
+            text_direction: TextDirection::Auto,
+            on_corrupt_media: CorruptMediaPolicy::Keep,
+            chat_fetch_limit: None,
+            quick_reactions: ["👍", "❤", "🔥", "😁", "😢"].iter().map(|e| e.to_string()).collect(),
+            auto_download: BTreeMap::new(),
+            chat_list_format: "{unread}{title}".to_string(),
+            quick_emoji: [("y", "👍"), ("h", "❤"), ("f", "🔥"), ("l", "😂"), ("o", "👌")]
+                .iter()
+                .map(|(key, emoji)| (key.to_string(), emoji.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl Config {
+    // Загружаем конфиг; при отсутствии или ошибке разбора используем значения по умолчанию
+    pub fn load() -> Self {
+        Self::try_load().unwrap_or_else(|e| {
+            log::error!("{}", e);
+            Self::default()
+        })
+    }
+
+    // Как load, но ошибку разбора возвращаем: при перечитывании (Ctrl+Alt+R) старые настройки лучше сохранить
+    pub fn try_load() -> anyhow::Result<Self> {
+        let path = config_path();
+        match std::fs::read_to_string(&path) {
+            Ok(data) => {
+                let config = serde_json::from_str::<Self>(&data)
+                    .map_err(|e| anyhow::anyhow!("Ошибка разбора конфига {}: {}", path.display(), e))?;
+                config.warn_unknown_placeholders();
+                Ok(config)
+            }
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    // Неизвестные плейсхолдеры не ломают строку статуса, но о них стоит сообщить
+    fn warn_unknown_placeholders(&self) {
+        let templates = [
+            ("status_format", &self.status_format, STATUS_PLACEHOLDERS),
+            ("chat_list_format", &self.chat_list_format, CHAT_LIST_PLACEHOLDERS),
+        ];
+        for (field, template, known) in templates {
+            for part in template.split('{').skip(1) {
+                if let Some(name) = part.split('}').next() {
+                    if part.contains('}') && !known.contains(&name) {
+                        log::warn!("Неизвестный плейсхолдер в {}: {{{}}}", field, name);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn auto_download_allowed(&self, media_type: &str) -> bool {
+        self.auto_download.get(media_type).copied().unwrap_or(true)
+    }
+
+    // Типы медиа, которые бэкенду не нужно скачивать заранее
+    pub fn skipped_downloads(&self) -> Vec<String> {
+        self.auto_download
+            .iter()
+            .filter(|(_, &enabled)| !enabled)
+            .map(|(media_type, _)| media_type.clone())
+            .collect()
+    }
+
+    pub fn quick_emoji_for(&self, key: char) -> Option<&str> {
+        self.quick_emoji.get(&key.to_string()).map(String::as_str)
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        std::fs::create_dir_all(config_dir())?;
+        let data = serde_json::to_string_pretty(self)?;
+        std::fs::write(config_path(), data)?;
+        Ok(())
+    }
+}
+
+pub fn config_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".vi-tg")
+}
+
+pub fn config_path() -> PathBuf {
+    config_dir().join("tui.json")
+}
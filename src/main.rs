@@ -3,10 +3,17 @@ use serde::{Deserialize, Serialize};
 
 mod api;
 mod app;
+mod cache;
+mod clipboard;
+mod config;
+mod session;
+mod symbols;
 mod ui;
+mod util;
 
 use api::ApiClient;
 use app::{App, AppState};
+use config::{Config, CorruptMediaPolicy, SendKey};
 use ui as ui_module;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,13 +23,25 @@ pub struct Chat {
     pub r#type: String,
     pub unread: i32,
     pub last_message: Option<String>,
+    // Публичное имя (@username) для открытых чатов
+    pub username: Option<String>,
+    // Непрочитанные упоминания, если бэкенд их считает
+    pub unread_mentions: Option<i32>,
+    // Время последнего сообщения (RFC 3339)
+    pub last_message_time: Option<String>,
+    // Уведомления чата отключены
+    pub muted: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Message {
     pub id: i32,
     pub text: String,
     pub from: String,
+    // Id пользователя-отправителя (нужен для блокировки)
+    pub from_id: Option<i64>,
+    // @username отправителя, если он есть
+    pub from_username: Option<String>,
     pub timestamp: String,
     pub chat_id: i64,
     pub r#type: String,
@@ -38,6 +57,8 @@ pub struct Message {
     pub voice_id: Option<i64>,
     pub voice_path: Option<String>,
     pub voice_duration: Option<i32>,
+    // Форма волны голосового: по значению на отсчет (распакованные 5-битные значения Telegram)
+    pub voice_waveform: Option<Vec<u8>>,
     pub audio_id: Option<i64>,
     pub audio_path: Option<String>,
     pub audio_duration: Option<i32>,
@@ -50,6 +71,70 @@ pub struct Message {
     pub location_title: Option<String>,
     pub location_address: Option<String>,
     pub location_map_path: Option<String>,
+    // Подпись к фото/видео (в альбоме приходит у первого элемента)
+    pub caption: Option<String>,
+    // Превью ссылки (заголовок, описание и миниатюра страницы)
+    pub webpage_title: Option<String>,
+    pub webpage_description: Option<String>,
+    pub webpage_image_path: Option<String>,
+    // Id сообщения, на которое это сообщение отвечает
+    pub reply_to_id: Option<i32>,
+    // Цепочка пересылки: от того, кто переслал нам, до автора оригинала
+    pub forward_origin: Option<Vec<ForwardOrigin>>,
+    // Кастомные (premium) эмодзи внутри текста
+    pub custom_emoji: Option<Vec<CustomEmoji>>,
+    // Скрытые спойлером участки текста
+    pub spoilers: Option<Vec<TextRange>>,
+    // Фото/видео скрыто спойлером
+    pub media_spoiler: Option<bool>,
+    // Реакции на сообщение
+    pub reactions: Option<Vec<Reaction>>,
+    // Время отложенной отправки (RFC 3339), пока сообщение еще не отправлено
+    pub scheduled_at: Option<String>,
+    // Локальное состояние отправки (только для оптимистично добавленных сообщений)
+    #[serde(skip)]
+    pub send_state: Option<SendState>,
+    // Поля, которые клиент пока не знает (нужны для неподдерживаемых типов сообщений)
+    #[serde(flatten)]
+    pub extra: std::collections::HashMap<String, serde_json::Value>,
+}
+
+// Кастомный эмодзи: позиция в тексте задается в UTF-16 единицах, как в Telegram.
+// В самом тексте на этом месте стоит обычный эмодзи-заменитель.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomEmoji {
+    pub offset: usize,
+    pub length: usize,
+    pub path: Option<String>,
+}
+
+// Звено пересылки; имя может быть скрыто настройками приватности отправителя
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardOrigin {
+    pub name: Option<String>,
+    pub link: Option<String>,
+}
+
+// Реакция: эмодзи, сколько раз поставлена и есть ли среди них наша
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reaction {
+    pub emoji: String,
+    pub count: i32,
+    #[serde(default)]
+    pub chosen: bool,
+}
+
+// Участок текста в UTF-16 единицах, как в сущностях Telegram
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextRange {
+    pub offset: usize,
+    pub length: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SendState {
+    Sending,
+    Sent,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,37 +142,44 @@ pub struct AuthStatus {
     pub authorized: bool,
     pub phone_number: Option<String>,
     pub needs_code: bool,
+    // Имя вошедшего аккаунта, если бэкенд его сообщает
+    pub name: Option<String>,
+    // Текущее время бэкенда (RFC 3339), чтобы заметить расхождение часов
+    pub server_time: Option<String>,
 }
 
 
-fn cleanup_corrupted_images() {
+// При on_corrupt_media = "delete" битые картинки удаляем сразу при запуске;
+// при "redownload" их найдет и скачает заново отрисовка
+fn cleanup_corrupted_images(policy: CorruptMediaPolicy) {
+    if policy != CorruptMediaPolicy::Delete {
+        return;
+    }
     let tmp_dir = "/tmp";
     if let Ok(entries) = std::fs::read_dir(tmp_dir) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if let Some(file_name) = path.file_name() {
-                    if let Some(name_str) = file_name.to_str() {
-                        if name_str.starts_with("vi-tg_image_") || name_str.starts_with("vi-tg_sticker_") {
-                            let path_str = path.to_str().unwrap();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Some(file_name) = path.file_name() {
+                if let Some(name_str) = file_name.to_str() {
+                    if name_str.starts_with("vi-tg_image_") || name_str.starts_with("vi-tg_sticker_") {
+                        let path_str = path.to_str().unwrap();
 
-                            // Проверяем файлы с различными расширениями изображений
-                            if name_str.ends_with(".png") || name_str.ends_with(".jpg") ||
-                               name_str.ends_with(".jpeg") || name_str.ends_with(".webp") ||
-                               name_str.ends_with(".gif") {
-                                // Простая проверка размера файла
-                                if let Ok(metadata) = std::fs::metadata(path_str) {
-                                    if metadata.len() < 100 {
-                                        let _ = std::fs::remove_file(&path);
-                                        continue;
-                                    }
-                                }
-
-                                // Проверяем, что файл действительно является изображением
-                                if !is_valid_image_file(path_str) {
+                        // Проверяем файлы с различными расширениями изображений
+                        if name_str.ends_with(".png") || name_str.ends_with(".jpg") ||
+                           name_str.ends_with(".jpeg") || name_str.ends_with(".webp") ||
+                           name_str.ends_with(".gif") {
+                            // Простая проверка размера файла
+                            if let Ok(metadata) = std::fs::metadata(path_str) {
+                                if metadata.len() < 100 {
                                     let _ = std::fs::remove_file(&path);
+                                    continue;
                                 }
                             }
+
+                            // Проверяем, что файл действительно является изображением
+                            if !is_valid_image_file(path_str) {
+                                let _ = std::fs::remove_file(&path);
+                            }
                         }
                     }
                 }
@@ -100,12 +192,14 @@ fn cleanup_corrupted_images() {
 async fn main() -> Result<()> {
     env_logger::init();
     
+    let config = Config::load();
+
     // Очищаем старые поврежденные файлы
-    cleanup_corrupted_images();
+    cleanup_corrupted_images(config.on_corrupt_media);
     
     let api_client = ApiClient::new("http://localhost:8080".to_string());
-    
-    let app = App::new(api_client);
+
+    let app = App::new(api_client, config);
 
     run_tui(app).await?;
     
@@ -116,66 +210,497 @@ async fn run_tui(mut app: App) -> Result<()> {
     // Настройка терминала
     crossterm::terminal::enable_raw_mode()?;
     let mut stdout = std::io::stdout();
-    crossterm::execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
+    crossterm::execute!(
+        stdout,
+        crossterm::terminal::EnterAlternateScreen,
+        crossterm::event::EnableFocusChange
+    )?;
+    // Если терминал поддерживает расширенный протокол клавиатуры, включаем его,
+    // чтобы различать сочетания вроде Ctrl+I и Tab
+    let keyboard_enhancement = crossterm::terminal::supports_keyboard_enhancement().unwrap_or(false);
+    if keyboard_enhancement {
+        crossterm::execute!(
+            stdout,
+            crossterm::event::PushKeyboardEnhancementFlags(
+                crossterm::event::KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+            )
+        )?;
+    }
     let backend = ratatui::backend::CrosstermBackend::new(stdout);
     let mut terminal = ratatui::Terminal::new(backend)?;
 
+    let mut last_draw = std::time::Instant::now();
     loop {
-        terminal.draw(|frame| ui_module::draw_ui(frame, &mut app))?;
+        // Перерисовываем только при изменениях, но не реже раза в секунду,
+        // чтобы подхватить докачанные в фоне файлы
+        if app.needs_redraw || last_draw.elapsed() >= std::time::Duration::from_secs(1) {
+            terminal.draw(|frame| ui_module::draw_ui(frame, &mut app))?;
+            app.needs_redraw = false;
+            last_draw = std::time::Instant::now();
+        }
 
         // Обработка событий
-        if crossterm::event::poll(std::time::Duration::from_millis(100))? {
-            if let crossterm::event::Event::Key(key) = crossterm::event::read()? {
+        if crossterm::event::poll(app.tick_rate())? {
+            let event = crossterm::event::read()?;
+            app.needs_redraw = true;
+            match event {
+                crossterm::event::Event::FocusGained => app.on_focus_gained().await,
+                crossterm::event::Event::FocusLost => app.on_focus_lost(),
+                _ => {}
+            }
+            if let crossterm::event::Event::Key(key) = event {
+                app.notice = None;
+                // Первая клавиша после простоя только показывает сводку новых сообщений
+                if app.wake_from_idle().await {
+                    continue;
+                }
+                if app.cancel_hover_preview(key.code) {
+                    continue;
+                }
+                // Повторитель действует только на следующую клавишу
+                let count = app.count_prefix.take();
+                let pending_key = app.pending_key.take();
                 match key.code {
-                    crossterm::event::KeyCode::Char('q') => break,
-                    crossterm::event::KeyCode::Tab => {
-                        app.toggle_focus();
+                    crossterm::event::KeyCode::Char('t') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                        app.toggle_timestamps();
                     }
-                    crossterm::event::KeyCode::Up => {
-                        if app.focus_on_messages {
-                            app.move_message_selection(-1, app.calculate_visible_capacity());
-                        } else {
-                            app.move_chat_selection(-1);
+                    // Ctrl+^ большинство терминалов передает как Ctrl+6
+                    crossterm::event::KeyCode::Char('^') | crossterm::event::KeyCode::Char('6') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) && app.state == AppState::Main => {
+                        if let Err(e) = app.switch_to_previous_chat().await {
+                            app.show_error(&format!("Ошибка выбора чата: {}", e));
                         }
                     }
-                    crossterm::event::KeyCode::Down => {
-                        if app.focus_on_messages {
-                            app.move_message_selection(1, app.calculate_visible_capacity());
-                        } else {
-                            app.move_chat_selection(1);
+                    crossterm::event::KeyCode::Char('b') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) && app.state == AppState::Main => {
+                        app.toggle_sidebar();
+                    }
+                    crossterm::event::KeyCode::Char('a') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) && matches!(app.state, AppState::Main | AppState::ImagePreview | AppState::VideoPreview) => {
+                        app.toggle_half_block_images();
+                    }
+                    crossterm::event::KeyCode::Char('d') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) && app.state == AppState::Main && app.focus_on_messages => {
+                        app.move_message_page(1, 2, count);
+                    }
+                    crossterm::event::KeyCode::Char('u') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) && app.state == AppState::Main && app.focus_on_messages => {
+                        app.move_message_page(-1, 2, count);
+                    }
+                    crossterm::event::KeyCode::PageDown if app.state == AppState::Main && app.focus_on_messages => {
+                        app.move_message_page(1, 1, count);
+                    }
+                    crossterm::event::KeyCode::PageUp if app.state == AppState::Main && app.focus_on_messages => {
+                        app.move_message_page(-1, 1, count);
+                    }
+                    crossterm::event::KeyCode::Char('o') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) && app.state == AppState::Main => {
+                        app.jump_back();
+                    }
+                    // Ctrl+I отличим от Tab только при включенном расширенном режиме клавиатуры
+                    crossterm::event::KeyCode::Char('i') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) && app.state == AppState::Main => {
+                        app.jump_forward();
+                    }
+                    // Многие терминалы без расширенного протокола передают Ctrl+Enter как Ctrl+J
+                    crossterm::event::KeyCode::Char('j') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) && app.state == AppState::MessageInput && app.config.send_key == SendKey::CtrlEnter => {
+                        submit_message(&mut app).await;
+                    }
+                    crossterm::event::KeyCode::Char('s') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) && matches!(app.state, AppState::Main | AppState::MessageInput) => {
+                        if let Err(e) = app.send_to_saved_messages().await {
+                            app.show_error(&format!("Ошибка сохранения в «Избранное»: {}", e));
                         }
                     }
-                    crossterm::event::KeyCode::Char('h') => {
-                        if app.focus_on_messages && app.audio_player.is_playing {
-                            if !app.audio_player.seek(-2) { // Rewind 2 seconds
-                                // If IPC communication failed, restart player at new position
-                                app.restart_player_at_position();
-                            }
+                    crossterm::event::KeyCode::Char('l') if key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) && app.state == AppState::MessageInput => {
+                        app.start_schedule_input();
+                    }
+                    crossterm::event::KeyCode::PageUp if app.state == AppState::MessageInput => {
+                        app.scroll_while_typing(-1);
+                    }
+                    crossterm::event::KeyCode::PageDown if app.state == AppState::MessageInput => {
+                        app.scroll_while_typing(1);
+                    }
+                    crossterm::event::KeyCode::Char('y') | crossterm::event::KeyCode::Enter if app.state == AppState::Confirm => {
+                        if let Err(e) = app.confirm_pending_action().await {
+                            app.show_error(&format!("Ошибка выполнения действия: {}", e));
                         }
                     }
-                    crossterm::event::KeyCode::Char('k') => {
-                        if app.focus_on_messages && app.audio_player.is_playing {
-                            if !app.audio_player.seek(2) { // Fast forward 2 seconds
-                                // If IPC communication failed, restart player at new position
-                                app.restart_player_at_position();
-                            }
+                    crossterm::event::KeyCode::Char('n') | crossterm::event::KeyCode::Esc if app.state == AppState::Confirm => {
+                        app.cancel_pending_action();
+                    }
+                    crossterm::event::KeyCode::Up if app.state == AppState::ReportReason => {
+                        app.move_report_reason(-1);
+                    }
+                    crossterm::event::KeyCode::Down if app.state == AppState::ReportReason => {
+                        app.move_report_reason(1);
+                    }
+                    crossterm::event::KeyCode::Enter if app.state == AppState::ReportReason => {
+                        if let Err(e) = app.submit_report().await {
+                            app.show_error(&format!("Ошибка отправки жалобы: {}", e));
                         }
                     }
-                    crossterm::event::KeyCode::Char(' ') => {
-                        if app.focus_on_messages && app.audio_player.is_playing {
-                            app.audio_player.stop(); // Stop playback
+                    crossterm::event::KeyCode::Esc if app.state == AppState::ReportReason => {
+                        app.cancel_report();
+                    }
+                    crossterm::event::KeyCode::F(2) if app.state == AppState::Main => {
+                        app.open_data_dir(false);
+                    }
+                    crossterm::event::KeyCode::F(3) if app.state == AppState::Main => {
+                        app.open_data_dir(true);
+                    }
+                    crossterm::event::KeyCode::F(12) if app.state == AppState::Main && app.focus_on_messages => {
+                        app.open_raw_json();
+                    }
+                    crossterm::event::KeyCode::Up if app.state == AppState::RawJson => {
+                        app.scroll_raw_json(-1);
+                    }
+                    crossterm::event::KeyCode::Down if app.state == AppState::RawJson => {
+                        app.scroll_raw_json(1);
+                    }
+                    crossterm::event::KeyCode::Char('y') if app.state == AppState::RawJson => {
+                        app.copy_raw_json();
+                    }
+                    crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::F(12) if app.state == AppState::RawJson => {
+                        app.close_raw_json();
+                    }
+                    crossterm::event::KeyCode::Char(c) if app.state == AppState::GlobalSearch => {
+                        app.global_search_input.push(c);
+                    }
+                    crossterm::event::KeyCode::Backspace if app.state == AppState::GlobalSearch => {
+                        app.global_search_input.pop();
+                    }
+                    crossterm::event::KeyCode::Up if app.state == AppState::GlobalSearch => {
+                        app.move_global_search_selection(-1);
+                    }
+                    crossterm::event::KeyCode::Down if app.state == AppState::GlobalSearch => {
+                        app.move_global_search_selection(1);
+                    }
+                    crossterm::event::KeyCode::Enter if app.state == AppState::GlobalSearch => {
+                        if let Err(e) = app.submit_global_search().await {
+                            app.show_error(&format!("Ошибка поиска: {}", e));
                         }
                     }
+                    crossterm::event::KeyCode::Esc if app.state == AppState::GlobalSearch => {
+                        app.close_global_search();
+                    }
+                    crossterm::event::KeyCode::Char('S') if app.state == AppState::Main => {
+                        app.open_global_search();
+                    }
+                    crossterm::event::KeyCode::Char(c) if app.state == AppState::GotoInput => {
+                        app.goto_input.push(c);
+                    }
+                    crossterm::event::KeyCode::Backspace if app.state == AppState::GotoInput => {
+                        app.goto_input.pop();
+                    }
+                    crossterm::event::KeyCode::Enter if app.state == AppState::GotoInput => {
+                        if let Err(e) = app.goto_message().await {
+                            app.show_error(&format!("Ошибка загрузки истории: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Esc if app.state == AppState::GotoInput => {
+                        app.cancel_goto_input();
+                    }
+                    crossterm::event::KeyCode::Char(':') if app.state == AppState::Main => {
+                        app.start_goto_input();
+                    }
+                    crossterm::event::KeyCode::Char(c) if app.state == AppState::ExportInput => {
+                        app.export_input.push(c);
+                    }
+                    crossterm::event::KeyCode::Backspace if app.state == AppState::ExportInput => {
+                        app.export_input.pop();
+                    }
+                    crossterm::event::KeyCode::Enter if app.state == AppState::ExportInput => {
+                        if let Err(e) = app.start_media_export() {
+                            app.show_error(&format!("Ошибка экспорта медиа: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Esc if app.state == AppState::ExportInput => {
+                        app.cancel_export_input();
+                    }
+                    crossterm::event::KeyCode::Char('E') if app.state == AppState::Main => {
+                        app.start_export_input();
+                    }
+                    crossterm::event::KeyCode::Char(c) if app.state == AppState::ScheduleInput => {
+                        app.schedule_input.push(c);
+                    }
+                    crossterm::event::KeyCode::Backspace if app.state == AppState::ScheduleInput => {
+                        app.schedule_input.pop();
+                    }
+                    crossterm::event::KeyCode::Enter if app.state == AppState::ScheduleInput => {
+                        if let Err(e) = app.schedule_message().await {
+                            app.show_error(&format!("Ошибка планирования сообщения: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Esc if app.state == AppState::ScheduleInput => {
+                        app.cancel_schedule_input();
+                    }
+                    crossterm::event::KeyCode::Char(c) if app.state == AppState::SearchInput => {
+                        app.push_search_char(c);
+                    }
+                    crossterm::event::KeyCode::Backspace if app.state == AppState::SearchInput => {
+                        app.pop_search_char();
+                    }
+                    crossterm::event::KeyCode::Enter if app.state == AppState::SearchInput => {
+                        app.submit_search();
+                    }
+                    crossterm::event::KeyCode::Esc if app.state == AppState::SearchInput => {
+                        app.cancel_search_input();
+                    }
+                    crossterm::event::KeyCode::Char(c) if app.state == AppState::ChatFilterInput => {
+                        app.push_chat_filter_char(c);
+                    }
+                    crossterm::event::KeyCode::Backspace if app.state == AppState::ChatFilterInput => {
+                        app.pop_chat_filter_char();
+                    }
+                    crossterm::event::KeyCode::Enter if app.state == AppState::ChatFilterInput => {
+                        app.submit_chat_filter();
+                    }
+                    crossterm::event::KeyCode::Esc if app.state == AppState::ChatFilterInput => {
+                        app.clear_chat_filter();
+                    }
+                    // Стрелки выбирают среди отфильтрованных, не прерывая ввод
+                    crossterm::event::KeyCode::Up if app.state == AppState::ChatFilterInput => {
+                        app.move_chat_selection(-1);
+                    }
+                    crossterm::event::KeyCode::Down if app.state == AppState::ChatFilterInput => {
+                        app.move_chat_selection(1);
+                    }
+                    // Набор текста раньше клавиш Main: иначе q, r, j/k и управление плеером перехватывают буквы
+                    crossterm::event::KeyCode::Char(c) if app.state == AppState::MessageInput => {
+                        app.push_message_char(c);
+                    }
+                    // Вторая клавиша после e выбирает эмодзи из quick_emoji, раньше остальных клавиш Main
+                    crossterm::event::KeyCode::Char(c) if app.state == AppState::Main && pending_key == Some('e') => {
+                        app.send_quick_emoji(c);
+                    }
+                    crossterm::event::KeyCode::Char('e') if app.state == AppState::Main => {
+                        app.pending_key = Some('e');
+                        app.show_notice("e: эмодзи сообщением…");
+                    }
+                    // Начатый префикс повторения продолжают любые цифры
+                    crossterm::event::KeyCode::Char(c) if app.state == AppState::Main && c.is_ascii_digit() && count.is_some() => {
+                        app.push_count_digit(count, c);
+                    }
+                    // В ленте 1-5 ставят быструю реакцию, префикс там начинается с Alt (Alt+5 j)
+                    crossterm::event::KeyCode::Char(c @ '1'..='5') if app.state == AppState::Main && app.focus_on_messages && !key.modifiers.contains(crossterm::event::KeyModifiers::ALT) => {
+                        let slot = c as usize - '1' as usize;
+                        if let Err(e) = app.quick_react(slot).await {
+                            app.show_error(&format!("Ошибка реакции: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Char(c) if app.state == AppState::Main && c.is_ascii_digit() && c != '0' => {
+                        app.push_count_digit(count, c);
+                    }
+                    crossterm::event::KeyCode::Char('/') if app.state == AppState::Main && app.focus_on_messages => {
+                        app.start_search();
+                    }
+                    crossterm::event::KeyCode::Char('/') if app.state == AppState::Main => {
+                        app.start_chat_filter();
+                    }
+                    crossterm::event::KeyCode::Char('n') if app.state == AppState::Main && app.focus_on_messages && app.search_term.is_some() => {
+                        app.search_next(-1);
+                    }
+                    crossterm::event::KeyCode::Char('N') if app.state == AppState::Main && app.focus_on_messages && app.search_term.is_some() => {
+                        app.search_next(1);
+                    }
+                    crossterm::event::KeyCode::Char('M') if app.state == AppState::Main && app.mark_current_chat_read() => {
+                        app.show_notice("Чат отмечен прочитанным");
+                    }
+                    crossterm::event::KeyCode::Char('V') if app.state == AppState::Main => {
+                        if let Err(e) = app.toggle_voice_recording().await {
+                            app.show_error(&format!("Ошибка записи голосового: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Esc if app.state == AppState::Main && app.recording.is_some() => {
+                        app.cancel_voice_recording();
+                    }
+                    crossterm::event::KeyCode::Char('F') if app.state == AppState::Main && app.focus_on_messages => {
+                        app.redownload_selected_media();
+                    }
+                    crossterm::event::KeyCode::Char('[') if app.state == AppState::Main && app.focus_on_messages => {
+                        app.select_same_sender_message(-1);
+                    }
+                    crossterm::event::KeyCode::Char(']') if app.state == AppState::Main && app.focus_on_messages => {
+                        app.select_same_sender_message(1);
+                    }
+                    crossterm::event::KeyCode::Up if app.state == AppState::Bookmarks => {
+                        app.move_bookmark_selection(-1);
+                    }
+                    crossterm::event::KeyCode::Down if app.state == AppState::Bookmarks => {
+                        app.move_bookmark_selection(1);
+                    }
+                    crossterm::event::KeyCode::Char('d') if app.state == AppState::Bookmarks => {
+                        app.remove_selected_bookmark();
+                    }
+                    crossterm::event::KeyCode::Enter if app.state == AppState::Bookmarks => {
+                        if let Err(e) = app.open_selected_bookmark().await {
+                            app.show_error(&format!("Ошибка перехода к закладке: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('\'') if app.state == AppState::Bookmarks => {
+                        app.close_bookmarks();
+                    }
+                    crossterm::event::KeyCode::Char('b') if app.state == AppState::Main && app.focus_on_messages => {
+                        app.toggle_bookmark_selected_message();
+                    }
+                    crossterm::event::KeyCode::Char('\'') if app.state == AppState::Main => {
+                        app.open_bookmarks();
+                    }
+                    crossterm::event::KeyCode::Up if app.state == AppState::CatchUp => {
+                        app.move_catch_up_selection(-1);
+                    }
+                    crossterm::event::KeyCode::Down if app.state == AppState::CatchUp => {
+                        app.move_catch_up_selection(1);
+                    }
+                    crossterm::event::KeyCode::Enter if app.state == AppState::CatchUp => {
+                        if let Err(e) = app.open_catch_up_chat().await {
+                            app.show_error(&format!("Ошибка выбора чата: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Esc if app.state == AppState::CatchUp => {
+                        app.close_catch_up();
+                    }
+                    crossterm::event::KeyCode::Char('v') if app.state == AppState::Main && app.focus_on_messages => {
+                        if let Err(e) = app.show_message_readers().await {
+                            app.show_error(&format!("Ошибка загрузки списка прочитавших: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('v') if app.state == AppState::Readers => {
+                        app.close_readers();
+                    }
+                    crossterm::event::KeyCode::Char('t') if app.state == AppState::Main && app.focus_on_messages => {
+                        if let Err(e) = app.toggle_translation().await {
+                            app.show_error(&format!("Ошибка перевода: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Char('I') if app.state == AppState::Main => {
+                        if let Err(e) = app.show_chat_info().await {
+                            app.show_error(&format!("Ошибка загрузки информации о чате: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('I') if app.state == AppState::ChatInfo => {
+                        app.close_chat_info();
+                    }
+                    crossterm::event::KeyCode::Char('L') if app.state == AppState::Main => {
+                        if let Err(e) = app.show_invite_link().await {
+                            app.show_error(&format!("Ошибка загрузки ссылки-приглашения: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Char('y') if app.state == AppState::InviteLink => {
+                        app.copy_invite_link();
+                    }
+                    crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('L') if app.state == AppState::InviteLink => {
+                        app.close_invite_link();
+                    }
+                    crossterm::event::KeyCode::Char('+') if app.state == AppState::Main && app.focus_on_messages => {
+                        app.open_reactions();
+                    }
+                    crossterm::event::KeyCode::Esc | crossterm::event::KeyCode::Char('+') if app.state == AppState::Reactions => {
+                        app.close_reactions();
+                    }
+                    crossterm::event::KeyCode::Char('-') if (app.state == AppState::Main && app.focus_on_messages) || app.state == AppState::Reactions => {
+                        if let Err(e) = app.remove_my_reaction().await {
+                            app.show_error(&format!("Ошибка снятия реакции: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Char('q') => break,
+                    crossterm::event::KeyCode::Tab if matches!(app.state, AppState::Main | AppState::MessageInput) => {
+                        app.cycle_focus(1);
+                    }
+                    crossterm::event::KeyCode::BackTab if matches!(app.state, AppState::Main | AppState::MessageInput) => {
+                        app.cycle_focus(-1);
+                    }
+                    crossterm::event::KeyCode::Up => {
+                        app.move_selection(-1, count);
+                    }
+                    crossterm::event::KeyCode::Down => {
+                        app.move_selection(1, count);
+                    }
+                    crossterm::event::KeyCode::Home if app.state == AppState::Main => {
+                        app.select_edge(false);
+                    }
+                    crossterm::event::KeyCode::Char('g') if app.state == AppState::Main && pending_key == Some('g') => {
+                        app.select_edge(false);
+                    }
+                    crossterm::event::KeyCode::Char('g') if app.state == AppState::Main => {
+                        app.pending_key = Some('g');
+                    }
+                    // Для сообщений End переходит к самому новому
+                    crossterm::event::KeyCode::End | crossterm::event::KeyCode::Char('G') if app.state == AppState::Main => {
+                        app.select_edge(true);
+                    }
+                    crossterm::event::KeyCode::Char('h') if app.state == AppState::Main && app.focus_on_messages && app.audio_player.is_playing => {
+                        let seeked = app.audio_player.seek(-2); // Rewind 2 seconds
+                        if !seeked {
+                            // If IPC communication failed, restart player at new position
+                            app.restart_player_at_position();
+                        }
+                    }
+                    // j/k остаются навигацией и во время воспроизведения
+                    crossterm::event::KeyCode::Char('l') if app.state == AppState::Main && app.focus_on_messages && app.audio_player.is_playing => {
+                        let seeked = app.audio_player.seek(2); // Fast forward 2 seconds
+                        if !seeked {
+                            // If IPC communication failed, restart player at new position
+                            app.restart_player_at_position();
+                        }
+                    }
+                    crossterm::event::KeyCode::Char('j') if app.state == AppState::Main => {
+                        app.move_selection(1, count);
+                    }
+                    crossterm::event::KeyCode::Char('k') if app.state == AppState::Main => {
+                        app.move_selection(-1, count);
+                    }
+                    crossterm::event::KeyCode::Char(' ') if app.state == AppState::Main && app.focus_on_messages && app.audio_player.is_playing => {
+                        app.audio_player.stop(); // Stop playback
+                    }
+                    crossterm::event::KeyCode::Char(' ') if app.state == AppState::Main && app.focus_on_messages => {
+                        app.toggle_mark_selected_message();
+                    }
+                    crossterm::event::KeyCode::Char('r') if app.state == AppState::Main && key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL | crossterm::event::KeyModifiers::ALT) => {
+                        app.reload_config();
+                    }
+                    crossterm::event::KeyCode::Char('r') if matches!(app.state, AppState::Main | AppState::ImagePreview | AppState::VideoPreview) && key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                        app.reload_image_rendering();
+                    }
                     crossterm::event::KeyCode::Char('r') => {
                         if let Err(e) = app.refresh_data().await {
                             app.show_error(&format!("Ошибка обновления: {}", e));
                         }
                     }
-                    crossterm::event::KeyCode::Char('i') => {
-                        if app.state == AppState::Main {
-                            app.state = AppState::MessageInput;
+                    crossterm::event::KeyCode::Char('P') if app.state == AppState::Main && !app.focus_on_messages => {
+                        app.toggle_pin_selected_chat();
+                    }
+                    crossterm::event::KeyCode::Char('O') if app.state == AppState::Main => {
+                        if let Err(e) = app.toggle_online().await {
+                            app.show_error(&format!("Ошибка смены статуса: {}", e));
                         }
                     }
+                    crossterm::event::KeyCode::Char('B') if app.state == AppState::Main => {
+                        app.request_block_user();
+                    }
+                    // Ctrl+U в списке чатов ничего не делает, а не открывает непрочитанный чат
+                    crossterm::event::KeyCode::Char('u') if app.state == AppState::Main && !key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                        if let Err(e) = app.select_next_unread_chat().await {
+                            app.show_error(&format!("Ошибка загрузки сообщений: {}", e));
+                        }
+                    }
+                    crossterm::event::KeyCode::Char('C') if app.state == AppState::Main => {
+                        app.request_clear_history();
+                    }
+                    crossterm::event::KeyCode::Char('!') if app.state == AppState::Main && app.focus_on_messages => {
+                        app.open_report_picker();
+                    }
+                    crossterm::event::KeyCode::Char('W') if app.state == AppState::Main => {
+                        app.open_chat_in_official_client();
+                    }
+                    crossterm::event::KeyCode::Char('s') if app.state == AppState::Main && app.focus_on_messages => {
+                        app.toggle_spoiler_reveal();
+                    }
+                    crossterm::event::KeyCode::Char('i') | crossterm::event::KeyCode::Char('a') if app.state == AppState::Main => {
+                        app.enter_message_input(false);
+                    }
+                    crossterm::event::KeyCode::Char('o') if app.state == AppState::Main => {
+                        app.enter_message_input(true);
+                    }
+                    crossterm::event::KeyCode::Char('d') if app.state == AppState::Main && app.focus_on_messages => {
+                        app.request_delete_message().await;
+                    }
+                    crossterm::event::KeyCode::Char('R') if app.state == AppState::Main && app.focus_on_messages => {
+                        app.reply_to_message();
+                    }
                     crossterm::event::KeyCode::Enter => {
                         match app.state {
                             AppState::Main => {
@@ -190,10 +715,12 @@ async fn run_tui(mut app: App) -> Result<()> {
                                 }
                             }
                             AppState::MessageInput => {
-                                if let Err(e) = app.send_message().await {
-                                    app.show_error(&format!("Ошибка отправки: {}", e));
+                                let ctrl = key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL);
+                                if app.config.send_key == SendKey::CtrlEnter && !ctrl {
+                                    app.push_message_char('\n');
+                                } else {
+                                    submit_message(&mut app).await;
                                 }
-                                app.state = AppState::Main;
                             }
                             AppState::PhoneInput => {
                                 if let Err(e) = app.set_phone_number().await {
@@ -206,18 +733,11 @@ async fn run_tui(mut app: App) -> Result<()> {
                                 }
                             }
                             AppState::ImagePreview => {
-                                // If this is a video preview (has video path stored), play the video
-                                if let Some(video_path) = &app.preview_video_path {
-                                    if !video_path.is_empty() {
-                                        if let Err(e) = app.play_video() {
-                                            app.show_error(&format!("Ошибка воспроизведения видео: {}", e));
-                                        }
-                                    } else {
-                                        app.close_image_preview();
+                                // Превью видео запускает воспроизведение, фото, стикер и карту Enter закрывает
+                                if app.preview_video().is_some() {
+                                    if let Err(e) = app.play_video() {
+                                        app.show_error(&format!("Ошибка воспроизведения видео: {}", e));
                                     }
-                                } else if app.preview_image_path.is_some() {
-                                    // This might be a location map preview, just close it
-                                    app.close_image_preview();
                                 } else {
                                     app.close_image_preview();
                                 }
@@ -234,6 +754,15 @@ async fn run_tui(mut app: App) -> Result<()> {
                         if app.state == AppState::MessageInput {
                             app.state = AppState::Main;
                             app.message_input.clear();
+                            app.cancel_reply();
+                        } else if app.state == AppState::Main && !app.focus_on_messages && !app.chat_filter.is_empty() {
+                            app.clear_chat_filter();
+                        } else if app.state == AppState::Main && !app.marked_messages.is_empty() {
+                            // Сначала Esc снимает отметки с сообщений
+                            app.marked_messages.clear();
+                        } else if app.state == AppState::Main && app.search_term.is_some() {
+                            // Затем сбрасывает подсветку поиска
+                            app.clear_search();
                         } else if app.state == AppState::Main {
                             // Esc возвращает фокус на список чатов
                             app.focus_chats();
@@ -247,7 +776,6 @@ async fn run_tui(mut app: App) -> Result<()> {
                         match app.state {
                             AppState::PhoneInput => app.phone_input.push(c),
                             AppState::CodeInput => app.code_input.push(c),
-                            AppState::MessageInput => app.message_input.push(c),
                             _ => {}
                         }
                     }
@@ -270,21 +798,36 @@ async fn run_tui(mut app: App) -> Result<()> {
         }
     }
 
+    // Незавершенная запись голосового не должна продолжаться после выхода
+    app.cancel_voice_recording();
+    app.save_session();
+
     // Восстановление терминала
+    if keyboard_enhancement {
+        crossterm::execute!(std::io::stdout(), crossterm::event::PopKeyboardEnhancementFlags)?;
+    }
     crossterm::terminal::disable_raw_mode()?;
     crossterm::execute!(
         std::io::stdout(),
+        crossterm::event::DisableFocusChange,
         crossterm::terminal::LeaveAlternateScreen
     )?;
 
     Ok(())
 }
 
+async fn submit_message(app: &mut App) {
+    if let Err(e) = app.send_message().await {
+        app.show_error(&format!("Ошибка отправки: {}", e));
+    }
+    app.state = AppState::Main;
+}
+
 // Функция для проверки, является ли файл валидным PNG
 fn is_valid_image_file(file_path: &str) -> bool {
     if let Ok(mut file) = std::fs::File::open(file_path) {
         let mut header = [0u8; 12];
-        if let Ok(_) = std::io::Read::read_exact(&mut file, &mut header) {
+        if std::io::Read::read_exact(&mut file, &mut header).is_ok() {
             // Проверяем различные форматы изображений
             if header.len() >= 2 {
                 // JPEG: начинается с 0xFF 0xD8
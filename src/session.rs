@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::config::config_dir;
+
+// Состояние интерфейса для восстановления последней сессии при запуске.
+// Отдельно от tui.json: меняется постоянно и правкой руками не предполагается.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSession {
+    // Открытый чат и выбранное в нем сообщение
+    pub chat_id: Option<i64>,
+    pub message_id: Option<i32>,
+    pub focus_on_messages: bool,
+    pub sidebar_hidden: bool,
+    // Открытый оверлей; предпросмотры и поля ввода не восстанавливаем
+    pub overlay: Option<SessionOverlay>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionOverlay {
+    // Список закладок (')
+    Bookmarks,
+    // Поиск по всем чатам (S)
+    GlobalSearch,
+    // Информация о чате (I)
+    ChatInfo,
+}
+
+fn session_path() -> PathBuf {
+    config_dir().join("session.json")
+}
+
+pub fn load() -> Option<UiSession> {
+    let data = std::fs::read_to_string(session_path()).ok()?;
+    match serde_json::from_str(&data) {
+        Ok(session) => Some(session),
+        Err(e) => {
+            log::warn!("Не удалось разобрать сохраненную сессию: {}", e);
+            None
+        }
+    }
+}
+
+pub fn save(session: &UiSession) {
+    let result = std::fs::create_dir_all(config_dir())
+        .map_err(anyhow::Error::from)
+        .and_then(|_| Ok(serde_json::to_string_pretty(session)?))
+        .and_then(|data| Ok(std::fs::write(session_path(), data)?));
+    if let Err(e) = result {
+        log::warn!("Не удалось сохранить сессию: {}", e);
+    }
+}
@@ -0,0 +1,144 @@
+use ratatui::symbols::border;
+
+// Значки интерфейса. Оба набора задаются здесь, чтобы при добавлении значка
+// не забыть ASCII-замену для терминалов без эмодзи и псевдографики.
+#[derive(Debug)]
+pub struct Symbols {
+    // Рамки панелей
+    pub border: border::Set,
+    // Маркер выбранного элемента списка
+    pub selected: &'static str,
+    // Отметка сообщения (Space)
+    pub marked: &'static str,
+    // Сообщение является ответом
+    pub reply: &'static str,
+    pub sending: &'static str,
+    pub sent: &'static str,
+    pub scheduled: &'static str,
+    pub pinned: &'static str,
+    // Непрочитанные упоминания в списке чатов
+    pub mention: &'static str,
+    // Чат без уведомлений
+    pub muted: &'static str,
+    // Баннер потери связи
+    pub warning: &'static str,
+    // Идет запись голосового
+    pub recording: &'static str,
+    // Заполнитель скрытого спойлером текста и медиа
+    pub spoiler: char,
+    pub media_spoiler: &'static str,
+    // Курсор в полях ввода оверлеев
+    pub cursor: &'static str,
+    // Заголовок ленты при свернутом списке чатов
+    pub sidebar_hidden: &'static str,
+    pub separator: &'static str,
+    pub sticker: &'static str,
+    pub photo: &'static str,
+    pub video: &'static str,
+    pub round_video: &'static str,
+    pub voice: &'static str,
+    pub audio: &'static str,
+    pub location: &'static str,
+    // Столбики формы волны голосового, от низкого к высокому
+    pub waveform: &'static [&'static str],
+    // Управление плеером
+    pub play: &'static str,
+    pub pause: &'static str,
+    pub close: &'static str,
+}
+
+pub const UNICODE: Symbols = Symbols {
+    border: border::PLAIN,
+    selected: "▶ ",
+    marked: "✔",
+    reply: "↩ ",
+    sending: "⏳",
+    sent: "✓",
+    scheduled: "🕓",
+    pinned: "📌",
+    mention: "🔔",
+    muted: "🔕",
+    warning: "⚠",
+    recording: "●",
+    spoiler: '█',
+    media_spoiler: "░░░",
+    cursor: "▏",
+    sidebar_hidden: "◀",
+    separator: "│",
+    sticker: "🏷️",
+    photo: "📷",
+    video: "🎬",
+    round_video: "🔮",
+    voice: "🎤",
+    audio: "🎵",
+    location: "📍",
+    waveform: &["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"],
+    play: "▶",
+    pause: "⏸",
+    close: "✗",
+};
+
+pub const ASCII: Symbols = Symbols {
+    border: border::Set {
+        top_left: "+",
+        top_right: "+",
+        bottom_left: "+",
+        bottom_right: "+",
+        vertical_left: "|",
+        vertical_right: "|",
+        horizontal_top: "-",
+        horizontal_bottom: "-",
+    },
+    selected: "> ",
+    marked: "*",
+    reply: "re: ",
+    sending: "[..]",
+    sent: "[ok]",
+    scheduled: "[at]",
+    pinned: "[pin]",
+    mention: "@",
+    muted: "[m]",
+    warning: "!",
+    recording: "(REC)",
+    spoiler: '#',
+    media_spoiler: "###",
+    cursor: "_",
+    sidebar_hidden: "<",
+    separator: "|",
+    sticker: "sticker",
+    photo: "photo",
+    video: "video",
+    round_video: "round",
+    voice: "voice",
+    audio: "audio",
+    location: "geo",
+    waveform: &["_", "_", ".", ".", ":", ":", "|", "|"],
+    play: ">",
+    pause: "||",
+    close: "x",
+};
+
+pub fn get(ascii_only: bool) -> &'static Symbols {
+    if ascii_only {
+        &ASCII
+    } else {
+        &UNICODE
+    }
+}
+
+// Консоль Linux и терминалы с не-UTF-8 локалью не умеют рисовать эмодзи и псевдографику
+pub fn terminal_lacks_unicode() -> bool {
+    let term = std::env::var("TERM").unwrap_or_default();
+    if matches!(term.as_str(), "linux" | "dumb" | "vt100" | "vt102" | "vt220") {
+        return true;
+    }
+    // Кодировку определяет первая непустая из переменных локали; без них считаем, что UTF-8 есть
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .is_some_and(|locale| {
+            let locale = locale.to_lowercase();
+            !locale.contains("utf-8") && !locale.contains("utf8")
+        })
+}
@@ -1,13 +1,19 @@
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Line},
+    text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap, Clear},
     Frame,
 };
-use ratatui_image::{picker::Picker, protocol::StatefulProtocol, StatefulImage};
+use ratatui_image::{picker::{Picker, ProtocolType}, protocol::StatefulProtocol, Resize, StatefulImage};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 
-use crate::app::{App, AppState};
+use crate::app::{expand_template, App, AppState};
+use crate::config::{MediaAlign, SenderName, TextDirection};
+use crate::symbols::Symbols;
+use crate::util::is_rtl_text;
+use crate::SendState;
 
 // Helper function to format duration: "MM:SS" for >= 60 seconds, "X сек" for < 60 seconds
 fn format_duration(duration_seconds: i32) -> String {
@@ -29,7 +35,7 @@ fn format_time_for_metadata(timestamp: &str) -> String {
         }
     }
     // Fallback to extracting time from any format
-    if let Some(time_part) = timestamp.split(' ').last() {
+    if let Some(time_part) = timestamp.split(' ').next_back() {
         if time_part.len() >= 5 {
             return time_part[..5].to_string();
         }
@@ -37,6 +43,411 @@ fn format_time_for_metadata(timestamp: &str) -> String {
     "??:??".to_string()
 }
 
+// Время отложенной отправки: "ДД.ММ ЧЧ:ММ" в локальном часовом поясе
+fn format_schedule_time(timestamp: &str) -> String {
+    match chrono::DateTime::parse_from_rfc3339(timestamp) {
+        Ok(at) => at.with_timezone(&chrono::Local).format("%d.%m %H:%M").to_string(),
+        Err(_) => timestamp.to_string(),
+    }
+}
+
+const COLLAPSED_MARK: &str = "… ещё";
+
+// Высота сообщения в ленте в строках; текст учитывает перенос и сворачивание длинных сообщений
+fn message_row_height(app: &App, msg: &crate::Message, is_selected: bool, width: u16) -> u16 {
+    let message_height = 1; // базовая высота для сообщения
+    let image_height = 12; // высота для изображения
+    let sticker_height = 8; // высота для стикера
+    let voice_height = 3; // увеличена высота для голосового сообщения с плеером
+    let audio_height = 3; // увеличена высота для аудио сообщения с плеером
+    let webpage_card_height = 4; // карточка превью ссылки
+    let webpage_selected_card_height = 9; // карточка с миниатюрой
+
+    match msg.r#type.as_str() {
+        "photo" | "video" => if is_selected { image_height } else { message_height },
+        "sticker" => if is_selected { sticker_height } else { message_height },
+        "voice" => if !is_selected {
+            message_height
+        } else if has_waveform(msg) {
+            voice_height + 1
+        } else {
+            voice_height
+        },
+        "audio" => if is_selected { audio_height } else { message_height },
+        "location" => message_height,
+        t if is_service_type(t) => message_height,
+        _ => {
+            let text_width = if is_selected { width.saturating_sub(2) } else { width };
+            let text_rows = (reply_quote_lines(app, msg, text_width).len()
+                + text_message_lines(app, msg, text_width, is_selected).len()
+                + translation_lines(app, msg, text_width).len()) as u16
+                + u16::from(has_reactions(msg));
+            if has_webpage_preview(msg) {
+                text_rows + if is_selected { webpage_selected_card_height } else { webpage_card_height }
+            } else {
+                text_rows
+            }
+        }
+    }
+}
+
+fn has_reactions(msg: &crate::Message) -> bool {
+    msg.reactions.as_ref().is_some_and(|r| !r.is_empty())
+}
+
+// Строка реакций под текстом: сколько помещается по ширине, остальные - счетчиком "+N"
+fn reactions_line(msg: &crate::Message, width: u16) -> Option<Line<'static>> {
+    let reactions = msg.reactions.as_ref().filter(|r| !r.is_empty())?;
+    let width = width as usize;
+    let mut spans = Vec::new();
+    let mut used = 0;
+    for (i, reaction) in reactions.iter().enumerate() {
+        let label = format!("{} {}", reaction.emoji, reaction.count);
+        let separator = if i == 0 { 0 } else { 2 };
+        let label_width = Line::from(label.as_str()).width();
+        let remaining = reactions.len() - i - 1;
+        // Для последней реакции место под "+N" не нужно
+        let overflow_width = if remaining > 0 { format!("  +{}", remaining).len() } else { 0 };
+        if used + separator + label_width + overflow_width > width {
+            let hidden = reactions.len() - i;
+            spans.push(Span::styled(format!("{}+{}", if i == 0 { "" } else { "  " }, hidden), Style::default().fg(Color::DarkGray)));
+            break;
+        }
+        if separator > 0 {
+            spans.push(Span::raw("  "));
+        }
+        let style = if reaction.chosen {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        spans.push(Span::styled(label, style));
+        used += separator + label_width;
+    }
+    Some(Line::from(spans))
+}
+
+fn reply_mark(msg: &crate::Message, symbols: &Symbols) -> &'static str {
+    if msg.reply_to_id.is_some() { symbols.reply } else { "" }
+}
+
+// "[переслано от A (изначально от B)] " перед текстом; у выбранного сообщения - вся цепочка и ссылка на оригинал
+fn forward_mark(msg: &crate::Message, full: bool) -> String {
+    let Some(chain) = msg.forward_origin.as_ref().filter(|c| !c.is_empty()) else {
+        return String::new();
+    };
+    let name = |origin: &crate::ForwardOrigin| {
+        origin.name.clone().filter(|n| !n.is_empty()).unwrap_or_else(|| "скрытого отправителя".to_string())
+    };
+    let mut label = format!("переслано от {}", name(&chain[0]));
+    if chain.len() > 1 {
+        let via = if full && chain.len() > 2 {
+            let middle: Vec<String> = chain[1..chain.len() - 1].iter().map(name).collect();
+            format!(" через {}", middle.join(", "))
+        } else {
+            String::new()
+        };
+        label.push_str(&format!(" (изначально от {}{})", name(&chain[chain.len() - 1]), via));
+    }
+    if let Some(link) = chain.last().and_then(|o| o.link.as_deref()).filter(|l| full && !l.is_empty()) {
+        label.push_str(&format!(" {}", link));
+    }
+    format!("[{}] ", label)
+}
+
+// Подсветка всех вхождений поискового термина в строке (без учета регистра)
+fn highlight_matches(line: String, term: &str) -> Line<'static> {
+    let term: Vec<char> = term.chars().flat_map(char::to_lowercase).collect();
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    if term.is_empty() {
+        return Line::from(line);
+    }
+
+    let highlight = Style::default().fg(Color::Black).bg(Color::Yellow);
+    let mut spans = Vec::new();
+    let mut plain_start = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        match match_len_at(&chars[i..], &term) {
+            Some(len) => {
+                let start = chars[i].0;
+                let end = chars.get(i + len).map(|(b, _)| *b).unwrap_or(line.len());
+                if plain_start < start {
+                    spans.push(Span::raw(line[plain_start..start].to_string()));
+                }
+                spans.push(Span::styled(line[start..end].to_string(), highlight));
+                plain_start = end;
+                i += len;
+            }
+            None => i += 1,
+        }
+    }
+    if plain_start < line.len() {
+        spans.push(Span::raw(line[plain_start..].to_string()));
+    }
+    Line::from(spans)
+}
+
+// Сколько символов с начала chars совпадают с термином (в нижнем регистре)
+fn match_len_at(chars: &[(usize, char)], term: &[char]) -> Option<usize> {
+    let mut matched = 0;
+    for (count, (_, c)) in chars.iter().enumerate() {
+        for lower in c.to_lowercase() {
+            if term.get(matched) != Some(&lower) {
+                return None;
+            }
+            matched += 1;
+        }
+        if matched == term.len() {
+            return Some(count + 1);
+        }
+    }
+    None
+}
+
+// Область медиа по настройке media_align: под отправителем - со сдвигом на ширину времени
+fn align_media_area(area: Rect, align: MediaAlign, time: &str) -> Rect {
+    match align {
+        MediaAlign::Sender => {
+            let shift = (time.chars().count() as u16).min(area.width);
+            Rect { x: area.x + shift, width: area.width - shift, ..area }
+        }
+        MediaAlign::Left | MediaAlign::Center => area,
+    }
+}
+
+// Подпись отправителя по настройке sender_name; без username остается отображаемое имя
+fn sender_name(app: &App, msg: &crate::Message) -> String {
+    let username = msg.from_username.as_deref().filter(|u| !u.is_empty());
+    match (app.config.sender_name, username) {
+        (SenderName::Username, Some(username)) => format!("@{}", username),
+        (SenderName::Both, Some(username)) => format!("{} (@{})", msg.from, username),
+        _ => msg.from.clone(),
+    }
+}
+
+// Строки текстового сообщения после переноса; невыбранные длинные сообщения сворачиваются
+fn text_message_lines(app: &App, msg: &crate::Message, width: u16, is_selected: bool) -> Vec<String> {
+    let time = if app.show_timestamps {
+        format!("{} ", format_time_for_metadata(&msg.timestamp))
+    } else {
+        String::new()
+    };
+    // Для локально отправленных сообщений показываем статус доставки, для запланированных - время отправки
+    let status_mark = match (msg.send_state, &msg.scheduled_at) {
+        (Some(SendState::Sending), _) => format!(" {} отправка…", app.symbols.sending),
+        (Some(SendState::Sent), _) => format!(" {}", app.symbols.sent),
+        (None, Some(at)) => format!(" {} запланировано на {}", app.symbols.scheduled, format_schedule_time(at)),
+        (None, None) => String::new(),
+    };
+    let sender = sender_name(app, msg);
+    let text = if app.spoilers_hidden(msg) { mask_spoilers(msg, app.symbols.spoiler) } else { msg.text.clone() };
+    let content = format!("{}{}: {}{}{}{}", time, sender, reply_mark(msg, app.symbols), forward_mark(msg, is_selected), text, status_mark);
+    let mut lines = wrap_text_lines(&content, width as usize);
+
+    let max_lines = app.config.max_collapsed_lines.max(1);
+    if !is_selected && lines.len() > max_lines {
+        let hidden = lines.len() - max_lines;
+        lines.truncate(max_lines);
+        lines.push(format!("{} {} {}", COLLAPSED_MARK, hidden, lines_word(hidden)));
+    }
+    lines
+}
+
+// Перевод (t) под текстом сообщения, с кодом языка; пусто, если перевод не показан
+fn translation_lines(app: &App, msg: &crate::Message, width: u16) -> Vec<String> {
+    let key = (msg.chat_id, msg.id);
+    let Some(translation) = app.translations.get(&key).filter(|_| app.shown_translations.contains(&key)) else {
+        return Vec::new();
+    };
+    let bar = format!("{} ", app.symbols.separator);
+    let available = (width as usize).saturating_sub(Line::from(bar.as_str()).width()).max(1);
+    wrap_text_lines(&format!("[{}] {}", app.config.translate_to, translation), available)
+        .into_iter()
+        .map(|line| format!("{}{}", bar, line))
+        .collect()
+}
+
+// Сколько строк цитаты исходного сообщения показывать над ответом
+const REPLY_QUOTE_MAX_LINES: usize = 3;
+
+// Цитата сообщения, на которое дан ответ: переносится по ширине, лишнее обрезается "…".
+// Пусто, если исходное сообщение не загружено.
+fn reply_quote_lines(app: &App, msg: &crate::Message, width: u16) -> Vec<String> {
+    let Some(original) = msg.reply_to_id.and_then(|id| app.messages.iter().find(|m| m.id == id)) else {
+        return Vec::new();
+    };
+    let text = if app.spoilers_hidden(original) { mask_spoilers(original, app.symbols.spoiler) } else { original.text.clone() };
+    // У медиа без текста цитируем подпись или тип сообщения
+    let text = if text.is_empty() {
+        media_caption(original).map(str::to_string).unwrap_or_else(|| format!("[{}]", original.r#type))
+    } else {
+        text
+    };
+    let text = text.replace('\n', " ");
+    let bar = format!("{} ", app.symbols.separator);
+    let bar_width = Line::from(bar.as_str()).width();
+    let available = (width as usize).saturating_sub(bar_width).max(1);
+    let mut lines = wrap_text_lines(&format!("{}: {}", sender_name(app, original), text), available);
+    if lines.len() > REPLY_QUOTE_MAX_LINES {
+        lines.truncate(REPLY_QUOTE_MAX_LINES);
+        if let Some(last) = lines.last_mut() {
+            while !last.is_empty() && Line::from(last.as_str()).width() + 1 > available {
+                last.pop();
+            }
+            last.push('…');
+        }
+    }
+    lines.into_iter().map(|line| format!("{}{}", bar, line)).collect()
+}
+
+fn lines_word(n: usize) -> &'static str {
+    match (n % 10, n % 100) {
+        (1, m) if m != 11 => "строка",
+        (2..=4, m) if !(12..=14).contains(&m) => "строки",
+        _ => "строк",
+    }
+}
+
+// Перенос текста по словам с учетом ширины символов; слишком длинные слова режутся
+fn wrap_text_lines(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut current = String::new();
+        let mut current_width = 0;
+        for word in paragraph.split(' ') {
+            let word_width = Line::from(word).width();
+            let separator = usize::from(!current.is_empty());
+            if current_width + separator + word_width <= width {
+                if separator == 1 {
+                    current.push(' ');
+                }
+                current.push_str(word);
+                current_width += separator + word_width;
+                continue;
+            }
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+                current_width = 0;
+            }
+            for ch in word.chars() {
+                let ch_width = Line::from(ch.to_string()).width();
+                if current_width + ch_width > width && !current.is_empty() {
+                    lines.push(std::mem::take(&mut current));
+                    current_width = 0;
+                }
+                current.push(ch);
+                current_width += ch_width;
+            }
+        }
+        lines.push(current);
+    }
+    lines
+}
+
+// Медиа под спойлером: вместо миниатюры заглушка, пока спойлер не раскрыт
+fn draw_media_spoiler(f: &mut Frame, area: Rect, time: &str, sender: &str, symbols: &Symbols) {
+    let inner_area = Rect {
+        x: area.x + 2,
+        y: area.y,
+        width: area.width.saturating_sub(2),
+        height: area.height,
+    };
+    let lines = vec![
+        Line::from(format!("{}{}:", time, sender)).style(Style::default().fg(Color::Yellow)),
+        Line::from(format!("{0} Медиа скрыто спойлером — s: показать {0}", symbols.media_spoiler)).style(Style::default().fg(Color::DarkGray)),
+    ];
+    f.render_widget(Paragraph::new(lines), inner_area);
+}
+
+// Подпись медиа; если бэкенд не прислал caption, используем текст сообщения
+fn media_caption(msg: &crate::Message) -> Option<&str> {
+    msg.caption
+        .as_deref()
+        .filter(|c| !c.is_empty())
+        .or_else(|| Some(msg.text.as_str()).filter(|t| !t.is_empty()))
+}
+
+fn caption_suffix(msg: &crate::Message) -> String {
+    media_caption(msg).map(|c| format!(" {}", c)).unwrap_or_default()
+}
+
+// Подпись выводится на последней строке области медиа, под изображением
+fn draw_media_caption(f: &mut Frame, msg: &crate::Message, area: Rect) {
+    if let Some(caption) = media_caption(msg) {
+        if area.height < 3 {
+            return;
+        }
+        let caption_area = Rect {
+            x: area.x,
+            y: area.y + area.height - 1,
+            width: area.width,
+            height: 1,
+        };
+        let caption_widget = Paragraph::new(caption.to_string())
+            .style(Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC));
+        f.render_widget(caption_widget, caption_area);
+    }
+}
+
+fn has_webpage_preview(msg: &crate::Message) -> bool {
+    msg.webpage_title.is_some() || msg.webpage_description.is_some()
+}
+
+// Карточка превью ссылки под текстом сообщения; миниатюра показывается только у выбранного
+fn draw_webpage_preview(f: &mut Frame, msg: &crate::Message, area: Rect, picker: Option<&Picker>, is_selected: bool, symbols: &Symbols) {
+    f.render_widget(Clear, area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_set(symbols.border)
+        .border_style(Style::default().fg(Color::DarkGray));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let mut text_area = inner;
+    if is_selected {
+        if let (Some(image_path), Some(picker)) = (&msg.webpage_image_path, picker) {
+            // Миниатюра слева, примерно квадратная с учетом пропорций ячейки
+            let thumb_width = (inner.height * 2).min(inner.width / 3);
+            let thumb_area = Rect { width: thumb_width, ..inner };
+            if render_cached_image(f, image_path, thumb_area, picker, false, false).is_ok() {
+                text_area = Rect {
+                    x: inner.x + thumb_width + 1,
+                    width: inner.width.saturating_sub(thumb_width + 1),
+                    ..inner
+                };
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    if let Some(title) = &msg.webpage_title {
+        lines.push(Line::from(title.clone()).style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)));
+    }
+    if let Some(description) = &msg.webpage_description {
+        lines.push(Line::from(description.clone()).style(Style::default().fg(Color::Gray)));
+    }
+    let card = Paragraph::new(lines).wrap(Wrap { trim: true });
+    f.render_widget(card, text_area);
+}
+
+fn is_rtl_message(app: &App, msg: &crate::Message) -> bool {
+    match app.config.text_direction {
+        TextDirection::Auto => is_rtl_text(&msg.text),
+        TextDirection::Ltr => false,
+        TextDirection::Rtl => true,
+    }
+}
+
+// Служебные сообщения (вход в группу, завершенный звонок, закрепление и т.п.)
+pub fn is_service_type(message_type: &str) -> bool {
+    matches!(
+        message_type,
+        "service" | "chat_action" | "join" | "leave" | "call" | "pin" | "title_changed" | "photo_changed"
+    )
+}
+
 // Helper function to extract date from timestamp for grouping: "YYYY-MM-DD"
 fn extract_date_from_timestamp(timestamp: &str) -> String {
     // Parse ISO 8601 timestamp like "2025-08-25T23:26:56+03:00"
@@ -74,45 +485,388 @@ fn format_date_for_display(date_str: &str) -> String {
     date_str.to_string()
 }
 
+// Меньше этого раскладка не помещается: расчеты высот и отступов дают пустые или выходящие за экран области
+const MIN_TERMINAL_WIDTH: u16 = 20;
+const MIN_TERMINAL_HEIGHT: u16 = 6;
+// Лента уже, чем это, не вмещает даже время и имя отправителя
+const MIN_MESSAGES_WIDTH: u16 = 10;
+
 pub fn draw_ui(f: &mut Frame, app: &mut App) {
+    HALF_BLOCK_IMAGES.with(|enabled| enabled.set(app.half_block_images));
+    let area = f.area();
+    if area.width < MIN_TERMINAL_WIDTH || area.height < MIN_TERMINAL_HEIGHT {
+        draw_too_small(f, area);
+        return;
+    }
     match app.state {
         AppState::Loading => draw_loading_screen(f, app),
         AppState::PhoneInput => draw_phone_input(f, app),
         AppState::CodeInput => draw_code_input(f, app),
         AppState::Main => draw_main_screen(f, app),
         AppState::MessageInput => draw_main_screen(f, app),
+        AppState::SearchInput => draw_main_screen(f, app),
+        AppState::ChatFilterInput => draw_main_screen(f, app),
+        AppState::ScheduleInput => draw_main_screen(f, app),
+        AppState::ExportInput => draw_main_screen(f, app),
+        AppState::GotoInput => draw_main_screen(f, app),
+        AppState::Bookmarks => {
+            draw_main_screen(f, app);
+            draw_bookmarks(f, app);
+        }
+        AppState::CatchUp => {
+            draw_main_screen(f, app);
+            draw_catch_up(f, app);
+        }
+        AppState::Reactions => {
+            draw_main_screen(f, app);
+            draw_reactions(f, app);
+        }
+        AppState::GlobalSearch => {
+            draw_main_screen(f, app);
+            draw_global_search(f, app);
+        }
+        AppState::Readers => {
+            draw_main_screen(f, app);
+            draw_readers(f, app);
+        }
+        AppState::ChatInfo => {
+            draw_main_screen(f, app);
+            draw_chat_info(f, app);
+        }
+        AppState::InviteLink => {
+            draw_main_screen(f, app);
+            draw_invite_link(f, app);
+        }
         AppState::Error => draw_error_screen(f, app),
         AppState::ImagePreview => draw_image_preview(f, app),
         AppState::VideoPreview => draw_video_preview(f, app),
+        AppState::Confirm => {
+            draw_main_screen(f, app);
+            draw_confirm_dialog(f, app);
+        }
+        AppState::ReportReason => {
+            draw_main_screen(f, app);
+            draw_report_reason_picker(f, app);
+        }
+        AppState::RawJson => {
+            draw_main_screen(f, app);
+            draw_raw_json(f, app);
+        }
+    }
+}
+
+fn draw_too_small(f: &mut Frame, area: Rect) {
+    f.render_widget(Clear, area);
+    let text = Paragraph::new("Терминал слишком мал")
+        .style(Style::default().fg(Color::Yellow))
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(text, area);
+}
+
+// Прямоугольник заданного размера по центру области
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+fn draw_confirm_dialog(f: &mut Frame, app: &App) {
+    let prompt = app.pending_action.as_ref().map(|a| a.prompt()).unwrap_or_default();
+    let area = centered_rect(50, 5, f.area());
+    f.render_widget(Clear, area);
+    let dialog = Paragraph::new(vec![
+        Line::from(prompt),
+        Line::from(""),
+        Line::from("y/Enter: да | n/Esc: нет").style(Style::default().fg(Color::Gray)),
+    ])
+    .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("Подтверждение"))
+    .style(Style::default().fg(Color::Yellow))
+    .wrap(Wrap { trim: true });
+    f.render_widget(dialog, area);
+}
+
+fn draw_raw_json(f: &mut Frame, app: &App) {
+    let frame_area = f.area();
+    // Оставляем снизу строку статуса с подсказками
+    let area = Rect {
+        x: frame_area.x + 2,
+        y: frame_area.y + 1,
+        width: frame_area.width.saturating_sub(4),
+        height: frame_area.height.saturating_sub(5),
+    };
+    f.render_widget(Clear, area);
+    let json = Paragraph::new(app.raw_json.as_str())
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("JSON сообщения (y: скопировать, Esc: закрыть)"))
+        .style(Style::default().fg(Color::Green))
+        .scroll((app.raw_json_scroll, 0));
+    f.render_widget(json, area);
+}
+
+fn draw_report_reason_picker(f: &mut Frame, app: &App) {
+    let items: Vec<ListItem> = crate::app::REPORT_REASONS
+        .iter()
+        .map(|(_, label)| ListItem::new(*label))
+        .collect();
+    let area = centered_rect(40, items.len() as u16 + 2, f.area());
+    f.render_widget(Clear, area);
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("Причина жалобы"))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol(app.symbols.selected);
+    let mut state = ListState::default();
+    state.select(Some(app.report_reason_index));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_bookmarks(f: &mut Frame, app: &App) {
+    let items: Vec<ListItem> = app.bookmark_labels
+        .iter()
+        .map(|label| ListItem::new(label.as_str()))
+        .collect();
+    let screen = f.area();
+    let width = (screen.width * 3 / 4).max(40);
+    let height = (items.len() as u16 + 2).min(screen.height.saturating_sub(4)).max(3);
+    let area = centered_rect(width, height, screen);
+    f.render_widget(Clear, area);
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("Закладки"))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol(app.symbols.selected);
+    let mut state = ListState::default();
+    state.select(Some(app.bookmark_index));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_global_search(f: &mut Frame, app: &App) {
+    let screen = f.area();
+    let width = (screen.width * 3 / 4).max(40);
+    let height = screen.height.saturating_sub(6).max(6);
+    let area = centered_rect(width, height, screen);
+    f.render_widget(Clear, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(area);
+
+    let input = Paragraph::new(format!("{}{}", app.global_search_input, app.symbols.cursor))
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("Поиск по всем чатам"))
+        .style(Style::default().fg(Color::Cyan));
+    f.render_widget(input, chunks[0]);
+
+    let items: Vec<ListItem> = app.global_search_results
+        .iter()
+        .map(|msg| {
+            let text = msg.caption.clone().filter(|c| !c.is_empty()).unwrap_or(msg.text.clone());
+            let snippet: String = text.lines().next().unwrap_or("").chars().take(80).collect();
+            let time = chrono::DateTime::parse_from_rfc3339(&msg.timestamp)
+                .map(|t| t.format("%d.%m.%Y %H:%M").to_string())
+                .unwrap_or_else(|_| msg.timestamp.clone());
+            ListItem::new(vec![
+                Line::from(format!("{} · {}", app.chat_title(msg.chat_id), time)).style(Style::default().fg(Color::Gray)),
+                Line::from(format!("  {}: {}", msg.from, snippet)),
+            ])
+        })
+        .collect();
+    let title = if app.global_search_query.is_empty() {
+        "Результаты".to_string()
+    } else {
+        format!("Результаты: {} ({})", app.global_search_query, app.global_search_results.len())
+    };
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title(title))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol(app.symbols.selected);
+    let mut state = ListState::default();
+    if !app.global_search_results.is_empty() {
+        state.select(Some(app.global_search_index));
     }
+    f.render_stateful_widget(list, chunks[1], &mut state);
+}
+
+fn draw_readers(f: &mut Frame, app: &App) {
+    let items: Vec<ListItem> = app.readers_lines
+        .iter()
+        .map(|line| ListItem::new(line.as_str()))
+        .collect();
+    let width = app.readers_lines.iter().map(|l| l.chars().count() as u16 + 4).max().unwrap_or(0).max(30);
+    let height = (items.len() as u16 + 2).min(f.area().height.saturating_sub(4)).max(3);
+    let area = centered_rect(width, height, f.area());
+    f.render_widget(Clear, area);
+
+    let list = List::new(items).block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("Прочитали"));
+    f.render_widget(list, area);
+}
+
+fn draw_chat_info(f: &mut Frame, app: &App) {
+    let Some(details) = &app.chat_details else {
+        return;
+    };
+    let mut lines = vec![Line::from(details.title.clone()).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))];
+    if let Some(username) = details.username.as_deref().filter(|u| !u.is_empty()) {
+        lines.push(Line::from(format!("@{}", username)).style(Style::default().fg(Color::Cyan)));
+    }
+    if let Some(count) = details.members_count {
+        lines.push(Line::from(format!("Участников: {}", count)).style(Style::default().fg(Color::Gray)));
+    }
+    lines.push(Line::from(""));
+    // В личных чатах показываем bio собеседника, в группах и каналах - описание
+    let about = if details.r#type == "user" { &details.bio } else { &details.description };
+    match about.as_deref().filter(|t| !t.trim().is_empty()) {
+        Some(text) => lines.extend(text.lines().map(|l| Line::from(l.to_string()))),
+        None => lines.push(Line::from("Описания нет").style(Style::default().fg(Color::DarkGray))),
+    }
+
+    let width = 60.min(f.area().width.saturating_sub(4));
+    let text_rows: u16 = lines
+        .iter()
+        .map(|l| (l.width() as u16).div_ceil(width.saturating_sub(2).max(1)).max(1))
+        .sum();
+    let height = (text_rows + 2).min(f.area().height.saturating_sub(4)).max(3);
+    let area = centered_rect(width, height, f.area());
+    f.render_widget(Clear, area);
+
+    let title = if details.r#type == "user" { "О пользователе" } else { "О чате" };
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title(title))
+        .wrap(Wrap { trim: false });
+    f.render_widget(paragraph, area);
+}
+
+fn draw_invite_link(f: &mut Frame, app: &App) {
+    let Some((title, link)) = &app.invite_link else {
+        return;
+    };
+    let lines = vec![
+        Line::from(title.clone()).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+        Line::from(""),
+        Line::from(link.clone()).style(Style::default().fg(Color::Cyan)),
+    ];
+    let width = 60.min(f.area().width.saturating_sub(4));
+    let link_rows = (Line::from(link.as_str()).width() as u16).div_ceil(width.saturating_sub(2).max(1)).max(1);
+    let area = centered_rect(width, link_rows + 4, f.area());
+    f.render_widget(Clear, area);
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("Ссылка-приглашение"))
+        .wrap(Wrap { trim: false });
+    f.render_widget(paragraph, area);
+}
+
+fn draw_reactions(f: &mut Frame, app: &App) {
+    let reactions = app.messages
+        .get(app.selected_message_index)
+        .and_then(|m| m.reactions.clone())
+        .unwrap_or_default();
+    let items: Vec<ListItem> = reactions
+        .iter()
+        .map(|reaction| {
+            let text = format!("{} {}{}", reaction.emoji, reaction.count, if reaction.chosen { " (вы)" } else { "" });
+            let style = if reaction.chosen { Style::default().fg(Color::Yellow) } else { Style::default() };
+            ListItem::new(text).style(style)
+        })
+        .collect();
+    let height = (items.len() as u16 + 2).min(f.area().height.saturating_sub(4)).max(3);
+    let area = centered_rect(30, height, f.area());
+    f.render_widget(Clear, area);
+
+    let list = List::new(items).block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("Реакции"));
+    f.render_widget(list, area);
+}
+
+fn draw_catch_up(f: &mut Frame, app: &App) {
+    let total: i32 = app.catch_up.iter().map(|e| e.new_messages).sum();
+    let mentions: i32 = app.catch_up.iter().map(|e| e.mentions).sum();
+    let mut title = format!("Пока вас не было: {} новых в {} чатах", total, app.catch_up.len());
+    if mentions > 0 {
+        title.push_str(&format!(", упоминаний: {}", mentions));
+    }
+
+    let items: Vec<ListItem> = app.catch_up
+        .iter()
+        .map(|entry| {
+            let mut text = format!("{} — +{}", entry.title, entry.new_messages);
+            if entry.mentions > 0 {
+                text.push_str(&format!(" (@{})", entry.mentions));
+            }
+            ListItem::new(text)
+        })
+        .collect();
+    let screen = f.area();
+    let width = (title.chars().count() as u16 + 4).max(screen.width / 2);
+    let height = (items.len() as u16 + 2).min(screen.height.saturating_sub(4)).max(3);
+    let area = centered_rect(width, height, screen);
+    f.render_widget(Clear, area);
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title(title))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol(app.symbols.selected);
+    let mut state = ListState::default();
+    state.select(Some(app.catch_up_index));
+    f.render_stateful_widget(list, area, &mut state);
 }
 
 fn draw_main_screen(f: &mut Frame, app: &mut App) {
     let area = f.area();
 
+    // При вводе многострочного сообщения строка статуса растет вместе с текстом,
+    // а с compose_height имеет постоянную высоту, чтобы лента над ней не прыгала
+    let status_height = if app.state == AppState::MessageInput && app.config.compose_height > 0 {
+        app.config.compose_height.min(area.height / 2).max(1) + 2
+    } else if app.state == AppState::MessageInput {
+        app.message_input.split('\n').count().min(8) as u16 + 2
+    } else {
+        3
+    };
+
+    // Без связи с бэкендом сверху висит баннер, чтобы устаревшие данные не принимались за текущие
+    let banner_height = if app.disconnected { 1 } else { 0 };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
+            Constraint::Length(banner_height),
             Constraint::Min(0),
-            Constraint::Length(3),
+            Constraint::Length(status_height),
         ])
         .split(area);
 
+    if app.disconnected {
+        let banner = Paragraph::new(format!("{} Нет связи с сервером — переподключение…", app.symbols.warning))
+            .style(Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD));
+        f.render_widget(banner, chunks[0]);
+    }
+
     let main_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Length(30),
+            Constraint::Length(if app.sidebar_hidden { 0 } else { 30 }),
             Constraint::Min(0),
         ])
-        .split(chunks[0]);
+        .split(chunks[1]);
 
-    draw_chat_list(f, app, main_chunks[0]);
+    if !app.sidebar_hidden {
+        draw_chat_list(f, app, main_chunks[0]);
+    }
     draw_messages(f, app, main_chunks[1]);
-    draw_status_bar(f, app, chunks[1]);
+    draw_status_bar(f, app, chunks[2]);
 }
 
 fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
-    let title = app.get_current_chat_title();
+    let title = if app.sidebar_hidden {
+        // Напоминание, что список чатов свернут
+        format!("{} Ctrl+B {} {}", app.symbols.sidebar_hidden, app.symbols.separator, app.get_current_chat_title())
+    } else {
+        app.get_current_chat_title()
+    };
 
     let inner_area = Rect {
         x: area.x + 1,
@@ -129,17 +883,20 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
         height: inner_area.height.min(area.height.saturating_sub(2)),
     };
 
-    let message_height = 1; // базовая высота для сообщения
-    let image_height = 12; // высота для изображения
-    let sticker_height = 8; // высота для стикера
-    let voice_height = 3; // увеличена высота для голосового сообщения с плеером
-    let audio_height = 3; // увеличена высота для аудио сообщения с плеером
+    // Сообщаем приложению реальную видимую емкость области сообщений
+    app.set_actual_visible_capacity((inner_area.height as usize).max(1));
+    // Список чатов оставил ленте слишком мало места (узкое окно без Ctrl+B)
+    if inner_area.width < MIN_MESSAGES_WIDTH || inner_area.height == 0 {
+        f.render_widget(Block::default().borders(Borders::ALL).border_set(app.symbols.border), area);
+        draw_too_small(f, inner_area);
+        return;
+    }
+    reset_inline_image_budget(app.config.max_inline_images);
+
     let date_header_height = 1; // высота для заголовка даты
+    let spacing = app.config.message_spacing.min(1) as usize; // пустые строки между сообщениями
 
-    let picker = match Picker::from_query_stdio() {
-        Ok(p) => Some(p),
-        Err(_) => None,
-    };
+    let picker = terminal_picker();
 
     // Умная логика прокрутки с учетом изображений и стикеров - АДАПТИРОВАНА ДЛЯ ЗАГОЛОВКОВ ДАТ
     let mut start_index = 0;
@@ -229,6 +986,22 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
 
         // Убеждаемся, что не выходим за границы
         start_index = start_index.min(app.messages.len().saturating_sub(1));
+
+        // Многострочные сообщения могут вытеснить выбранное за нижний край - сдвигаем начало
+        let selected = app.selected_message_index;
+        start_index = start_index.min(selected);
+        let mut rows: usize = (start_index..=selected)
+            .map(|i| message_row_height(app, &app.messages[i], app.focus_on_messages && i == selected, inner_area.width) as usize + spacing)
+            .sum();
+        while start_index < selected && rows > visible_height {
+            rows -= message_row_height(app, &app.messages[start_index], false, inner_area.width) as usize + spacing;
+            start_index += 1;
+        }
+    }
+
+    // При наборе лента может быть пролистана вверх независимо от выделения
+    if app.state == AppState::MessageInput {
+        start_index = start_index.saturating_sub(app.input_scroll_back);
     }
 
     let mut y_offset = 0i32;
@@ -255,7 +1028,6 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
     }
 
     // Группируем сообщения по датам для отображения заголовков
-    let mut current_date = String::new();
     let mut previous_date = String::new();
 
     // Начинаем с рассчитанного индекса
@@ -263,18 +1035,10 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
     while index < app.messages.len() && y_offset < available_height {
         let msg = &app.messages[index];
         let is_selected = app.focus_on_messages && index == app.selected_message_index;
-        let current_height = if msg.r#type == "photo" || msg.r#type == "video" {
-            if is_selected { image_height } else { message_height }
-        } else if msg.r#type == "sticker" {
-            if is_selected { sticker_height } else { message_height }
-        } else if msg.r#type == "voice" {
-            if is_selected { voice_height } else { message_height }
-        } else if msg.r#type == "audio" {
-            if is_selected { audio_height } else { message_height }
-        } else { message_height };
+        let current_height = message_row_height(app, msg, is_selected, inner_area.width);
 
         // Получаем дату текущего сообщения
-        current_date = extract_date_from_timestamp(&msg.timestamp);
+        let current_date = extract_date_from_timestamp(&msg.timestamp);
 
         // Проверяем, изменилась ли дата по сравнению с предыдущим сообщением
         if current_date != previous_date && !previous_date.is_empty() {
@@ -321,31 +1085,40 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
             height: message_area.height.min(frame_area.height.saturating_sub(message_area.y)),
         };
 
-        let time = format_time_for_metadata(&msg.timestamp);
+        // Время выводится вместе с разделяющим пробелом, чтобы при скрытии не оставался отступ
+        let time = if app.show_timestamps {
+            format!("{} ", format_time_for_metadata(&msg.timestamp))
+        } else {
+            String::new()
+        };
+        let sender = sender_name(app, msg);
 
         match msg.r#type.as_str() {
             "sticker" => {
                 if is_selected {
-                    draw_sticker_message(f, msg, message_area, &time, picker.as_ref());
+                    draw_sticker_message(f, msg, message_area, &time, picker, app);
                 } else {
                     let sticker_text = if let Some(emoji) = &msg.sticker_emoji {
                         format!("{} [стикер — Enter: открыть]", emoji)
                     } else {
-                        "[🏷️ Стикер — Enter: открыть]".to_string()
+                        format!("[{} Стикер — Enter: открыть]", app.symbols.sticker)
                     };
-                    let text_content = format!("{} {}: {}", time, msg.from, sticker_text);
+                    let text_content = format!("{}{}: {}", time, sender, sticker_text);
                     let text_widget = Paragraph::new(text_content)
                         .style(Style::default().fg(Color::Magenta))
                         .wrap(Wrap { trim: true });
                     f.render_widget(text_widget, message_area);
                 }
             }
+            "photo" | "video" if is_selected && msg.media_spoiler == Some(true) && app.spoilers_hidden(msg) => {
+                draw_media_spoiler(f, message_area, &time, &sender, app.symbols);
+            }
             "photo" => {
                 if is_selected {
-                    draw_photo_message(f, msg, message_area, &time, picker.as_ref(), is_selected);
+                    draw_photo_message(f, msg, message_area, &time, picker, app, is_selected);
                 } else {
-                    let label = "[📷 Фото — Enter: открыть]";
-                    let text_content = format!("{} {}: {}", time, msg.from, label);
+                    let label = format!("[{} Фото — Enter: открыть]", app.symbols.photo);
+                    let text_content = format!("{}{}: {}{}", time, sender, label, caption_suffix(msg));
                     let text_widget = Paragraph::new(text_content)
                         .style(Style::default().fg(Color::Cyan))
                         .wrap(Wrap { trim: true });
@@ -354,19 +1127,19 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
             }
             "video" => {
                 if is_selected {
-                    draw_video_message(f, msg, message_area, &time, picker.as_ref(), is_selected);
+                    draw_video_message(f, msg, message_area, &time, picker, app, is_selected);
                 } else {
                     // Для невыбранных сообщений используем разделенный формат
                     let content_text = if let Some(is_round) = msg.video_is_round {
                         if is_round {
-                            "[🔮 Круглое видео — Enter: открыть]"
+                            format!("[{} Круглое видео — Enter: открыть]", app.symbols.round_video)
                         } else {
-                            "[🎬 Видео — Enter: открыть]"
+                            format!("[{} Видео — Enter: открыть]", app.symbols.video)
                         }
                     } else {
-                        "[🎬 Видео — Enter: открыть]"
+                        format!("[{} Видео — Enter: открыть]", app.symbols.video)
                     };
-                    let text_content = format!("{} {}: {}", time, msg.from, content_text);
+                    let text_content = format!("{}{}: {}{}", time, sender, content_text, caption_suffix(msg));
                     let text_widget = Paragraph::new(text_content)
                         .style(Style::default().fg(Color::White))
                         .wrap(Wrap { trim: true });
@@ -382,8 +1155,8 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
                     } else {
                         "неизвестно".to_string()
                     };
-                    let label = format!("[🎤 Голосовое — {}]", duration_text);
-                    let text_content = format!("{} {}: {}", time, msg.from, label);
+                    let label = format!("[{} Голосовое — {}]", app.symbols.voice, duration_text);
+                    let text_content = format!("{}{}: {}", time, sender, label);
 
                     let text_widget = Paragraph::new(text_content)
                         .style(Style::default().fg(Color::White))
@@ -410,8 +1183,8 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
                     } else {
                         "Аудио".to_string()
                     };
-                    let label = format!("[🎵 {} — {}]", title_text, duration_text);
-                    let text_content = format!("{} {}: {}", time, msg.from, label);
+                    let label = format!("[{} {} — {}]", app.symbols.audio, title_text, duration_text);
+                    let text_content = format!("{}{}: {}", time, sender, label);
 
                     let text_widget = Paragraph::new(text_content)
                         .style(Style::default().fg(Color::White))
@@ -421,7 +1194,7 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
             }
             "location" => {
                 if is_selected {
-                    draw_location_message(f, msg, message_area, &time, is_selected);
+                    draw_location_message(f, msg, message_area, &time, &sender, is_selected, app.symbols);
                 } else {
                     let location_info = if let (Some(lat), Some(lng)) = (msg.location_lat, msg.location_lng) {
                         format!("{:.4}, {:.4}", lat, lng)
@@ -435,8 +1208,8 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
                         String::new()
                     };
 
-                    let label = format!("[📍 Местоположение{}]", title_info);
-                    let text_content = format!("{} {}: {} {}", time, msg.from, label, location_info);
+                    let label = format!("[{} Местоположение{}]", app.symbols.location, title_info);
+                    let text_content = format!("{}{}: {} {}", time, sender, label, location_info);
 
                     let text_widget = Paragraph::new(text_content)
                         .style(Style::default().fg(Color::Green))
@@ -444,11 +1217,50 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
                     f.render_widget(text_widget, message_area);
                 }
             }
+            t if is_service_type(t) => {
+                // Служебные сообщения выводим по центру, приглушенно и без отправителя
+                let text = if msg.text.is_empty() { "служебное сообщение" } else { msg.text.as_str() };
+                let service_widget = Paragraph::new(text)
+                    .style(Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC))
+                    .alignment(ratatui::layout::Alignment::Center);
+                f.render_widget(service_widget, message_area);
+            }
             _ => {
-                let text_content = format!("{} {}: {}", time, msg.from, msg.text);
-                let text_widget = Paragraph::new(text_content)
-                    .style(Style::default())
-                    .wrap(Wrap { trim: true });
+                // Неотправленные сообщения приглушаем
+                let text_style = match msg.send_state {
+                    Some(SendState::Sending) => Style::default().fg(Color::DarkGray),
+                    _ => Style::default(),
+                };
+                let text_width = if is_selected { message_area.width.saturating_sub(2) } else { message_area.width };
+                let quote_lines = reply_quote_lines(app, msg, text_width);
+                let quote_rows = quote_lines.len() as u16;
+                let text_lines = text_message_lines(app, msg, text_width, is_selected);
+                let mut lines: Vec<Line> = quote_lines
+                    .into_iter()
+                    .map(|line| Line::from(line).style(Style::default().fg(Color::DarkGray)))
+                    .collect();
+                lines.extend(text_lines
+                    .into_iter()
+                    .map(|line| {
+                        if line.starts_with(COLLAPSED_MARK) {
+                            Line::from(line).style(Style::default().fg(Color::DarkGray))
+                        } else if let Some(term) = app.active_search_term() {
+                            highlight_matches(line, term)
+                        } else {
+                            Line::from(line)
+                        }
+                    }));
+                lines.extend(translation_lines(app, msg, text_width)
+                    .into_iter()
+                    .map(|line| Line::from(line).style(Style::default().fg(Color::Cyan))));
+                if let Some(reactions) = reactions_line(msg, text_width) {
+                    lines.push(reactions);
+                }
+                let text_rows = lines.len() as u16;
+                // Порядок символов внутри строки оставляем терминалу, сами только выравниваем
+                let rtl = is_rtl_message(app, msg);
+                let alignment = if rtl { ratatui::layout::Alignment::Right } else { ratatui::layout::Alignment::Left };
+                let text_widget = Paragraph::new(lines).style(text_style).alignment(alignment);
                 if is_selected {
                     let inner_area = Rect {
                         x: message_area.x + 2,
@@ -467,12 +1279,35 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
                 } else {
                     f.render_widget(text_widget, message_area);
                 }
+
+                if has_webpage_preview(msg) && message_area.height > text_rows {
+                    let card_area = Rect {
+                        x: message_area.x + 2,
+                        y: message_area.y + text_rows,
+                        width: message_area.width.saturating_sub(2),
+                        height: message_area.height - text_rows,
+                    };
+                    draw_webpage_preview(f, msg, card_area, picker, is_selected, app.symbols);
+                }
+
+                // Позиции кастомных эмодзи считаются от левого края
+                if app.config.custom_emoji_images && !rtl {
+                    let text_x = if is_selected { message_area.x + 2 } else { message_area.x };
+                    let prefix = format!("{}{}: {}{}", time, sender, reply_mark(msg, app.symbols), forward_mark(msg, is_selected));
+                    // Эмодзи стоят в первой строке текста, под цитатой
+                    let text_area = Rect {
+                        y: message_area.y + quote_rows,
+                        height: message_area.height.saturating_sub(quote_rows),
+                        ..message_area
+                    };
+                    draw_custom_emoji(f, app, msg, &prefix, text_x, text_area, is_selected);
+                }
             }
         }
 
         // Индикатор выбора (как в списке чатов) - размещаем на строке с метаданными
         if is_selected {
-            let indicator_text = "▶ ";
+            let indicator_text = app.symbols.selected;
             let indicator = Paragraph::new(indicator_text)
                 .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
 
@@ -488,10 +1323,23 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
             f.render_widget(indicator, indicator_area);
         }
 
+        // Отметка для пакетных операций - в правом краю строки с метаданными
+        if app.marked_messages.contains(&msg.id) && message_area.width > 2 {
+            let mark_area = Rect {
+                x: message_area.x + message_area.width - 2,
+                y: message_area.y,
+                width: 2,
+                height: 1,
+            };
+            let mark = Paragraph::new(app.symbols.marked)
+                .style(Style::default().fg(Color::Green).add_modifier(Modifier::BOLD));
+            f.render_widget(mark, mark_area);
+        }
+
         // Сохраняем текущую дату для следующего сравнения
-        previous_date = current_date.clone();
+        previous_date = current_date;
 
-        y_offset += current_height as i32;
+        y_offset += (current_height as usize + spacing) as i32;
         index += 1;
     }
 
@@ -499,19 +1347,158 @@ fn draw_messages(f: &mut Frame, app: &mut App, area: Rect) {
     let messages_block = Block::default()
         .title(title)
         .borders(Borders::ALL)
+        .border_set(app.symbols.border)
         .style(Style::default());
     f.render_widget(messages_block, area);
+
+    if app.messages.is_empty() {
+        let hint = if app.selected_chat.is_some() {
+            "Нет сообщений — начните разговор (i)"
+        } else {
+            "Выберите чат в списке (Enter)"
+        };
+        draw_empty_state(f, inner_area, hint);
+    }
+}
+
+// Подсказка по центру пустой панели; в узкой панели переносится вниз от середины
+fn draw_empty_state(f: &mut Frame, area: Rect, text: &str) {
+    let middle = area.height / 2;
+    let rows = Rect { y: area.y + middle, height: area.height - middle, ..area };
+    let hint = Paragraph::new(text)
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(hint, rows);
+}
+
+// Рисует картинки кастомных эмодзи поверх их текстовых заменителей.
+// Позиции считаются только для первой строки без переноса, иначе остаются обычные эмодзи.
+fn draw_custom_emoji(f: &mut Frame, app: &App, msg: &crate::Message, prefix: &str, text_x: u16, area: Rect, is_selected: bool) {
+    // Эмодзи в пару клеток полублоками не различить - остаются текстовые заменители
+    if half_block_images() {
+        return;
+    }
+    let (Some(emojis), Some(picker)) = (&msg.custom_emoji, terminal_picker()) else {
+        return;
+    };
+    let hide_spoilers = app.spoilers_hidden(msg);
+
+    let first_line = msg.text.split('\n').next().unwrap_or_default();
+    let prefix_width = Line::from(prefix).width() as u16;
+    let right_edge = area.x.saturating_add(area.width);
+    if text_x.saturating_add(prefix_width).saturating_add(Line::from(first_line).width() as u16) > right_edge {
+        return;
+    }
+
+    for emoji in emojis {
+        let Some(path) = &emoji.path else {
+            continue;
+        };
+        if hide_spoilers && in_spoiler(msg, emoji.offset) {
+            continue;
+        }
+        let Some(byte_offset) = utf16_to_byte_offset(&msg.text, emoji.offset) else {
+            continue;
+        };
+        if byte_offset > first_line.len() {
+            break;
+        }
+
+        // Сверх лимита картинок на кадр остаются обычные эмодзи-заменители; выбранное сообщение рисуется всегда
+        if !is_selected && !take_inline_image_slot() {
+            break;
+        }
+        let x = text_x + prefix_width + Line::from(&msg.text[..byte_offset]).width() as u16;
+        let emoji_area = Rect { x, y: area.y, width: 2, height: 1 };
+        let _ = render_cached_image(f, path, emoji_area, picker, false, false);
+    }
+}
+
+fn in_spoiler(msg: &crate::Message, utf16_offset: usize) -> bool {
+    msg.spoilers
+        .iter()
+        .flatten()
+        .any(|range| utf16_offset >= range.offset && utf16_offset < range.offset + range.length)
+}
+
+// Закрываем символы спойлеров блоками, сохраняя ширину строки
+fn mask_spoilers(msg: &crate::Message, fill: char) -> String {
+    let mut units = 0;
+    let mut masked = String::with_capacity(msg.text.len());
+    for ch in msg.text.chars() {
+        if in_spoiler(msg, units) && ch != '\n' {
+            let width = Line::from(ch.to_string()).width().max(1);
+            masked.extend(std::iter::repeat_n(fill, width));
+        } else {
+            masked.push(ch);
+        }
+        units += ch.len_utf16();
+    }
+    masked
+}
+
+// Переводит смещение в UTF-16 единицах (формат Telegram) в байтовое смещение строки
+fn utf16_to_byte_offset(text: &str, utf16_offset: usize) -> Option<usize> {
+    let mut units = 0;
+    for (byte_index, ch) in text.char_indices() {
+        if units == utf16_offset {
+            return Some(byte_index);
+        }
+        units += ch.len_utf16();
+    }
+    (units == utf16_offset).then_some(text.len())
+}
+
+fn chat_placeholder(app: &App, chat: &crate::Chat, name: &str) -> Option<String> {
+    let value = match name {
+        "unread" if chat.unread > 0 => format!("({}) ", chat.unread),
+        "unread" => String::new(),
+        "title" => chat.title.clone(),
+        // Только первая строка: строка списка не переносится
+        "preview" => chat.last_message.as_deref().and_then(|m| m.lines().next()).unwrap_or("").to_string(),
+        "time" => chat.last_message_time.as_deref().map(format_chat_time).unwrap_or_default(),
+        "mute" if chat.muted == Some(true) => format!("{} ", app.symbols.muted),
+        "mute" => String::new(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+// Время последнего сообщения в списке чатов: "ЧЧ:ММ" за сегодня, иначе "ДД.ММ"
+fn format_chat_time(timestamp: &str) -> String {
+    match chrono::DateTime::parse_from_rfc3339(timestamp) {
+        Ok(at) => {
+            let at = at.with_timezone(&chrono::Local);
+            if at.date_naive() == chrono::Local::now().date_naive() {
+                at.format("%H:%M").to_string()
+            } else {
+                at.format("%d.%m").to_string()
+            }
+        }
+        Err(_) => String::new(),
+    }
 }
 
 fn draw_chat_list(f: &mut Frame, app: &App, area: Rect) {
-    let items: Vec<ListItem> = app.chats
+    let visible = app.visible_chat_indices();
+    let items: Vec<ListItem> = visible
         .iter()
-        .enumerate()
-        .map(|(i, chat)| {
-            let mut text = chat.title.clone();
-            if chat.unread > 0 {
-                text = format!("({}) {}", chat.unread, text);
+        .map(|&i| {
+            let chat = &app.chats[i];
+            let mut spans = Vec::new();
+            if app.is_chat_pinned(chat.id) {
+                spans.push(Span::raw(format!("{} ", app.symbols.pinned)));
+            }
+            // Упоминания важнее остальных непрочитанных, поэтому отдельным цветным значком
+            let mentions = chat.unread_mentions.unwrap_or(0);
+            if mentions > 0 {
+                spans.push(Span::styled(
+                    format!("{}{} ", app.symbols.mention, mentions),
+                    Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+                ));
             }
+            spans.push(Span::raw(expand_template(&app.config.chat_list_format, |name| chat_placeholder(app, chat, name))));
 
             let mut style = if i == app.selected_chat_index {
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
@@ -522,21 +1509,43 @@ fn draw_chat_list(f: &mut Frame, app: &App, area: Rect) {
                 style = style.bg(Color::Blue);
             }
 
-            ListItem::new(text).style(style)
+            ListItem::new(Line::from(spans)).style(style)
         })
         .collect();
 
+    let title = if app.chat_filter.is_empty() {
+        "Чаты".to_string()
+    } else {
+        format!("Чаты /{} ({}/{})", app.chat_filter, visible.len(), app.chats.len())
+    };
     let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Чаты"))
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title(title))
         .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
-        .highlight_symbol("▶ ");
+        .highlight_symbol(app.symbols.selected);
 
     let mut state = ListState::default();
-    state.select(Some(app.selected_chat_index));
+    state.select(visible.iter().position(|&i| i == app.selected_chat_index));
     f.render_stateful_widget(list, area, &mut state);
+
+    if visible.is_empty() {
+        let inner = Rect {
+            x: area.x + 1,
+            y: area.y + 1,
+            width: area.width.saturating_sub(2),
+            height: area.height.saturating_sub(2),
+        };
+        let hint = if app.chats.is_empty() {
+            "Нет чатов — нажмите r для обновления"
+        } else {
+            "Нет чатов по фильтру — Esc: показать все"
+        };
+        draw_empty_state(f, inner, hint);
+    }
 }
 
-fn draw_photo_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, picker: Option<&Picker>, is_selected: bool) {
+fn draw_photo_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, picker: Option<&Picker>, app: &App, is_selected: bool) {
+    let sender = sender_name(app, msg);
+    let align = app.config.media_align;
     let inner_area = Rect {
         x: area.x + 2,
         y: area.y,
@@ -549,9 +1558,9 @@ fn draw_photo_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &st
         // Метаданные на первой строке - выделяем желтым только при выборе
         let metadata_color = if is_selected { Color::Yellow } else { Color::White };
         let mut photo_lines = vec![
-        Line::from(format!("{} {}:", time, msg.from)).style(Style::default().fg(metadata_color)),
+        Line::from(format!("{}{}:", time, sender)).style(Style::default().fg(metadata_color)),
         ];
-        photo_lines.push(Line::from(format!("📷 Фото")).style(Style::default().fg(Color::Red)));
+        photo_lines.push(Line::from(format!("{} Фото", app.symbols.photo)).style(Style::default().fg(Color::Red)));
 
         let content_widget = Paragraph::new(photo_lines)
             .style(Style::default().fg(Color::Cyan));
@@ -568,28 +1577,27 @@ fn draw_photo_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &st
 
         if let Some(image_path) = &msg.image_path {
             if let Some(picker) = picker {
-                match try_display_image(image_path, picker, image_area) {
-                    Ok(mut protocol) => {
-                        let image_widget = StatefulImage::new();
-                        f.render_stateful_widget(image_widget, image_area, &mut protocol);
-                    }
+                match render_cached_image(f, image_path, align_media_area(image_area, align, time), picker, false, align == MediaAlign::Center) {
+                    Ok(()) => {}
                     Err(e) => {
-                        let error_text = format!("[📷 Ошибка: {}]", e);
+                        let error_text = format!("[{} Ошибка: {}]", app.symbols.photo, e);
                         let error_widget = Paragraph::new(error_text)
                             .style(Style::default().fg(Color::Red));
                         f.render_widget(error_widget, image_area);
                     }
                 }
             } else {
-                let placeholder = Paragraph::new("[📷 Терминал не поддерживает изображения]")
+                let placeholder = Paragraph::new(format!("[{} Терминал не поддерживает изображения]", app.symbols.photo))
                     .style(Style::default().fg(Color::Yellow));
                 f.render_widget(placeholder, image_area);
             }
         } else {
-            let placeholder = Paragraph::new("[📷 Загрузка...]")
+            let placeholder = Paragraph::new(format!("[{} Загрузка...]", app.symbols.photo))
                 .style(Style::default().fg(Color::Blue));
             f.render_widget(placeholder, image_area);
         }
+
+        draw_media_caption(f, msg, inner_area);
     } else {
         // Если нет места для текста, показываем только изображение
         let image_area = Rect {
@@ -601,25 +1609,22 @@ fn draw_photo_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &st
 
         if let Some(image_path) = &msg.image_path {
             if let Some(picker) = picker {
-                match try_display_image(image_path, picker, image_area) {
-                    Ok(mut protocol) => {
-                        let image_widget = StatefulImage::new();
-                        f.render_stateful_widget(image_widget, image_area, &mut protocol);
-                    }
+                match render_cached_image(f, image_path, align_media_area(image_area, align, time), picker, false, align == MediaAlign::Center) {
+                    Ok(()) => {}
                     Err(e) => {
-                        let error_text = format!("[📷 Ошибка: {}]", e);
+                        let error_text = format!("[{} Ошибка: {}]", app.symbols.photo, e);
                         let error_widget = Paragraph::new(error_text)
                             .style(Style::default().fg(Color::Red));
                         f.render_widget(error_widget, image_area);
                     }
                 }
             } else {
-                let placeholder = Paragraph::new("[📷 Терминал не поддерживает изображения]")
+                let placeholder = Paragraph::new(format!("[{} Терминал не поддерживает изображения]", app.symbols.photo))
                     .style(Style::default().fg(Color::Yellow));
                 f.render_widget(placeholder, image_area);
             }
         } else {
-            let placeholder = Paragraph::new("[📷 Загрузка...]")
+            let placeholder = Paragraph::new(format!("[{} Загрузка...]", app.symbols.photo))
                 .style(Style::default().fg(Color::Blue));
             f.render_widget(placeholder, image_area);
         }
@@ -629,7 +1634,8 @@ fn draw_photo_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &st
     f.render_widget(message_block, area);
 }
 
-fn draw_voice_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, audio_player: &crate::app::AudioPlayer, _app: &crate::App, is_selected: bool) {
+fn draw_voice_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, audio_player: &crate::app::AudioPlayer, app: &crate::App, is_selected: bool) {
+    let sender = sender_name(app, msg);
     let inner_area = Rect {
         x: area.x + 2,
         y: area.y,
@@ -651,18 +1657,29 @@ fn draw_voice_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &st
     // Метаданные на первой строке - выделяем желтым только при выборе
     let metadata_color = if is_selected { Color::Yellow } else { Color::White };
     let mut voice_lines = vec![
-        Line::from(format!("{} {}:", time, msg.from)).style(Style::default().fg(metadata_color)),
+        Line::from(format!("{}{}:", time, sender)).style(Style::default().fg(metadata_color)),
     ];
     // Контент на отдельной строке
-    voice_lines.push(Line::from(format!("🎤 Голосовое сообщение — {}", duration_display)).style(Style::default().fg(Color::Red)));
+    voice_lines.push(Line::from(format!("{} Голосовое сообщение — {}", app.symbols.voice, duration_display)).style(Style::default().fg(Color::Red)));
+    // Форма волны; проигранная часть подсвечивается
+    if let Some(waveform) = msg.voice_waveform.as_deref().filter(|w| !w.is_empty()) {
+        let progress = match audio_player.total_duration {
+            Some(total) if is_current && !total.is_zero() => {
+                (audio_player.current_position.as_secs_f64() / total.as_secs_f64()).min(1.0)
+            }
+            _ => 0.0,
+        };
+        let width = inner_area.width.saturating_sub(2).min(WAVEFORM_MAX_WIDTH) as usize;
+        voice_lines.push(waveform_line(waveform, width, progress, app.symbols.waveform));
+    }
     // Добавляем строку с элементами управления
     if is_current {
         let time_display = audio_player.get_current_time_display();
-        let play_pause = if audio_player.is_playing { "⏸" } else { "▶" };
-        let controls_line = format!("{} | {} | h: -2s | k: +2s | Esc: ✗", time_display, play_pause);
+        let play_pause = if audio_player.is_playing { app.symbols.pause } else { app.symbols.play };
+        let controls_line = format!("{} | {} | h: -2s | l: +2s | Esc: {}", time_display, play_pause, app.symbols.close);
         voice_lines.push(Line::from(controls_line).style(Style::default().fg(Color::Green)));
     } else {
-        voice_lines.push(Line::from("Enter: ▶  Esc: ✗").style(Style::default().fg(Color::Gray)));
+        voice_lines.push(Line::from(format!("Enter: {}  Esc: {}", app.symbols.play, app.symbols.close)).style(Style::default().fg(Color::Gray)));
     }
 
     let voice_widget = Paragraph::new(voice_lines)
@@ -671,7 +1688,32 @@ fn draw_voice_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &st
     f.render_widget(voice_widget, inner_area);
 }
 
-fn draw_audio_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, audio_player: &crate::app::AudioPlayer, _app: &crate::App, is_selected: bool) {
+const WAVEFORM_MAX_WIDTH: u16 = 48;
+
+fn has_waveform(msg: &crate::Message) -> bool {
+    msg.voice_waveform.as_ref().is_some_and(|w| !w.is_empty())
+}
+
+// Сжимаем отсчеты до ширины столбиков (берем максимум в каждом отрезке) и переводим в высоты
+fn waveform_line(waveform: &[u8], width: usize, progress: f64, bars: &[&'static str]) -> Line<'static> {
+    let width = width.min(waveform.len()).max(1);
+    let peak = waveform.iter().copied().max().unwrap_or(0).max(1) as usize;
+    let played = (progress * width as f64).round() as usize;
+    let spans: Vec<Span> = (0..width)
+        .map(|column| {
+            let start = column * waveform.len() / width;
+            let end = ((column + 1) * waveform.len() / width).max(start + 1);
+            let value = waveform[start..end].iter().copied().max().unwrap_or(0) as usize;
+            let bar = bars[value * (bars.len() - 1) / peak];
+            let color = if column < played { Color::Green } else { Color::DarkGray };
+            Span::styled(bar, Style::default().fg(color))
+        })
+        .collect();
+    Line::from(spans)
+}
+
+fn draw_audio_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, audio_player: &crate::app::AudioPlayer, app: &crate::App, is_selected: bool) {
+    let sender = sender_name(app, msg);
     let inner_area = Rect {
         x: area.x + 2,
         y: area.y,
@@ -703,18 +1745,18 @@ fn draw_audio_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &st
     // Метаданные на первой строке - выделяем желтым только при выборе
     let metadata_color = if is_selected { Color::Yellow } else { Color::White };
     let mut audio_lines = vec![
-        Line::from(format!("{} {}:", time, msg.from)).style(Style::default().fg(metadata_color)),
+        Line::from(format!("{}{}:", time, sender)).style(Style::default().fg(metadata_color)),
     ];
     // Контент на отдельной строке
-    audio_lines.push(Line::from(format!("🎵 {} — {}", title_text, duration_display)).style(Style::default().fg(Color::Blue)));
+    audio_lines.push(Line::from(format!("{} {} — {}", app.symbols.audio, title_text, duration_display)).style(Style::default().fg(Color::Blue)));
     // Добавляем строку с временем и элементами управления
     if is_current {
         let time_display = audio_player.get_current_time_display();
-        let play_pause = if audio_player.is_playing { "⏸" } else { "▶" };
-        let controls_line = format!("{} | {} | h: -2s | k: +2s | Esc: ✗", time_display, play_pause);
+        let play_pause = if audio_player.is_playing { app.symbols.pause } else { app.symbols.play };
+        let controls_line = format!("{} | {} | h: -2s | l: +2s | Esc: {}", time_display, play_pause, app.symbols.close);
         audio_lines.push(Line::from(controls_line).style(Style::default().fg(Color::Green)));
     } else {
-        audio_lines.push(Line::from("Enter: ▶  Esc: ✗").style(Style::default().fg(Color::Gray)));
+        audio_lines.push(Line::from(format!("Enter: {}  Esc: {}", app.symbols.play, app.symbols.close)).style(Style::default().fg(Color::Gray)));
     }
 
     let audio_widget = Paragraph::new(audio_lines)
@@ -734,12 +1776,9 @@ fn draw_video_preview(f: &mut Frame, app: &App) {
     // Рисуем превью видео, если путь есть
     if let Some(preview_path) = &app.preview_video_path {
         let inner = Rect { x: area.x + 1, y: area.y + 1, width: area.width.saturating_sub(2), height: area.height.saturating_sub(4) };
-        if let Ok(picker) = Picker::from_query_stdio() {
-            match try_display_image_full(preview_path, &picker) {
-                Ok(mut protocol) => {
-                    let widget = StatefulImage::new();
-                    f.render_stateful_widget(widget, inner, &mut protocol);
-                }
+        if let Some(picker) = terminal_picker() {
+            match render_cached_image(f, preview_path, inner, picker, true, false) {
+                Ok(()) => {}
                 Err(e) => {
                     let text = Paragraph::new(format!("Не удалось отобразить превью видео: {}", e))
                         .style(Style::default().fg(Color::Red))
@@ -758,11 +1797,135 @@ fn draw_video_preview(f: &mut Frame, app: &App) {
     // Нижняя подсказка
     let hint = Paragraph::new("Enter: воспроизвести в mpv | Esc: назад")
         .style(Style::default().fg(Color::Gray))
-        .block(Block::default().borders(Borders::ALL).title("Превью видео"));
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("Превью видео"));
     let hint_area = Rect { x: area.x + 2, y: area.y + area.height.saturating_sub(3), width: area.width.saturating_sub(4), height: 3 };
     f.render_widget(hint, hint_area);
 }
 
+// Сколько декодированных изображений держать в памяти
+const IMAGE_CACHE_LIMIT: usize = 64;
+
+thread_local! {
+    // Picker опрашивает терминал, поэтому создается один раз, а не на каждом кадре; заново - по Ctrl+R
+    static PICKER: Cell<Option<Option<&'static Picker>>> = const { Cell::new(None) };
+    // Picker с протоколом полублоков для режима Ctrl+A
+    static HALF_BLOCK_PICKER: Cell<Option<&'static Picker>> = const { Cell::new(None) };
+    static HALF_BLOCK_IMAGES: Cell<bool> = const { Cell::new(false) };
+    // Готовые протоколы изображений: ключ включает размер файла, чтобы докачанный файл перечитывался
+    static IMAGE_PROTOCOLS: RefCell<HashMap<String, Result<StatefulProtocol, String>>> =
+        RefCell::new(HashMap::new());
+    // Сколько еще встроенных картинок можно нарисовать в текущем кадре (max_inline_images)
+    static INLINE_IMAGES_LEFT: Cell<usize> = const { Cell::new(usize::MAX) };
+    // Существующие файлы, которые не удалось разобрать как картинку; их забирает App (on_corrupt_media)
+    static CORRUPT_MEDIA: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn take_corrupt_media() -> Vec<String> {
+    CORRUPT_MEDIA.with(|paths| std::mem::take(&mut *paths.borrow_mut()))
+}
+
+fn reset_inline_image_budget(limit: usize) {
+    INLINE_IMAGES_LEFT.with(|left| left.set(limit));
+}
+
+fn take_inline_image_slot() -> bool {
+    INLINE_IMAGES_LEFT.with(|left| match left.get() {
+        0 => false,
+        n => {
+            left.set(n - 1);
+            true
+        }
+    })
+}
+
+fn half_block_images() -> bool {
+    HALF_BLOCK_IMAGES.with(|enabled| enabled.get())
+}
+
+// В режиме полублоков картинки рисуются и там, где опрос терминала не удался
+fn terminal_picker() -> Option<&'static Picker> {
+    let queried = PICKER.with(|picker| {
+        picker.get().unwrap_or_else(|| {
+            let queried = Picker::from_query_stdio().ok().map(|p| &*Box::leak(Box::new(p)));
+            picker.set(Some(queried));
+            queried
+        })
+    });
+    if !half_block_images() {
+        return queried;
+    }
+    Some(HALF_BLOCK_PICKER.with(|picker| {
+        picker.get().unwrap_or_else(|| {
+            // Размер шрифта берем у настоящего терминала, он нужен для пропорций картинки
+            let mut half_blocks = queried.cloned().unwrap_or_else(|| Picker::from_fontsize((8, 16)));
+            half_blocks.set_protocol_type(ProtocolType::Halfblocks);
+            let half_blocks: &'static Picker = Box::leak(Box::new(half_blocks));
+            picker.set(Some(half_blocks));
+            half_blocks
+        })
+    }))
+}
+
+// Ctrl+R: терминал опрашивается заново на следующем кадре, картинки декодируются заново.
+// Старые Picker остаются в памяти - это пара сотен байт на перезагрузку
+pub fn reset_image_rendering() {
+    PICKER.with(|picker| picker.set(None));
+    HALF_BLOCK_PICKER.with(|picker| picker.set(None));
+    clear_image_cache();
+}
+
+// Сбрасываем кэш изображений (например, при смене чата)
+pub fn clear_image_cache() {
+    IMAGE_PROTOCOLS.with(|cache| cache.borrow_mut().clear());
+}
+
+// Рисует изображение, декодируя файл только при первом показе
+fn render_cached_image(f: &mut Frame, path: &str, area: Rect, picker: &Picker, full: bool, centered: bool) -> Result<(), String> {
+    let file_size = std::fs::metadata(path).map(|m| m.len()).ok();
+    let key = format!("{}|{:?}|{}|{:?}", if full { "full" } else { "inline" }, picker.protocol_type(), path, file_size);
+
+    IMAGE_PROTOCOLS.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if !cache.contains_key(&key) {
+            let loaded = if full {
+                try_display_image_full(path, picker)
+            } else {
+                try_display_image(path, picker, area)
+            };
+            // Отсутствующий файл может скоро появиться, такие ошибки не запоминаем
+            if file_size.is_none() {
+                if let Err(e) = loaded {
+                    return Err(e);
+                }
+            }
+            if let Err(e) = &loaded {
+                log::warn!("Поврежденный файл медиа: {}", e);
+                CORRUPT_MEDIA.with(|paths| paths.borrow_mut().push(path.to_string()));
+            }
+            if cache.len() >= IMAGE_CACHE_LIMIT {
+                cache.clear();
+            }
+            cache.insert(key.clone(), loaded);
+        }
+
+        match cache.get_mut(&key) {
+            Some(Ok(protocol)) => {
+                let area = if centered {
+                    // Картинка вписывается в область с сохранением пропорций, остаток делим поровну
+                    let size = protocol.size_for(Resize::Fit(None), area);
+                    Rect { x: area.x + area.width.saturating_sub(size.width) / 2, ..area }
+                } else {
+                    area
+                };
+                f.render_stateful_widget(StatefulImage::new(), area, protocol);
+                Ok(())
+            }
+            Some(Err(e)) => Err(e.clone()),
+            None => Err("изображение не загружено".to_string()),
+        }
+    })
+}
+
 fn try_display_image(image_path: &str, picker: &Picker, _area: Rect) -> Result<StatefulProtocol, String> {
     let actual_path = if std::path::Path::new(image_path).exists() {
         image_path.to_string()
@@ -790,15 +1953,12 @@ fn try_display_image(image_path: &str, picker: &Picker, _area: Rect) -> Result<S
     }
 
     // Проверяем, что файл не пустой и читаемый
-    let file = std::fs::File::open(&actual_path)
+    let _file = std::fs::File::open(&actual_path)
         .map_err(|e| format!("не удалось открыть файл: {}", e))?;
 
+    // Что делать с файлом, который не удалось декодировать, решает App по on_corrupt_media
     let dyn_img = image::open(&actual_path)
-        .map_err(|e| {
-            // Не удаляем файл автоматически при ошибке декодирования
-            // Даем пользователю возможность попробовать перезагрузить чат
-            format!("не удалось открыть изображение: {} (путь: {})", e, actual_path)
-        })?;
+        .map_err(|e| format!("не удалось открыть изображение: {} (путь: {})", e, actual_path))?;
 
     let protocol = picker.new_resize_protocol(dyn_img);
 
@@ -808,6 +1968,29 @@ fn try_display_image(image_path: &str, picker: &Picker, _area: Rect) -> Result<S
 fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
     let status_text = if app.state == AppState::MessageInput {
         format!("Сообщение: {}", app.message_input)
+    } else if app.state == AppState::SearchInput {
+        format!("Поиск: /{}", app.search_input)
+    } else if app.state == AppState::ChatFilterInput {
+        format!("Фильтр чатов: /{}", app.chat_filter)
+    } else if app.state == AppState::GotoInput {
+        format!("Перейти к сообщению #{}", app.goto_input)
+    } else if app.state == AppState::ExportInput {
+        format!("Экспорт медиа в папку: {}", app.export_input)
+    } else if app.state == AppState::ScheduleInput {
+        // Ошибка разбора времени показывается рядом с вводом до следующего нажатия
+        let error = app.notice.as_ref().map(|n| format!("  — {}", n)).unwrap_or_default();
+        format!("Отправить в (ЧЧ:ММ, ДД.ММ ЧЧ:ММ, +30m): {}{}", app.schedule_input, error)
+    } else if let Some(recording) = app.recording.as_ref().filter(|_| app.state == AppState::Main) {
+        let seconds = recording.elapsed().as_secs() as i32;
+        // Если открыт другой чат, напоминаем, куда уйдет запись
+        let target = if app.selected_chat.as_ref().map(|c| c.id) == Some(recording.chat_id) {
+            String::new()
+        } else {
+            format!(" в «{}»", app.chat_title(recording.chat_id))
+        };
+        format!("{} Запись голосового{} {} — V: отправить, Esc: отменить", app.symbols.recording, target, format_duration(seconds))
+    } else if let Some(notice) = &app.notice {
+        notice.clone()
     } else {
         app.get_status_text()
     };
@@ -815,11 +1998,70 @@ fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
     let color = match app.state {
         AppState::Error => Color::Red,
         AppState::MessageInput => Color::Green,
+        AppState::SearchInput => Color::Cyan,
+        AppState::ChatFilterInput => Color::Cyan,
+        AppState::ScheduleInput => Color::Magenta,
+        AppState::ExportInput => Color::Cyan,
+        AppState::GotoInput => Color::Cyan,
+        AppState::Main if app.recording.is_some() => Color::Red,
+        _ if app.notice.is_some() => Color::Yellow,
         _ => Color::Gray,
     };
 
+    // При наборе в заголовке показываем длину сообщения относительно лимита Telegram
+    let title = if app.state == AppState::MessageInput {
+        let length = crate::app::message_length(&app.message_input);
+        let limit = crate::app::MESSAGE_LENGTH_LIMIT;
+        let counter_color = if length >= limit {
+            Color::Red
+        } else if length * 10 >= limit * 9 {
+            Color::Yellow
+        } else {
+            Color::Gray
+        };
+        // Цель ответа (R) видна, пока набирается черновик
+        let reply = app.reply_target_label().map(|label| format!(" — {}", label)).unwrap_or_default();
+        Line::from(format!("Статус — {}/{}{}", length, limit, reply)).style(Style::default().fg(counter_color))
+    } else if let Some(account) = app.account_label() {
+        Line::from(format!("Статус — вы: {}", account))
+    } else {
+        Line::from("Статус")
+    };
+
+    let mut block = Block::default().borders(Borders::ALL).border_set(app.symbols.border).title(title);
+    if let Some((saved, failed, total)) = app.export_progress {
+        block = block.title(Line::from(format!(" экспорт: {}/{} ", saved + failed, total)).style(Style::default().fg(Color::Cyan)));
+    }
+    if matches!(app.state, AppState::Main | AppState::SearchInput) {
+        if let Some((position, total)) = app.search_matches() {
+            let label = match position {
+                Some(position) => format!(" совпадений: {} ({}/{}) ", total, position, total),
+                None => format!(" совпадений: {} ", total),
+            };
+            let color = if total == 0 { Color::Red } else { Color::Cyan };
+            block = block.title(Line::from(label).style(Style::default().fg(color)));
+        }
+    }
+    if app.state == AppState::Main {
+        if let Some(timestamp) = app.selected_message_timestamp() {
+            block = block.title(Line::from(format!(" {} ", timestamp)).right_aligned());
+        }
+    }
+
+    // Черновик переносим сами и прокручиваем так, чтобы его конец всегда был виден
+    if app.state == AppState::MessageInput {
+        let lines = wrap_text_lines(&status_text, area.width.saturating_sub(2) as usize);
+        let scroll = lines.len().saturating_sub(area.height.saturating_sub(2) as usize) as u16;
+        let status = Paragraph::new(lines.join("\n"))
+            .block(block)
+            .style(Style::default().fg(color))
+            .scroll((scroll, 0));
+        f.render_widget(status, area);
+        return;
+    }
+
     let status = Paragraph::new(status_text)
-        .block(Block::default().borders(Borders::ALL).title("Статус"))
+        .block(block)
         .style(Style::default().fg(color))
         .wrap(Wrap { trim: true });
 
@@ -839,7 +2081,7 @@ fn draw_error_screen(f: &mut Frame, app: &App) {
         .split(area);
 
     let title = Paragraph::new("Ошибка")
-        .block(Block::default().borders(Borders::ALL))
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border))
         .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD));
     f.render_widget(title, chunks[0]);
 
@@ -851,7 +2093,7 @@ fn draw_error_screen(f: &mut Frame, app: &App) {
     ];
 
     let error_msg = Paragraph::new(error_text)
-        .block(Block::default().borders(Borders::ALL).title("Подробности"))
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("Подробности"))
         .style(Style::default().fg(Color::Red))
         .wrap(Wrap { trim: true });
 
@@ -873,12 +2115,14 @@ fn draw_image_preview(f: &mut Frame, app: &App) {
     // Рисуем изображение, если путь есть
     if let Some(path) = &app.preview_image_path {
         let inner = Rect { x: area.x + 1, y: area.y + 1, width: area.width.saturating_sub(2), height: area.height.saturating_sub(4) };
-        if let Ok(picker) = Picker::from_query_stdio() {
-            match try_display_image_full(path, &picker) {
-                Ok(mut protocol) => {
-                    let widget = StatefulImage::new();
-                    f.render_stateful_widget(widget, inner, &mut protocol);
-                }
+        if app.is_downloading(path) && !std::path::Path::new(path).exists() {
+            let text = Paragraph::new("Загрузка... (Esc: отменить)")
+                .style(Style::default().fg(Color::Yellow))
+                .wrap(Wrap { trim: true });
+            f.render_widget(text, inner);
+        } else if let Some(picker) = terminal_picker() {
+            match render_cached_image(f, path, inner, picker, true, false) {
+                Ok(()) => {}
                 Err(e) => {
                     let text = Paragraph::new(format!("Не удалось отобразить изображение: {}", e))
                         .style(Style::default().fg(Color::Red))
@@ -895,22 +2139,15 @@ fn draw_image_preview(f: &mut Frame, app: &App) {
     }
 
     // Нижняя подсказка - зависит от типа превью
-    let (hint_text, title) = if let Some(video_path) = &app.preview_video_path {
-        if !video_path.is_empty() {
-            // Это видео превью
-            ("Enter: воспроизвести в mpv | Esc: назад", "Превью видео")
-        } else {
-            // Это обычное изображение
-            ("Esc/Enter: выйти из просмотра", "Просмотр изображения")
-        }
+    let (hint_text, title) = if app.preview_video().is_some() {
+        ("Enter: воспроизвести в mpv | Esc: назад", "Превью видео")
     } else {
-        // Это обычное изображение
         ("Esc/Enter: выйти из просмотра", "Просмотр изображения")
     };
 
     let hint = Paragraph::new(hint_text)
         .style(Style::default().fg(Color::Gray))
-        .block(Block::default().borders(Borders::ALL).title(title));
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title(title));
     let hint_area = Rect { x: area.x + 2, y: area.y + area.height.saturating_sub(3), width: area.width.saturating_sub(4), height: 3 };
     f.render_widget(hint, hint_area);
 }
@@ -940,7 +2177,7 @@ fn try_display_image_full(image_path: &str, picker: &Picker) -> Result<StatefulP
     }
 
     // Проверяем размер файла
-    let metadata = std::fs::metadata(&actual_path)
+    let metadata = std::fs::metadata(actual_path)
         .map_err(|e| format!("не удалось получить метаданные: {}", e))?;
 
     if metadata.len() < 100 {
@@ -948,11 +2185,11 @@ fn try_display_image_full(image_path: &str, picker: &Picker) -> Result<StatefulP
     }
 
     // Проверяем, что файл читаем
-    let _file = std::fs::File::open(&actual_path)
+    let _file = std::fs::File::open(actual_path)
         .map_err(|e| format!("не удалось открыть файл: {} (путь: {})", e, actual_path))?;
 
     // Пытаемся определить формат по первым байтам
-    if let Ok(header) = std::fs::read(&actual_path) {
+    if let Ok(header) = std::fs::read(actual_path) {
         if header.is_empty() || header.len() < 4 {
             return Err(format!("файл пустой или слишком мал для определения формата (путь: {})", actual_path));
         }
@@ -969,22 +2206,19 @@ fn try_display_image_full(image_path: &str, picker: &Picker) -> Result<StatefulP
         }
     }
 
-    let dyn_img = image::open(&actual_path)
-        .map_err(|e| {
-            // Не удаляем файл автоматически при ошибке декодирования
-            // Даем пользователю возможность попробовать перезагрузить чат
-            format!("не удалось открыть изображение: {} (путь: {})", e, actual_path)
-        })?;
+    let dyn_img = image::open(actual_path)
+        .map_err(|e| format!("не удалось открыть изображение: {} (путь: {})", e, actual_path))?;
 
     Ok(picker.new_resize_protocol(dyn_img))
 }
 
-fn draw_loading_screen(f: &mut Frame, _app: &mut App) {
+fn draw_loading_screen(f: &mut Frame, app: &mut App) {
     let area = f.area();
 
     let block = Block::default()
         .title("vi-tg")
         .borders(Borders::ALL)
+        .border_set(app.symbols.border)
         .style(Style::default());
 
     let text = vec![
@@ -1014,7 +2248,7 @@ fn draw_phone_input(f: &mut Frame, app: &App) {
         .split(area);
 
     let title = Paragraph::new("Авторизация в Telegram")
-        .block(Block::default().borders(Borders::ALL))
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border))
         .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
     f.render_widget(title, chunks[0]);
 
@@ -1034,7 +2268,7 @@ fn draw_phone_input(f: &mut Frame, app: &App) {
 
     let input_text = format!("Номер: {}", app.phone_input);
     let input = Paragraph::new(input_text)
-        .block(Block::default().borders(Borders::ALL).title("Ввод"))
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("Ввод"))
         .style(Style::default().fg(Color::Green));
     f.render_widget(input, main_chunks[1]);
 
@@ -1056,7 +2290,7 @@ fn draw_code_input(f: &mut Frame, app: &App) {
         .split(area);
 
     let title = Paragraph::new("Код подтверждения")
-        .block(Block::default().borders(Borders::ALL))
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border))
         .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
     f.render_widget(title, chunks[0]);
 
@@ -1076,7 +2310,7 @@ fn draw_code_input(f: &mut Frame, app: &App) {
 
     let input_text = format!("Код: {} ({})", app.code_input, app.code_input.len());
     let input = Paragraph::new(input_text)
-        .block(Block::default().borders(Borders::ALL).title("Ввод"))
+        .block(Block::default().borders(Borders::ALL).border_set(app.symbols.border).title("Ввод"))
         .style(Style::default().fg(Color::Green));
     f.render_widget(input, main_chunks[1]);
 
@@ -1085,7 +2319,9 @@ fn draw_code_input(f: &mut Frame, app: &App) {
     f.render_widget(status, chunks[2]);
 }
 
-fn draw_video_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, picker: Option<&Picker>, is_selected: bool) {
+fn draw_video_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, picker: Option<&Picker>, app: &App, is_selected: bool) {
+    let sender = sender_name(app, msg);
+    let align = app.config.media_align;
     let inner_area = Rect {
         x: area.x + 2,
         y: area.y,
@@ -1100,16 +2336,16 @@ fn draw_video_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &st
         // Метаданные на первой строке - выделяем желтым только при выборе
         let metadata_color = if is_selected { Color::Yellow } else { Color::White };
         let mut photo_lines = vec![
-        Line::from(format!("{} {}:", time, msg.from)).style(Style::default().fg(metadata_color)),
+        Line::from(format!("{}{}:", time, sender)).style(Style::default().fg(metadata_color)),
         ];
         let content_text = if let Some(is_round) = msg.video_is_round {
             if is_round {
-                "🔮 Круглое видео"
+                format!("{} Круглое видео", app.symbols.round_video)
             } else {
-                "🎬 Видео"
+                format!("{} Видео", app.symbols.video)
             }
         } else {
-            "🎬 Видео"
+            format!("{} Видео", app.symbols.video)
         };
         photo_lines.push(Line::from(content_text));
 
@@ -1127,28 +2363,27 @@ fn draw_video_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &st
 
         if let Some(preview_path) = &msg.video_preview_path {
             if let Some(picker) = picker {
-                match try_display_image(preview_path, picker, preview_area) {
-                    Ok(mut protocol) => {
-                        let image_widget = StatefulImage::new();
-                        f.render_stateful_widget(image_widget, preview_area, &mut protocol);
-                    }
+                match render_cached_image(f, preview_path, align_media_area(preview_area, align, time), picker, false, align == MediaAlign::Center) {
+                    Ok(()) => {}
                     Err(e) => {
-                        let error_text = format!("[🎬 Ошибка превью: {}]", e);
+                        let error_text = format!("[{} Ошибка превью: {}]", app.symbols.video, e);
                         let error_widget = Paragraph::new(error_text)
                             .style(Style::default().fg(Color::Red));
                         f.render_widget(error_widget, preview_area);
                     }
                 }
             } else {
-                let placeholder = Paragraph::new("[🎬 Терминал не поддерживает изображения]")
+                let placeholder = Paragraph::new(format!("[{} Терминал не поддерживает изображения]", app.symbols.video))
                     .style(Style::default().fg(Color::Yellow));
                 f.render_widget(placeholder, preview_area);
             }
         } else {
-            let placeholder = Paragraph::new("[🎬 Загрузка превью...]")
+            let placeholder = Paragraph::new(format!("[{} Загрузка превью...]", app.symbols.video))
                 .style(Style::default().fg(Color::Blue));
             f.render_widget(placeholder, preview_area);
         }
+
+        draw_media_caption(f, msg, inner_area);
     } else {
         // Если нет места для текста, показываем только превью видео
         let preview_area = Rect {
@@ -1160,25 +2395,22 @@ fn draw_video_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &st
 
         if let Some(preview_path) = &msg.video_preview_path {
             if let Some(picker) = picker {
-                match try_display_image(preview_path, picker, preview_area) {
-                    Ok(mut protocol) => {
-                        let image_widget = StatefulImage::new();
-                        f.render_stateful_widget(image_widget, preview_area, &mut protocol);
-                    }
+                match render_cached_image(f, preview_path, align_media_area(preview_area, align, time), picker, false, align == MediaAlign::Center) {
+                    Ok(()) => {}
                     Err(e) => {
-                        let error_text = format!("[🎬 Ошибка превью: {}]", e);
+                        let error_text = format!("[{} Ошибка превью: {}]", app.symbols.video, e);
                         let error_widget = Paragraph::new(error_text)
                             .style(Style::default().fg(Color::Red));
                         f.render_widget(error_widget, preview_area);
                     }
                 }
             } else {
-                let placeholder = Paragraph::new("[🎬 Терминал не поддерживает изображения]")
+                let placeholder = Paragraph::new(format!("[{} Терминал не поддерживает изображения]", app.symbols.video))
                     .style(Style::default().fg(Color::Yellow));
                 f.render_widget(placeholder, preview_area);
             }
         } else {
-            let placeholder = Paragraph::new("[🎬 Загрузка превью...]")
+            let placeholder = Paragraph::new(format!("[{} Загрузка превью...]", app.symbols.video))
                 .style(Style::default().fg(Color::Blue));
             f.render_widget(placeholder, preview_area);
         }
@@ -1188,7 +2420,9 @@ fn draw_video_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &st
     f.render_widget(message_block, area);
 }
 
-fn draw_sticker_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, picker: Option<&Picker>) {
+fn draw_sticker_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, picker: Option<&Picker>, app: &App) {
+    let sender = sender_name(app, msg);
+    let align = app.config.media_align;
     let inner_area = Rect {
         x: area.x + 2,
         y: area.y,
@@ -1199,8 +2433,8 @@ fn draw_sticker_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &
     // Проверяем, достаточно ли места для отображения текста сверху
     let has_space_for_text = inner_area.height > 1;
 
-    let _text_area = if has_space_for_text {
-        let text_content = format!("{} {}:", time, msg.from);
+    if has_space_for_text {
+        let text_content = format!("{}{}:", time, sender);
         let text_widget = Paragraph::new(text_content)
             .style(Style::default().fg(Color::Yellow));
         f.render_widget(text_widget, inner_area);
@@ -1211,7 +2445,7 @@ fn draw_sticker_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &
         x: inner_area.x,
         y: if has_space_for_text { inner_area.y + 1 } else { inner_area.y },
         width: inner_area.width,
-        height: if has_space_for_text { inner_area.height } else { inner_area.height },
+        height: inner_area.height,
     };
 
     if let Some(sticker_path) = &msg.sticker_path {
@@ -1239,36 +2473,33 @@ fn draw_sticker_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &
 
         if file_exists {
             if let Some(picker) = picker {
-                match try_display_image(&actual_path, picker, sticker_area) {
-                    Ok(mut protocol) => {
-                        let image_widget = StatefulImage::new();
-                        f.render_stateful_widget(image_widget, sticker_area, &mut protocol);
-                    }
+                match render_cached_image(f, &actual_path, align_media_area(sticker_area, align, time), picker, false, align == MediaAlign::Center) {
+                    Ok(()) => {}
                     Err(e) => {
-                        let error_text = format!("[🏷️ Ошибка стикера: {}]", e);
+                        let error_text = format!("[{} Ошибка стикера: {}]", app.symbols.sticker, e);
                         let error_widget = Paragraph::new(error_text)
                             .style(Style::default().fg(Color::Red));
                         f.render_widget(error_widget, sticker_area);
                     }
                 }
             } else {
-                let placeholder = Paragraph::new("[🏷️ Терминал не поддерживает изображения]")
+                let placeholder = Paragraph::new(format!("[{} Терминал не поддерживает изображения]", app.symbols.sticker))
                     .style(Style::default().fg(Color::Yellow));
                 f.render_widget(placeholder, sticker_area);
             }
         } else {
             // File doesn't exist, show a more helpful message
             let helpful_message = if sticker_path.contains("sticker") {
-                format!("[🏷️ Стикер не найден. Попробуйте обновить чат для повторной загрузки.]")
+                format!("[{} Стикер не найден. Попробуйте обновить чат для повторной загрузки.]", app.symbols.sticker)
             } else {
-                format!("[🏷️ Стикер не найден: {}]", sticker_path)
+                format!("[{} Стикер не найден: {}]", app.symbols.sticker, sticker_path)
             };
             let error_widget = Paragraph::new(helpful_message)
                 .style(Style::default().fg(Color::Yellow));
             f.render_widget(error_widget, sticker_area);
         }
     } else {
-        let placeholder = Paragraph::new("[🏷️ Загрузка стикера...]")
+        let placeholder = Paragraph::new(format!("[{} Загрузка стикера...]", app.symbols.sticker))
             .style(Style::default().fg(Color::Blue));
         f.render_widget(placeholder, sticker_area);
     }
@@ -1277,7 +2508,7 @@ fn draw_sticker_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &
     f.render_widget(message_block, area);
 }
 
-fn draw_location_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, is_selected: bool) {
+fn draw_location_message(f: &mut Frame, msg: &crate::Message, area: Rect, time: &str, sender: &str, is_selected: bool, symbols: &Symbols) {
     let inner_area = Rect {
         x: area.x + 2,
         y: area.y,
@@ -1308,10 +2539,10 @@ fn draw_location_message(f: &mut Frame, msg: &crate::Message, area: Rect, time:
     // Метаданные на первой строке - выделяем желтым только при выборе
     let metadata_color = if is_selected { Color::Yellow } else { Color::White };
     let mut location_lines = vec![
-        Line::from(format!("{} {}:", time, msg.from)).style(Style::default().fg(metadata_color)),
+        Line::from(format!("{}{}:", time, sender)).style(Style::default().fg(metadata_color)),
     ];
     // Контент на отдельных строках
-    location_lines.push(Line::from("📍 Местоположение").style(Style::default().fg(Color::Green)));
+    location_lines.push(Line::from(format!("{} Местоположение", symbols.location)).style(Style::default().fg(Color::Green)));
     location_lines.push(Line::from(coordinates_text).style(Style::default().fg(Color::Cyan)));
     location_lines.push(Line::from(title_text).style(Style::default().fg(Color::Cyan)));
     location_lines.push(Line::from(address_text).style(Style::default().fg(Color::Cyan)));
@@ -0,0 +1,46 @@
+// Мелкие функции разбора текста, не привязанные к состоянию приложения и отрисовке
+
+// Направление текста по первому сильному символу: буква письменности справа налево
+// (иврит, арабский, сирийский, тана, нко) или любая другая буква
+pub fn is_rtl_text(text: &str) -> bool {
+    text.chars()
+        .find(|c| c.is_alphabetic())
+        .is_some_and(|c| matches!(c as u32, 0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hebrew_is_rtl() {
+        assert!(is_rtl_text("שלום עולם"));
+        // Презентационные формы (лигатуры) тоже считаются
+        assert!(is_rtl_text("ﬠ"));
+    }
+
+    #[test]
+    fn arabic_is_rtl() {
+        assert!(is_rtl_text("مرحبا بالعالم"));
+        assert!(is_rtl_text("ﻣﺮﺣﺒﺎ"));
+    }
+
+    #[test]
+    fn mixed_direction_follows_first_letter() {
+        assert!(is_rtl_text("שלום hello"));
+        assert!(!is_rtl_text("hello שלום"));
+        assert!(is_rtl_text("مرحبا, привет"));
+        // Цифры, знаки и эмодзи в начале не решают направление
+        assert!(is_rtl_text("12:30 — 👍 שלום"));
+        assert!(!is_rtl_text("(1) Привет, مرحبا"));
+    }
+
+    #[test]
+    fn ltr_and_neutral_text_is_not_rtl() {
+        assert!(!is_rtl_text("Hello, world"));
+        assert!(!is_rtl_text("Привет, мир"));
+        assert!(!is_rtl_text("こんにちは"));
+        assert!(!is_rtl_text(""));
+        assert!(!is_rtl_text("123 !? 👍"));
+    }
+}